@@ -0,0 +1 @@
+CREATE INDEX mods_published ON mods (published DESC);
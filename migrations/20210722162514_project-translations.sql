@@ -0,0 +1,7 @@
+CREATE TABLE project_translations (
+    mod_id bigint REFERENCES mods ON UPDATE CASCADE NOT NULL,
+    locale varchar(32) NOT NULL,
+    title varchar(256) NOT NULL,
+    description varchar(2048) NOT NULL,
+    PRIMARY KEY (mod_id, locale)
+);
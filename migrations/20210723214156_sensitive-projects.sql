@@ -0,0 +1,2 @@
+ALTER TABLE mods
+    ADD COLUMN sensitive boolean NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE mods_gallery ADD COLUMN featured boolean NOT NULL DEFAULT FALSE;
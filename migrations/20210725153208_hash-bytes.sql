@@ -0,0 +1,17 @@
+-- Hashes were previously stored as the ASCII bytes of their hex encoding. Rows which
+-- aren't valid hex can't be decoded, so they are left untouched and reported instead
+-- of failing the whole migration.
+DO $$
+DECLARE
+    invalid_hashes bigint;
+BEGIN
+    SELECT COUNT(*) INTO invalid_hashes FROM hashes
+    WHERE encode(hash, 'escape') !~ '^([0-9a-fA-F]{2})+$';
+
+    IF invalid_hashes > 0 THEN
+        RAISE WARNING '% hashes are not valid hex and were left unchanged', invalid_hashes;
+    END IF;
+
+    UPDATE hashes SET hash = decode(convert_from(hash, 'UTF8'), 'hex')
+    WHERE encode(hash, 'escape') ~ '^([0-9a-fA-F]{2})+$';
+END $$;
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE versions ADD COLUMN featured_order integer NULL;
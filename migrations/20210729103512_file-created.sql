@@ -0,0 +1,8 @@
+-- Add migration script here
+ALTER TABLE files ADD COLUMN created timestamptz NOT NULL DEFAULT now();
+
+-- Existing files predate the column, so the version's publish date is the best estimate
+UPDATE files f
+SET created = v.date_published
+FROM versions v
+WHERE v.id = f.version_id;
@@ -0,0 +1,3 @@
+-- Add migration script here
+CREATE INDEX downloads_version_id_date ON downloads (version_id, date);
+CREATE INDEX downloads_identifier ON downloads (identifier);
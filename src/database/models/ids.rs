@@ -102,6 +102,47 @@ generate_ids!(
     NotificationId
 );
 
+/// Generates many unique notification IDs at once, checking them against the database
+/// in a single query per attempt rather than one query per ID
+pub async fn generate_notification_ids(
+    count: usize,
+    con: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Vec<NotificationId>, DatabaseError> {
+    let mut rng = rand::thread_rng();
+    let mut ids: Vec<i64> = Vec::with_capacity(count);
+    let mut retry_count = 0;
+
+    loop {
+        while ids.len() < count {
+            let id = random_base62_rng(&mut rng, 8) as i64;
+
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+
+        let taken = sqlx::query!("SELECT id FROM notifications WHERE id = ANY($1)", &ids[..])
+            .fetch_all(&mut *con)
+            .await?
+            .into_iter()
+            .map(|x| x.id)
+            .collect::<Vec<i64>>();
+
+        if taken.is_empty() {
+            break;
+        }
+
+        ids.retain(|id| !taken.contains(id));
+
+        retry_count += 1;
+        if retry_count > ID_RETRY_COUNT {
+            return Err(DatabaseError::RandomIdError);
+        }
+    }
+
+    Ok(ids.into_iter().map(NotificationId).collect())
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Type)]
 #[sqlx(transparent)]
 pub struct UserId(pub i64);
@@ -1,6 +1,10 @@
 use super::ids::*;
 use crate::database::models::DatabaseError;
 
+// The sizes of the title and text columns in the database
+const MAX_TITLE_LENGTH: usize = 255;
+const MAX_TEXT_LENGTH: usize = 2048;
+
 pub struct NotificationBuilder {
     pub notification_type: Option<String>,
     pub title: String,
@@ -34,6 +38,16 @@ pub struct NotificationAction {
     pub action_route: String,
 }
 
+/// Splits `users` into batches of at most `batch_size` recipients, leaving out everyone
+/// past the first `max_recipients`
+fn recipient_batches(
+    users: &[UserId],
+    max_recipients: usize,
+    batch_size: usize,
+) -> std::slice::Chunks<'_, UserId> {
+    users[..users.len().min(max_recipients)].chunks(batch_size.max(1))
+}
+
 impl NotificationBuilder {
     pub async fn insert(
         &self,
@@ -43,50 +57,139 @@ impl NotificationBuilder {
         self.insert_many(vec![user], transaction).await
     }
 
+    /// Inserts this notification for every user in `users`. Recipients are capped at
+    /// `NOTIFICATION_MAX_RECIPIENTS`, and the notifications are inserted in chunks of
+    /// `NOTIFICATION_BATCH_SIZE` so a large burst never turns into one huge statement.
     pub async fn insert_many(
         &self,
         users: Vec<UserId>,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<(), DatabaseError> {
-        for user in users {
-            let id = generate_notification_id(&mut *transaction).await?;
+        let max_recipients = dotenv::var("NOTIFICATION_MAX_RECIPIENTS")
+            .ok()
+            .and_then(|x| x.parse::<usize>().ok())
+            .unwrap_or(usize::MAX);
+        let batch_size = dotenv::var("NOTIFICATION_BATCH_SIZE")
+            .ok()
+            .and_then(|x| x.parse::<usize>().ok())
+            .unwrap_or(1000);
+
+        if users.len() > max_recipients {
+            log::warn!(
+                "Notification \"{}\" has {} recipients, only sending it to the first {}",
+                self.title,
+                users.len(),
+                max_recipients
+            );
+        }
 
-            let mut actions = Vec::new();
+        let (title, text) = truncate_fields(&self.title, &self.text);
+
+        for chunk in recipient_batches(&users, max_recipients, batch_size) {
+            let ids = generate_notification_ids(chunk.len(), &mut *transaction)
+                .await?
+                .into_iter()
+                .map(|x| x.0)
+                .collect::<Vec<i64>>();
+            let user_ids = chunk.iter().map(|x| x.0).collect::<Vec<i64>>();
+
+            sqlx::query!(
+                "
+                INSERT INTO notifications (
+                    id, user_id, title, text, link, type
+                )
+                SELECT n.id, n.user_id, $3, $4, $5, $6
+                FROM UNNEST($1::bigint[], $2::bigint[]) AS n(id, user_id)
+                ",
+                &ids[..],
+                &user_ids[..],
+                &title,
+                &text,
+                &self.link,
+                self.notification_type
+            )
+            .execute(&mut *transaction)
+            .await?;
 
             for action in &self.actions {
-                actions.push(NotificationAction {
-                    id: NotificationActionId(0),
-                    notification_id: id,
-                    title: action.title.clone(),
-                    action_route_method: action.action_route.0.clone(),
-                    action_route: action.action_route.1.clone(),
-                })
+                sqlx::query!(
+                    "
+                    INSERT INTO notifications_actions (
+                        notification_id, title, action_route, action_route_method
+                    )
+                    SELECT n.id, $2, $3, $4
+                    FROM UNNEST($1::bigint[]) AS n(id)
+                    ",
+                    &ids[..],
+                    &action.title,
+                    &action.action_route.1,
+                    &action.action_route.0
+                )
+                .execute(&mut *transaction)
+                .await?;
             }
-
-            Notification {
-                id,
-                user_id: user,
-                notification_type: self.notification_type.clone(),
-                title: self.title.clone(),
-                text: self.text.clone(),
-                link: self.link.clone(),
-                read: false,
-                created: chrono::Utc::now(),
-                actions,
-            }
-            .insert(&mut *transaction)
-            .await?;
         }
 
         Ok(())
     }
 }
 
+/// Gets the configured maximum length of a notification field, which can't exceed
+/// the size of its column
+fn max_length(var: &str, column_length: usize) -> usize {
+    dotenv::var(var)
+        .ok()
+        .and_then(|x| x.parse::<usize>().ok())
+        .map(|x| std::cmp::min(x, column_length))
+        .unwrap_or(column_length)
+}
+
+/// Picks the notifications of `notification_type` out of `notifications`, or all of them
+/// when no type is given
+fn notifications_of_type(
+    notifications: Vec<(NotificationId, Option<String>)>,
+    notification_type: Option<&str>,
+) -> Vec<NotificationId> {
+    notifications
+        .into_iter()
+        .filter(|(_, type_)| notification_type.is_none() || type_.as_deref() == notification_type)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Cuts a string down to `max` characters, marking it with an ellipsis if it was truncated
+fn truncate_with_ellipsis(string: &str, max: usize) -> String {
+    if string.chars().count() <= max {
+        return string.to_string();
+    }
+
+    let mut truncated: String = string.chars().take(max.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Notifications are generated by the server, so overly long ones are truncated
+/// instead of being rejected
+fn truncate_fields(title: &str, text: &str) -> (String, String) {
+    (
+        truncate_with_ellipsis(
+            title,
+            max_length("NOTIFICATION_TITLE_MAX_LENGTH", MAX_TITLE_LENGTH),
+        ),
+        truncate_with_ellipsis(
+            text,
+            max_length("NOTIFICATION_TEXT_MAX_LENGTH", MAX_TEXT_LENGTH),
+        ),
+    )
+}
+
 impl Notification {
     pub async fn insert(
         &self,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     ) -> Result<(), sqlx::error::Error> {
+        let (title, text) = truncate_fields(&self.title, &self.text);
+
         sqlx::query!(
             "
             INSERT INTO notifications (
@@ -98,8 +201,8 @@ impl Notification {
             ",
             self.id as NotificationId,
             self.user_id as UserId,
-            &self.title,
-            &self.text,
+            &title,
+            &text,
             &self.link,
             self.notification_type
         )
@@ -283,6 +386,75 @@ impl Notification {
         .await
     }
 
+    pub async fn read<'a, E>(id: NotificationId, exec: E) -> Result<(), sqlx::Error>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        sqlx::query!(
+            "
+            UPDATE notifications
+            SET read = TRUE
+            WHERE id = $1
+            ",
+            id as NotificationId,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Marks all unread notifications of a user as read, optionally only those of a single
+    /// type, returning how many were changed
+    pub async fn read_many_user<'a, E>(
+        user_id: UserId,
+        notification_type: Option<&str>,
+        exec: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres> + Copy,
+    {
+        use futures::TryStreamExt;
+        use sqlx::Done;
+
+        let unread = sqlx::query!(
+            "
+            SELECT id, type notification_type FROM notifications
+            WHERE user_id = $1 AND read = FALSE
+            ",
+            user_id as UserId,
+        )
+        .fetch_many(exec)
+        .try_filter_map(|e| async {
+            Ok(e.right()
+                .map(|x| (NotificationId(x.id), x.notification_type)))
+        })
+        .try_collect::<Vec<(NotificationId, Option<String>)>>()
+        .await?;
+
+        let notification_ids: Vec<i64> = notifications_of_type(unread, notification_type)
+            .into_iter()
+            .map(|x| x.0)
+            .collect();
+
+        if notification_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let result = sqlx::query!(
+            "
+            UPDATE notifications
+            SET read = TRUE
+            WHERE id IN (SELECT * FROM UNNEST($1::bigint[])) AND read = FALSE
+            ",
+            &notification_ids,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     pub async fn remove(
         id: NotificationId,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
@@ -365,3 +537,70 @@ impl NotificationAction {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{notifications_of_type, recipient_batches, truncate_with_ellipsis, UserId};
+    use crate::database::models::ids::NotificationId;
+
+    #[test]
+    fn short_strings_are_unchanged() {
+        assert_eq!(truncate_with_ellipsis("Hello", 5), "Hello");
+        assert_eq!(truncate_with_ellipsis("", 0), "");
+    }
+
+    #[test]
+    fn long_strings_end_with_an_ellipsis() {
+        let truncated = truncate_with_ellipsis("Hello world", 6);
+
+        assert_eq!(truncated, "Hello…");
+        assert_eq!(truncated.chars().count(), 6);
+    }
+
+    #[test]
+    fn truncates_by_characters_instead_of_bytes() {
+        assert_eq!(truncate_with_ellipsis("ééééé", 3), "éé…");
+    }
+
+    #[test]
+    fn recipients_are_capped_and_batched() {
+        let users = (0..2500).map(UserId).collect::<Vec<_>>();
+
+        let batches = recipient_batches(&users, 2100, 1000)
+            .map(|x| x.len())
+            .collect::<Vec<_>>();
+        assert_eq!(batches, vec![1000, 1000, 100]);
+
+        let batches = recipient_batches(&users, usize::MAX, 1000).collect::<Vec<_>>();
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[2].last().map(|x| x.0), Some(2499));
+
+        assert_eq!(recipient_batches(&users, 0, 1000).count(), 0);
+        assert_eq!(recipient_batches(&users[..3], 10, 0).count(), 3);
+    }
+
+    fn unread() -> Vec<(NotificationId, Option<String>)> {
+        vec![
+            (NotificationId(1), Some("project_update".to_string())),
+            (NotificationId(2), Some("team_invite".to_string())),
+            (NotificationId(3), Some("project_update".to_string())),
+            (NotificationId(4), None),
+        ]
+    }
+
+    #[test]
+    fn reading_one_type_leaves_other_types_unread() {
+        let read = notifications_of_type(unread(), Some("project_update"))
+            .into_iter()
+            .map(|x| x.0)
+            .collect::<Vec<_>>();
+
+        assert_eq!(read, vec![1, 3]);
+    }
+
+    #[test]
+    fn reading_without_a_type_reads_everything() {
+        assert_eq!(notifications_of_type(unread(), None).len(), 4);
+        assert!(notifications_of_type(unread(), Some("moderator_message")).is_empty());
+    }
+}
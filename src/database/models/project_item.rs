@@ -38,6 +38,7 @@ impl DonationUrl {
 pub struct GalleryItem {
     pub project_id: ProjectId,
     pub image_url: String,
+    pub featured: bool,
 }
 
 impl GalleryItem {
@@ -48,20 +49,132 @@ impl GalleryItem {
         sqlx::query!(
             "
             INSERT INTO mods_gallery (
-                mod_id, image_url
+                mod_id, image_url, featured
             )
             VALUES (
-                $1, $2
+                $1, $2, $3
             )
             ",
             self.project_id as ProjectId,
             self.image_url,
+            self.featured,
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Parses the aggregated gallery image URLs of a project, marking the featured ones
+    fn parse(
+        project_id: ProjectId,
+        gallery: Option<String>,
+        featured: Option<String>,
+    ) -> Vec<Self> {
+        let featured = featured.unwrap_or_default();
+
+        gallery
+            .unwrap_or_default()
+            .split(',')
+            .filter(|x| !x.is_empty())
+            .map(|x| GalleryItem {
+                project_id,
+                image_url: x.to_string(),
+                featured: featured.split(',').any(|y| y == x),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ProjectTranslation {
+    pub project_id: ProjectId,
+    pub locale: String,
+    pub title: String,
+    pub description: String,
+}
+
+impl ProjectTranslation {
+    pub async fn insert(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), sqlx::error::Error> {
+        sqlx::query!(
+            "
+            INSERT INTO project_translations (
+                mod_id, locale, title, description
+            )
+            VALUES (
+                $1, $2, $3, $4
+            )
+            ON CONFLICT (mod_id, locale) DO UPDATE
+            SET title = EXCLUDED.title, description = EXCLUDED.description
+            ",
+            self.project_id as ProjectId,
+            self.locale,
+            self.title,
+            self.description,
         )
         .execute(&mut *transaction)
         .await?;
 
         Ok(())
     }
+
+    pub async fn get_project<'a, E>(
+        project_id: ProjectId,
+        exec: E,
+    ) -> Result<Vec<ProjectTranslation>, sqlx::Error>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        use futures::stream::TryStreamExt;
+
+        sqlx::query!(
+            "
+            SELECT locale, title, description
+            FROM project_translations
+            WHERE mod_id = $1
+            ",
+            project_id as ProjectId,
+        )
+        .fetch_many(exec)
+        .try_filter_map(|e| async {
+            Ok(e.right().map(|x| ProjectTranslation {
+                project_id,
+                locale: x.locale,
+                title: x.title,
+                description: x.description,
+            }))
+        })
+        .try_collect::<Vec<ProjectTranslation>>()
+        .await
+    }
+
+    pub async fn remove(
+        project_id: ProjectId,
+        locale: &str,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<Option<()>, sqlx::error::Error> {
+        use sqlx::Done;
+
+        let result = sqlx::query!(
+            "
+            DELETE FROM project_translations
+            WHERE mod_id = $1 AND locale = $2
+            ",
+            project_id as ProjectId,
+            locale,
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(()))
+        }
+    }
 }
 
 pub struct ProjectBuilder {
@@ -118,6 +231,7 @@ impl ProjectBuilder {
             slug: self.slug,
             rejection_reason: None,
             rejection_body: None,
+            sensitive: false,
         };
         project_struct.insert(&mut *transaction).await?;
 
@@ -178,6 +292,7 @@ pub struct Project {
     pub slug: Option<String>,
     pub rejection_reason: Option<String>,
     pub rejection_body: Option<String>,
+    pub sensitive: bool,
 }
 
 impl Project {
@@ -242,7 +357,7 @@ impl Project {
                    updated, status,
                    issues_url, source_url, wiki_url, discord_url, license_url,
                    team_id, client_side, server_side, license, slug,
-                   rejection_reason, rejection_body
+                   rejection_reason, rejection_body, sensitive
             FROM mods
             WHERE id = $1
             ",
@@ -277,6 +392,7 @@ impl Project {
                 follows: row.follows,
                 rejection_reason: row.rejection_reason,
                 rejection_body: row.rejection_body,
+                sensitive: row.sensitive,
             }))
         } else {
             Ok(None)
@@ -300,7 +416,7 @@ impl Project {
                    updated, status,
                    issues_url, source_url, wiki_url, discord_url, license_url,
                    team_id, client_side, server_side, license, slug,
-                   rejection_reason, rejection_body
+                   rejection_reason, rejection_body, sensitive
             FROM mods
             WHERE id IN (SELECT * FROM UNNEST($1::bigint[]))
             ",
@@ -333,6 +449,7 @@ impl Project {
                 follows: m.follows,
                 rejection_reason: m.rejection_reason,
                 rejection_body: m.rejection_body,
+                sensitive: m.sensitive,
             }))
         })
         .try_collect::<Vec<Project>>()
@@ -380,6 +497,16 @@ impl Project {
         .execute(&mut *transaction)
         .await?;
 
+        sqlx::query!(
+            "
+            DELETE FROM project_translations
+            WHERE mod_id = $1
+            ",
+            id as ProjectId
+        )
+        .execute(&mut *transaction)
+        .await?;
+
         sqlx::query!(
             "
             DELETE FROM mod_follows
@@ -589,9 +716,9 @@ impl Project {
             m.icon_url icon_url, m.body body, m.body_url body_url, m.published published,
             m.updated updated, m.status status,
             m.issues_url issues_url, m.source_url source_url, m.wiki_url wiki_url, m.discord_url discord_url, m.license_url license_url,
-            m.team_id team_id, m.client_side client_side, m.server_side server_side, m.license license, m.slug slug, m.rejection_reason rejection_reason, m.rejection_body rejection_body,
+            m.team_id team_id, m.client_side client_side, m.server_side server_side, m.license license, m.slug slug, m.rejection_reason rejection_reason, m.rejection_body rejection_body, m.sensitive sensitive,
             s.status status_name, cs.name client_side_type, ss.name server_side_type, l.short short, l.name license_name, pt.name project_type_name,
-            STRING_AGG(DISTINCT c.category, ',') categories, STRING_AGG(DISTINCT v.id::text, ',') versions, STRING_AGG(DISTINCT mg.image_url, ',') gallery,
+            STRING_AGG(DISTINCT c.category, ',') categories, STRING_AGG(DISTINCT v.id::text, ',') versions, STRING_AGG(DISTINCT mg.image_url, ',') gallery, STRING_AGG(DISTINCT mg.image_url, ',') FILTER (WHERE mg.featured = TRUE) featured_gallery,
             STRING_AGG(DISTINCT md.joining_platform_id || ', ' || md.url || ', ' || dp.short || ', ' || dp.name, ' ,') donations
             FROM mods m
             LEFT OUTER JOIN mods_categories mc ON joining_mod_id = m.id
@@ -640,6 +767,7 @@ impl Project {
                     follows: m.follows,
                     rejection_reason: m.rejection_reason,
                     rejection_body: m.rejection_body,
+                    sensitive: m.sensitive,
                 },
                 project_type: m.project_type_name,
                 categories: m
@@ -675,14 +803,7 @@ impl Project {
                     })
                     .flatten()
                     .collect(),
-                gallery_items: m
-                    .gallery
-                    .into_iter()
-                    .map(|x| GalleryItem {
-                        project_id: id,
-                        image_url: x,
-                    })
-                    .collect(),
+                gallery_items: GalleryItem::parse(id, m.gallery, m.featured_gallery),
                 status: crate::models::projects::ProjectStatus::from_str(&m.status_name),
                 license_id: m.short,
                 license_name: m.license_name,
@@ -710,9 +831,9 @@ impl Project {
             m.icon_url icon_url, m.body body, m.body_url body_url, m.published published,
             m.updated updated, m.status status,
             m.issues_url issues_url, m.source_url source_url, m.wiki_url wiki_url, m.discord_url discord_url, m.license_url license_url,
-            m.team_id team_id, m.client_side client_side, m.server_side server_side, m.license license, m.slug slug, m.rejection_reason rejection_reason, m.rejection_body rejection_body,
+            m.team_id team_id, m.client_side client_side, m.server_side server_side, m.license license, m.slug slug, m.rejection_reason rejection_reason, m.rejection_body rejection_body, m.sensitive sensitive,
             s.status status_name, cs.name client_side_type, ss.name server_side_type, l.short short, l.name license_name, pt.name project_type_name,
-            STRING_AGG(DISTINCT c.category, ',') categories, STRING_AGG(DISTINCT v.id::text, ',') versions, STRING_AGG(DISTINCT mg.image_url, ',') gallery,
+            STRING_AGG(DISTINCT c.category, ',') categories, STRING_AGG(DISTINCT v.id::text, ',') versions, STRING_AGG(DISTINCT mg.image_url, ',') gallery, STRING_AGG(DISTINCT mg.image_url, ',') FILTER (WHERE mg.featured = TRUE) featured_gallery,
             STRING_AGG(DISTINCT md.joining_platform_id || ', ' || md.url || ', ' || dp.short || ', ' || dp.name, ' ,') donations
             FROM mods m
             LEFT OUTER JOIN mods_categories mc ON joining_mod_id = m.id
@@ -761,6 +882,7 @@ impl Project {
                         follows: m.follows,
                         rejection_reason: m.rejection_reason,
                         rejection_body: m.rejection_body,
+                        sensitive: m.sensitive,
                     },
                     project_type: m.project_type_name,
                     categories: m.categories.unwrap_or_default().split(',').map(|x| x.to_string()).collect(),
@@ -786,10 +908,7 @@ impl Project {
                             })
                             .flatten()
                             .collect(),
-                    gallery_items: m.gallery.iter().map(|x| GalleryItem {
-                        project_id:  ProjectId(id),
-                        image_url: x.to_string()
-                    }).collect(),
+                    gallery_items: GalleryItem::parse(ProjectId(id), m.gallery, m.featured_gallery),
                     status: crate::models::projects::ProjectStatus::from_str(&m.status_name),
                     license_id: m.short,
                     license_name: m.license_name,
@@ -815,3 +934,49 @@ pub struct QueryProject {
     pub client_side: crate::models::projects::SideType,
     pub server_side: crate::models::projects::SideType,
 }
+
+#[cfg(test)]
+impl QueryProject {
+    /// An approved project for tests, which they change to fit what they check
+    pub fn example(id: i64) -> QueryProject {
+        QueryProject {
+            inner: Project {
+                id: ProjectId(id),
+                project_type: ProjectTypeId(1),
+                team_id: TeamId(2000),
+                title: "Example".to_string(),
+                description: "An example project".to_string(),
+                body: "The body".to_string(),
+                body_url: None,
+                published: chrono::Utc::now(),
+                updated: chrono::Utc::now(),
+                status: StatusId(1),
+                downloads: 100,
+                follows: 10,
+                icon_url: Some("https://cdn.example.com/data/icon.png".to_string()),
+                issues_url: Some("https://example.com/issues".to_string()),
+                source_url: None,
+                wiki_url: None,
+                license_url: None,
+                discord_url: None,
+                client_side: SideTypeId(1),
+                server_side: SideTypeId(2),
+                license: LicenseId(3),
+                slug: None,
+                rejection_reason: None,
+                rejection_body: None,
+                sensitive: false,
+            },
+            project_type: "mod".to_string(),
+            categories: vec!["magic".to_string()],
+            versions: vec![VersionId(3000)],
+            donation_urls: vec![],
+            gallery_items: vec![],
+            status: crate::models::projects::ProjectStatus::Approved,
+            license_id: "mit".to_string(),
+            license_name: "MIT".to_string(),
+            client_side: crate::models::projects::SideType::Required,
+            server_side: crate::models::projects::SideType::Optional,
+        }
+    }
+}
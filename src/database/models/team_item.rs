@@ -144,6 +144,23 @@ impl TeamMember {
         id: TeamId,
         executor: E,
     ) -> Result<Vec<QueryTeamMember>, super::DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        Self::get_page_from_team_full(id, true, None, 0, executor).await
+    }
+
+    /// Gets a page of a team's members ordered by their ids, which keeps pages stable but
+    /// isn't the order they were added in since ids are random. Members who haven't accepted
+    /// their invite are left out unless `include_pending` is set.
+    /// Every member after `offset` is returned if `limit` is `None`.
+    pub async fn get_page_from_team_full<'a, 'b, E>(
+        id: TeamId,
+        include_pending: bool,
+        limit: Option<i64>,
+        offset: i64,
+        executor: E,
+    ) -> Result<Vec<QueryTeamMember>, super::DatabaseError>
     where
         E: sqlx::Executor<'a, Database = sqlx::Postgres>,
     {
@@ -157,9 +174,14 @@ impl TeamMember {
             u.created created, u.role user_role
             FROM team_members tm
             INNER JOIN users u ON u.id = tm.user_id
-            WHERE tm.team_id = $1
+            WHERE tm.team_id = $1 AND ($2 OR tm.accepted = TRUE)
+            ORDER BY tm.id
+            LIMIT $3 OFFSET $4
             ",
             id as TeamId,
+            include_pending,
+            limit,
+            offset,
         )
         .fetch_many(executor)
         .try_filter_map(|e| async {
@@ -486,6 +508,64 @@ impl TeamMember {
         Ok(())
     }
 
+    /// Hands ownership of a team to another member, giving the current owner the
+    /// `demoted_role` role and `demoted_permissions` instead
+    pub async fn transfer_ownership(
+        id: TeamId,
+        current_owner_id: UserId,
+        new_owner_id: UserId,
+        demoted_role: String,
+        demoted_permissions: Permissions,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), super::DatabaseError> {
+        use sqlx::Done;
+
+        let demoted = sqlx::query!(
+            "
+            UPDATE team_members
+            SET role = $1, permissions = $2
+            WHERE (team_id = $3 AND user_id = $4 AND role = $5)
+            ",
+            demoted_role,
+            demoted_permissions.bits() as i64,
+            id as TeamId,
+            current_owner_id as UserId,
+            crate::models::teams::OWNER_ROLE,
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        if demoted.rows_affected() != 1 {
+            return Err(super::DatabaseError::Other(format!(
+                "Demoting the owner failed; {} rows updated",
+                demoted.rows_affected()
+            )));
+        }
+
+        let promoted = sqlx::query!(
+            "
+            UPDATE team_members
+            SET role = $1, permissions = $2
+            WHERE (team_id = $3 AND user_id = $4 AND accepted = TRUE)
+            ",
+            crate::models::teams::OWNER_ROLE,
+            Permissions::ALL.bits() as i64,
+            id as TeamId,
+            new_owner_id as UserId,
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        if promoted.rows_affected() != 1 {
+            return Err(super::DatabaseError::Other(format!(
+                "Promoting the new owner failed; {} rows updated",
+                promoted.rows_affected()
+            )));
+        }
+
+        Ok(())
+    }
+
     pub async fn get_from_user_id_project<'a, 'b, E>(
         id: ProjectId,
         user_id: UserId,
@@ -64,6 +64,52 @@ impl DependencyBuilder {
 
         Ok(())
     }
+
+    /// Gets the first dependency on a specific version that doesn't exist, if any
+    pub async fn find_missing_version<'a, E>(
+        dependencies: &[DependencyBuilder],
+        executor: E,
+    ) -> Result<Option<VersionId>, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+    {
+        let version_ids: Vec<i64> = dependencies
+            .iter()
+            .filter_map(|x| x.version_id)
+            .map(|x| x.0)
+            .collect();
+
+        if version_ids.is_empty() {
+            return Ok(None);
+        }
+
+        let existing: Vec<i64> = sqlx::query!(
+            "
+            SELECT id FROM versions
+            WHERE id = ANY($1)
+            ",
+            &version_ids
+        )
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+
+        Ok(Self::first_missing_version(dependencies, &existing))
+    }
+
+    /// Gets the first dependency on a specific version which isn't one of the `existing`
+    /// versions, if any
+    fn first_missing_version(
+        dependencies: &[DependencyBuilder],
+        existing: &[i64],
+    ) -> Option<VersionId> {
+        dependencies
+            .iter()
+            .filter_map(|x| x.version_id)
+            .find(|x| !existing.contains(&x.0))
+    }
 }
 
 pub struct VersionFileBuilder {
@@ -71,6 +117,8 @@ pub struct VersionFileBuilder {
     pub filename: String,
     pub hashes: Vec<HashBuilder>,
     pub primary: bool,
+    pub size: Option<i64>,
+    pub file_type: Option<String>,
 }
 
 impl VersionFileBuilder {
@@ -83,13 +131,15 @@ impl VersionFileBuilder {
 
         sqlx::query!(
             "
-            INSERT INTO files (id, version_id, url, filename)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO files (id, version_id, url, filename, size, file_type)
+            VALUES ($1, $2, $3, $4, $5, $6)
             ",
             file_id as FileId,
             version_id as VersionId,
             self.url,
             self.filename,
+            self.size,
+            self.file_type,
         )
         .execute(&mut *transaction)
         .await?;
@@ -475,10 +525,16 @@ impl Version {
         Ok(Some(()))
     }
 
+    /// Gets the versions of a project matching the given game versions, loaders and featured
+    /// state, newest first. Featured versions are returned in the order they were featured
+    /// in. Without a limit, every matching version is returned.
     pub async fn get_project_versions<'a, E>(
         project_id: ProjectId,
         game_versions: Option<Vec<String>>,
         loaders: Option<Vec<String>>,
+        featured: Option<bool>,
+        limit: Option<i64>,
+        offset: Option<i64>,
         exec: E,
     ) -> Result<Vec<VersionId>, sqlx::Error>
     where
@@ -489,18 +545,23 @@ impl Version {
         let vec = sqlx::query!(
             "
             SELECT version.id FROM (
-                SELECT DISTINCT ON(v.id) v.id, v.date_published FROM versions v
+                SELECT DISTINCT ON(v.id) v.id, v.date_published, v.featured_order FROM versions v
                 INNER JOIN game_versions_versions gvv ON gvv.joining_version_id = v.id
                 INNER JOIN game_versions gv on gvv.game_version_id = gv.id AND (cardinality($2::varchar[]) = 0 OR gv.version = ANY($2::varchar[]))
                 INNER JOIN loaders_versions lv ON lv.version_id = v.id
                 INNER JOIN loaders l on lv.loader_id = l.id AND (cardinality($3::varchar[]) = 0 OR l.loader = ANY($3::varchar[]))
-                WHERE v.mod_id = $1
+                WHERE v.mod_id = $1 AND ($4::bool IS NULL OR v.featured = $4)
             ) AS version
-            ORDER BY version.date_published ASC
+            ORDER BY CASE WHEN $4 THEN version.featured_order END ASC NULLS LAST,
+                version.date_published DESC, version.id DESC
+            LIMIT $5 OFFSET $6
             ",
             project_id as ProjectId,
             &game_versions.unwrap_or_default(),
             &loaders.unwrap_or_default(),
+            featured,
+            limit,
+            offset.unwrap_or(0),
         )
         .fetch_many(exec)
         .try_filter_map(|e| async { Ok(e.right().map(|v| VersionId(v.id))) })
@@ -605,8 +666,8 @@ impl Version {
             v.changelog changelog, v.changelog_url changelog_url, v.date_published date_published, v.downloads downloads,
             rc.channel release_channel, v.featured featured,
             STRING_AGG(DISTINCT gv.version, ',') game_versions, STRING_AGG(DISTINCT l.loader, ',') loaders,
-            STRING_AGG(DISTINCT f.id || ', ' || f.filename || ', ' || f.is_primary || ', ' || f.url, ' ,') files,
-            STRING_AGG(DISTINCT h.algorithm || ', ' || encode(h.hash, 'escape') || ', ' || h.file_id,  ' ,') hashes,
+            (JSONB_AGG(DISTINCT JSONB_BUILD_OBJECT('id', f.id, 'filename', f.filename, 'primary', f.is_primary, 'size', f.size, 'file_type', f.file_type, 'url', f.url)) FILTER (WHERE f.id IS NOT NULL))::text files,
+            STRING_AGG(DISTINCT h.algorithm || ', ' || encode(h.hash, 'hex') || ', ' || h.file_id,  ' ,') hashes,
             STRING_AGG(DISTINCT COALESCE(d.dependency_id, 0) || ', ' || COALESCE(d.mod_dependency_id, 0) || ', ' || d.dependency_type,  ' ,') dependencies
             FROM versions v
             INNER JOIN release_channels rc on v.release_channel = rc.id
@@ -637,7 +698,7 @@ impl Version {
                         Some((
                             FileId(hash[2].parse().unwrap_or(0)),
                             hash[0].to_string(),
-                            hash[1].to_string().into_bytes(),
+                            hex::decode(hash[1]).unwrap_or_default(),
                         ))
                     } else {
                         None
@@ -657,36 +718,7 @@ impl Version {
                 date_published: v.date_published,
                 downloads: v.downloads,
                 release_channel: v.release_channel,
-                files: v
-                    .files
-                    .unwrap_or_default()
-                    .split(" ,")
-                    .map(|f| {
-                        let file: Vec<&str> = f.split(", ").collect();
-
-                        if file.len() >= 4 {
-                            let file_id = FileId(file[0].parse().unwrap_or(0));
-                            let mut file_hashes = HashMap::new();
-
-                            for hash in &hashes {
-                                if (hash.0).0 == file_id.0 {
-                                    file_hashes.insert(hash.1.clone(), hash.2.clone());
-                                }
-                            }
-
-                            Some(QueryFile {
-                                id: file_id,
-                                url: file[3].to_string(),
-                                filename: file[1].to_string(),
-                                hashes: file_hashes,
-                                primary: file[2].parse().unwrap_or(false),
-                            })
-                        } else {
-                            None
-                        }
-                    })
-                    .flatten()
-                    .collect(),
+                files: parse_files(v.files, &hashes)?,
                 game_versions: v
                     .game_versions
                     .unwrap_or_default()
@@ -709,9 +741,9 @@ impl Version {
 
                         if dependency.len() >= 3 {
                             Some(QueryDependency {
-                                project_id: match &*dependency[2] {
+                                project_id: match &*dependency[1] {
                                     "0" => None,
-                                    _ => match dependency[2].parse() {
+                                    _ => match dependency[1].parse() {
                                         Ok(x) => Some(ProjectId(x)),
                                         Err(_) => None,
                                     },
@@ -723,7 +755,7 @@ impl Version {
                                         Err(_) => None,
                                     },
                                 },
-                                dependency_type: dependency[1].to_string(),
+                                dependency_type: dependency[2].to_string(),
                             })
                         } else {
                             None
@@ -753,8 +785,8 @@ impl Version {
             v.changelog changelog, v.changelog_url changelog_url, v.date_published date_published, v.downloads downloads,
             rc.channel release_channel, v.featured featured,
             STRING_AGG(DISTINCT gv.version, ',') game_versions, STRING_AGG(DISTINCT l.loader, ',') loaders,
-            STRING_AGG(DISTINCT f.id || ', ' || f.filename || ', ' || f.is_primary || ', ' || f.url, ' ,') files,
-            STRING_AGG(DISTINCT h.algorithm || ', ' || encode(h.hash, 'escape') || ', ' || h.file_id,  ' ,') hashes,
+            (JSONB_AGG(DISTINCT JSONB_BUILD_OBJECT('id', f.id, 'filename', f.filename, 'primary', f.is_primary, 'size', f.size, 'file_type', f.file_type, 'url', f.url)) FILTER (WHERE f.id IS NOT NULL))::text files,
+            STRING_AGG(DISTINCT h.algorithm || ', ' || encode(h.hash, 'hex') || ', ' || h.file_id,  ' ,') hashes,
             STRING_AGG(DISTINCT COALESCE(d.dependency_id, 0) || ', ' || COALESCE(d.mod_dependency_id, 0) || ', ' || d.dependency_type,  ' ,') dependencies
             FROM versions v
             INNER JOIN release_channels rc on v.release_channel = rc.id
@@ -773,7 +805,7 @@ impl Version {
         )
             .fetch_many(exec)
             .try_filter_map(|e| async {
-                Ok(e.right().map(|v| {
+                e.right().map(|v| -> Result<QueryVersion, sqlx::Error> {
                     let hashes: Vec<(FileId, String, Vec<u8>)> = v.hashes.unwrap_or_default().split(" ,").map(|f| {
                         let hash: Vec<&str> = f.split(", ").collect();
 
@@ -781,14 +813,14 @@ impl Version {
                             Some((
                                 FileId(hash[2].parse().unwrap_or(0)),
                                 hash[0].to_string(),
-                                hash[1].to_string().into_bytes(),
+                                hex::decode(hash[1]).unwrap_or_default(),
                             ))
                         } else {
                             None
                         }
                     }).flatten().collect();
 
-                    QueryVersion {
+                    Ok(QueryVersion {
                         id: VersionId(v.id),
                         project_id: ProjectId(v.mod_id),
                         author_id: UserId(v.author_id),
@@ -799,30 +831,7 @@ impl Version {
                         date_published: v.date_published,
                         downloads: v.downloads,
                         release_channel: v.release_channel,
-                        files: v.files.unwrap_or_default().split(" ,").map(|f| {
-                            let file: Vec<&str> = f.split(", ").collect();
-
-                            if file.len() >= 4 {
-                                let file_id = FileId(file[0].parse().unwrap_or(0));
-                                let mut file_hashes = HashMap::new();
-
-                                for hash in &hashes {
-                                    if (hash.0).0 == file_id.0 {
-                                        file_hashes.insert(hash.1.clone(), hash.2.clone());
-                                    }
-                                }
-
-                                Some(QueryFile {
-                                    id: file_id,
-                                    url: file[3].to_string(),
-                                    filename: file[1].to_string(),
-                                    hashes: file_hashes,
-                                    primary: file[2].parse().unwrap_or(false),
-                                })
-                            } else {
-                                None
-                            }
-                        }).flatten().collect(),
+                        files: parse_files(v.files, &hashes)?,
                         game_versions: v.game_versions.unwrap_or_default().split(',').map(|x| x.to_string()).collect(),
                         loaders: v.loaders.unwrap_or_default().split(',').map(|x| x.to_string()).collect(),
                         featured: v.featured,
@@ -834,9 +843,9 @@ impl Version {
 
                                 if dependency.len() >= 3 {
                                     Some(QueryDependency {
-                                        project_id: match &*dependency[2] {
+                                        project_id: match &*dependency[1] {
                                             "0" => None,
-                                            _ => match dependency[2].parse() {
+                                            _ => match dependency[1].parse() {
                                                 Ok(x) => Some(ProjectId(x)),
                                                 Err(_) => None,
                                             },
@@ -848,14 +857,14 @@ impl Version {
                                                 Err(_) => None,
                                             },
                                         },
-                                        dependency_type: dependency[1].to_string(),
+                                        dependency_type: dependency[2].to_string(),
                                     })
                                 } else {
                                     None
                                 }
                             }).flatten().collect(),
-                    }
-                }))
+                    })
+                }).transpose()
             })
             .try_collect::<Vec<QueryVersion>>()
             .await
@@ -915,4 +924,111 @@ pub struct QueryFile {
     pub filename: String,
     pub hashes: HashMap<String, Vec<u8>>,
     pub primary: bool,
+    pub size: Option<i64>,
+    pub file_type: Option<String>,
+}
+
+/// A file as aggregated to JSON by `Version::get_full` and `Version::get_many_full`, which
+/// unlike a comma separated aggregate can't be thrown off by a comma in the filename
+#[derive(serde::Deserialize)]
+struct FileRow {
+    id: i64,
+    url: String,
+    filename: String,
+    primary: bool,
+    size: Option<i64>,
+    file_type: Option<String>,
+}
+
+/// Parses the files aggregated by a version query. Malformed JSON fails to decode instead
+/// of making the version look like it has no files.
+fn parse_files(
+    files: Option<String>,
+    hashes: &[(FileId, String, Vec<u8>)],
+) -> Result<Vec<QueryFile>, sqlx::Error> {
+    let rows: Vec<FileRow> = match files {
+        Some(files) => {
+            serde_json::from_str(&files).map_err(|err| sqlx::Error::Decode(Box::new(err)))?
+        }
+        None => Vec::new(),
+    };
+
+    Ok(rows
+        .into_iter()
+        .map(|row| QueryFile {
+            id: FileId(row.id),
+            url: row.url,
+            filename: row.filename,
+            hashes: hashes
+                .iter()
+                .filter(|hash| (hash.0).0 == row.id)
+                .map(|hash| (hash.1.clone(), hash.2.clone()))
+                .collect(),
+            primary: row.primary,
+            size: row.size,
+            file_type: row.file_type,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_files_with_commas_in_their_names() {
+        let files = r#"[{"id": 1, "filename": "mod, 1.0.jar", "primary": true, "size": 5000000000, "file_type": null, "url": "https://cdn.modrinth.com/mod, 1.0.jar"}]"#;
+        let hashes = vec![
+            (FileId(1), "sha1".to_string(), vec![1, 2]),
+            (FileId(2), "sha1".to_string(), vec![3, 4]),
+        ];
+
+        let files = parse_files(Some(files.to_string()), &hashes).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].filename, "mod, 1.0.jar");
+        assert_eq!(files[0].url, "https://cdn.modrinth.com/mod, 1.0.jar");
+        assert!(files[0].primary);
+        assert_eq!(files[0].size, Some(5_000_000_000));
+        assert_eq!(files[0].file_type, None);
+        assert_eq!(files[0].hashes.get("sha1"), Some(&vec![1, 2]));
+
+        assert!(parse_files(None, &hashes).unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_files_fail_to_decode() {
+        assert!(matches!(
+            parse_files(Some("[{\"id\": 1".to_string()), &[]),
+            Err(sqlx::Error::Decode(_))
+        ));
+    }
+
+    fn dependency(version_id: Option<i64>, project_id: Option<i64>) -> DependencyBuilder {
+        DependencyBuilder {
+            project_id: project_id.map(ProjectId),
+            version_id: version_id.map(VersionId),
+            dependency_type: "required".to_string(),
+        }
+    }
+
+    #[test]
+    fn dependencies_on_missing_versions_are_found() {
+        let dependencies = vec![
+            dependency(Some(1), None),
+            dependency(None, Some(5)),
+            dependency(Some(2), None),
+        ];
+
+        // A single missing version rejects all of the dependencies
+        assert_eq!(
+            DependencyBuilder::first_missing_version(&dependencies, &[1]),
+            Some(VersionId(2))
+        );
+        assert_eq!(
+            DependencyBuilder::first_missing_version(&dependencies, &[1, 2]),
+            None
+        );
+        assert_eq!(DependencyBuilder::first_missing_version(&[], &[]), None);
+    }
 }
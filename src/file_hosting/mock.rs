@@ -52,3 +52,64 @@ impl FileHost for MockHost {
         })
     }
 }
+
+/// A file host for tests which keeps no files, but records the names of the files uploaded
+/// to and deleted from it. Every deletion fails if `fail_deletes` is set.
+#[cfg(test)]
+pub struct RecordingHost {
+    pub uploaded: std::sync::Mutex<Vec<String>>,
+    pub deleted: std::sync::Mutex<Vec<String>>,
+    pub fail_deletes: bool,
+}
+
+#[cfg(test)]
+impl RecordingHost {
+    pub fn new(fail_deletes: bool) -> Self {
+        RecordingHost {
+            uploaded: std::sync::Mutex::new(Vec::new()),
+            deleted: std::sync::Mutex::new(Vec::new()),
+            fail_deletes,
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl FileHost for RecordingHost {
+    async fn upload_file(
+        &self,
+        content_type: &str,
+        file_name: &str,
+        file_bytes: Vec<u8>,
+    ) -> Result<UploadFileData, FileHostingError> {
+        self.uploaded.lock().unwrap().push(file_name.to_string());
+
+        Ok(UploadFileData {
+            file_id: file_name.to_string(),
+            file_name: file_name.to_string(),
+            content_length: file_bytes.len() as u32,
+            content_sha512: format!("{:x}", sha2::Sha512::digest(&file_bytes)),
+            content_sha1: sha1::Sha1::from(&file_bytes).hexdigest(),
+            content_md5: None,
+            content_type: content_type.to_string(),
+            upload_timestamp: 0,
+        })
+    }
+
+    async fn delete_file_version(
+        &self,
+        file_id: &str,
+        file_name: &str,
+    ) -> Result<DeleteFileData, FileHostingError> {
+        if self.fail_deletes {
+            return Err(FileHostingError::InvalidFilename);
+        }
+
+        self.deleted.lock().unwrap().push(file_name.to_string());
+
+        Ok(DeleteFileData {
+            file_id: file_id.to_string(),
+            file_name: file_name.to_string(),
+        })
+    }
+}
@@ -7,6 +7,8 @@ mod s3_host;
 
 pub use backblaze::BackblazeHost;
 pub use mock::MockHost;
+#[cfg(test)]
+pub use mock::RecordingHost;
 use s3::creds::AwsCredsError;
 use s3::S3Error;
 pub use s3_host::S3Host;
@@ -25,6 +27,8 @@ pub enum FileHostingError {
     FileSystemError(#[from] std::io::Error),
     #[error("Invalid Filename")]
     InvalidFilename,
+    #[error("Invalid hash returned by the file host: {0}")]
+    InvalidHash(#[from] hex::FromHexError),
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +49,14 @@ pub struct DeleteFileData {
     pub file_name: String,
 }
 
+/// Gets the path of a file on the file host from its public CDN URL, or `None`
+/// if the URL doesn't point to the CDN
+pub fn get_cdn_path<'a>(url: &'a str, cdn_url: &str) -> Option<&'a str> {
+    url.strip_prefix(cdn_url)
+        .and_then(|x| x.strip_prefix('/'))
+        .filter(|x| !x.is_empty())
+}
+
 #[async_trait]
 pub trait FileHost {
     async fn upload_file(
@@ -60,3 +72,27 @@ pub trait FileHost {
         file_name: &str,
     ) -> Result<DeleteFileData, FileHostingError>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cdn_path_strips_the_cdn_url() {
+        let cdn_url = "https://cdn.example.com";
+
+        assert_eq!(
+            get_cdn_path("https://cdn.example.com/data/abc/icon.png", cdn_url),
+            Some("data/abc/icon.png")
+        );
+        assert_eq!(
+            get_cdn_path("https://other.example.com/data/abc/icon.png", cdn_url),
+            None
+        );
+        assert_eq!(
+            get_cdn_path("https://cdn.example.com.evil/data/icon.png", cdn_url),
+            None
+        );
+        assert_eq!(get_cdn_path("https://cdn.example.com/", cdn_url), None);
+    }
+}
@@ -64,6 +64,16 @@ async fn main() -> std::io::Result<()> {
     let search_config = search::SearchConfig {
         address: dotenv::var("MEILISEARCH_ADDR").unwrap(),
         key: dotenv::var("MEILISEARCH_KEY").unwrap(),
+        max_retries: dotenv::var("INDEXING_MAX_RETRIES")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(5),
+        retry_base_delay: std::time::Duration::from_millis(
+            dotenv::var("INDEXING_RETRY_DELAY_MS")
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(1000),
+        ),
     };
 
     if config.reset_indices {
@@ -144,12 +154,16 @@ async fn main() -> std::io::Result<()> {
             .unwrap_or(3600),
     );
 
+    let indexing_guard = Arc::new(search::indexing::IndexingGuard::default());
+
     let pool_ref = pool.clone();
     let thread_search_config = search_config.clone();
+    let thread_indexing_guard = indexing_guard.clone();
     let mut skip = skip_initial;
     scheduler.run(local_index_interval, move || {
         let pool_ref = pool_ref.clone();
         let thread_search_config = thread_search_config.clone();
+        let thread_indexing_guard = thread_indexing_guard.clone();
         let local_skip = skip;
         if skip {
             skip = false;
@@ -158,16 +172,28 @@ async fn main() -> std::io::Result<()> {
             if local_skip {
                 return;
             }
+            if !thread_indexing_guard.try_start() {
+                info!("Skipping local indexing, since it is already running");
+                return;
+            }
             info!("Indexing local database");
             let settings = IndexingSettings { index_local: true };
             let result = index_projects(pool_ref, settings, &thread_search_config).await;
             if let Err(e) = result {
                 warn!("Local project indexing failed: {:?}", e);
             }
+            thread_indexing_guard.finish();
             info!("Done indexing local database");
         }
     });
 
+    // The number of days downloads are kept for, which limits how far back download
+    // analytics go. Deduplicating downloads only needs the last 30 minutes.
+    let download_history_days: i32 = dotenv::var("DOWNLOAD_HISTORY_DAYS")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(30);
+
     let pool_ref = pool.clone();
     scheduler.run(std::time::Duration::from_secs(15 * 60), move || {
         let pool_ref = pool_ref.clone();
@@ -178,8 +204,9 @@ async fn main() -> std::io::Result<()> {
             let downloads_result = sqlx::query!(
                 "
                 DELETE FROM downloads
-                WHERE date < (CURRENT_DATE - INTERVAL '30 minutes ago')
-                "
+                WHERE date < (NOW() - make_interval(days => $1))
+                ",
+                download_history_days
             )
             .execute(&pool_ref)
             .await;
@@ -238,12 +265,27 @@ async fn main() -> std::io::Result<()> {
 
     scheduler::schedule_versions(&mut scheduler, pool.clone(), skip_initial);
 
+    let pagination_config = util::pagination::PaginationConfig::from_env();
+    let project_check_config = util::validate::ProjectCheckConfig::from_env();
+    let webhook_config = util::webhook::WebhookConfig::from_env();
+
     let ip_salt = Pepper {
         pepper: crate::models::ids::Base62Id(crate::models::ids::random_base62(11)).to_string(),
     };
 
     let store = MemoryStore::new();
 
+    let download_limiter = Arc::new(util::ratelimit::DownloadLimiter::from_env());
+
+    let limiter_ref = download_limiter.clone();
+    scheduler.run(std::time::Duration::from_secs(5 * 60), move || {
+        let limiter = limiter_ref.clone();
+
+        async move {
+            limiter.prune();
+        }
+    });
+
     info!("Starting Actix HTTP server!");
 
     // Init App
@@ -307,7 +349,12 @@ async fn main() -> std::io::Result<()> {
             .data(file_host.clone())
             .data(indexing_queue.clone())
             .data(search_config.clone())
+            .data(pagination_config.clone())
+            .data(project_check_config.clone())
+            .data(webhook_config.clone())
             .data(ip_salt.clone())
+            .data(download_limiter.clone())
+            .data(indexing_guard.clone())
             .configure(routes::v1_config)
             .configure(routes::v2_config)
             .service(routes::index_get)
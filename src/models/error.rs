@@ -1,8 +1,122 @@
-use serde::{Deserialize, Serialize};
+use serde::{Serialize, Serializer};
 
 /// An error returned by the API
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize)]
 pub struct ApiError<'a> {
-    pub error: &'a str,
+    pub error: ErrorCode,
     pub description: &'a str,
 }
+
+/// An error returned by the API for input which failed validation, which also lists every
+/// field that failed so clients can point out the specific inputs at fault
+#[derive(Serialize)]
+pub struct ValidationApiError<'a> {
+    #[serde(flatten)]
+    pub error: ApiError<'a>,
+    pub fields: Vec<FieldError>,
+}
+
+/// A single field which failed validation
+#[derive(Serialize)]
+pub struct FieldError {
+    /// The path of the field, such as `title` or `gallery_items[0].title`
+    pub field: String,
+    /// The validation which failed, such as `length` or `url`
+    pub code: String,
+    pub message: String,
+}
+
+/// Declares `ErrorCode` along with the code each variant is sent as, so that the list of
+/// every code can't fall out of sync with the enum
+macro_rules! error_codes {
+    ($($variant:ident => $code:literal,)*) => {
+        /// The machine readable code of an error returned by the API. All error types map
+        /// onto these, so the same kind of failure is always reported with the same code.
+        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        pub enum ErrorCode {
+            $($variant,)*
+        }
+
+        impl ErrorCode {
+            /// Every error code, so that they can be checked to be unique
+            #[cfg(test)]
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$variant,)*];
+
+            /// The code sent to clients, which must never change once it has been released
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => $code,)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    EnvironmentError => "environment_error",
+    DatabaseError => "database_error",
+    IndexingError => "indexing_error",
+    FileHostingError => "file_hosting_error",
+    SearchError => "search_error",
+    MeilisearchError => "meilisearch_error",
+    XmlError => "xml_error",
+    JsonError => "json_error",
+    GithubError => "github_error",
+    DecodingError => "decoding_error",
+    InvalidInput => "invalid_input",
+    DuplicateFile => "duplicate_file",
+    Unauthorized => "unauthorized",
+    Forbidden => "forbidden",
+    AuthenticationError => "authentication_error",
+    InvalidCredentials => "invalid_credentials",
+    NotFound => "not_found",
+    RateLimited => "rate_limited",
+    Conflict => "conflict",
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.as_str())
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ErrorCode;
+    use std::collections::HashSet;
+
+    #[test]
+    fn error_codes_are_unique() {
+        let codes = ErrorCode::ALL
+            .iter()
+            .map(|x| x.as_str())
+            .collect::<HashSet<_>>();
+
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
+    }
+
+    #[test]
+    fn error_codes_are_stable() {
+        assert_eq!(ErrorCode::InvalidInput.as_str(), "invalid_input");
+        assert_eq!(ErrorCode::Unauthorized.as_str(), "unauthorized");
+        assert_eq!(ErrorCode::Forbidden.as_str(), "forbidden");
+        assert_eq!(ErrorCode::NotFound.as_str(), "not_found");
+        assert_eq!(
+            serde_json::to_string(&ErrorCode::NotFound).unwrap(),
+            "\"not_found\""
+        );
+        assert_eq!(
+            serde_json::to_string(&ErrorCode::RateLimited).unwrap(),
+            "\"rate_limited\""
+        );
+    }
+}
@@ -3,6 +3,9 @@ use super::users::UserId;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// The types of notifications which can be sent to users
+pub const NOTIFICATION_TYPES: &[&str] = &["project_update", "team_invite", "status_change"];
+
 #[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(from = "Base62Id")]
 #[serde(into = "Base62Id")]
@@ -18,6 +21,7 @@ pub struct Notification {
     pub text: String,
     pub link: String,
     pub read: bool,
+    #[serde(with = "crate::util::time::rfc3339")]
     pub created: DateTime<Utc>,
     pub actions: Vec<NotificationAction>,
 }
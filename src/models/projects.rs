@@ -5,8 +5,12 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
+/// The maximum number of categories a project can be in, unless its project type has
+/// its own limit
+pub const MAX_CATEGORIES: usize = 3;
+
 /// The ID of a specific project, encoded as base62 for usage in the API
-#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 #[serde(from = "Base62Id")]
 #[serde(into = "Base62Id")]
 pub struct ProjectId(pub u64);
@@ -37,8 +41,10 @@ pub struct Project {
     /// The link to the long description of the project. (Deprecated), being replaced by `body`
     pub body_url: Option<String>,
     /// The date at which the project was first published.
+    #[serde(with = "crate::util::time::rfc3339")]
     pub published: DateTime<Utc>,
     /// The date at which the project was first published.
+    #[serde(with = "crate::util::time::rfc3339")]
     pub updated: DateTime<Utc>,
 
     /// The status of the project
@@ -78,6 +84,11 @@ pub struct Project {
 
     /// A string of URLs to visual content featuring the project
     pub gallery: Vec<String>,
+    /// The URL of the image used when previewing the project, either the featured gallery
+    /// image or the icon if there is none
+    pub featured_gallery_url: Option<String>,
+    /// Whether the project contains adult or otherwise sensitive content
+    pub sensitive: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -223,6 +234,7 @@ pub struct Version {
     /// A link to the changelog for this version of the project. (Deprecated), being replaced by `changelog`
     pub changelog_url: Option<String>,
     /// The date that this version was published.
+    #[serde(with = "crate::util::time::rfc3339")]
     pub date_published: DateTime<Utc>,
     /// The number of downloads this specific version has had.
     pub downloads: u32,
@@ -251,6 +263,46 @@ pub struct VersionFile {
     pub filename: String,
     /// Whether the file is the primary file of a version
     pub primary: bool,
+    /// The size of the file in bytes, if known
+    pub size: Option<u64>,
+    /// What kind of artifact the file is, if it was labeled when uploaded
+    pub file_type: Option<FileType>,
+}
+
+/// A label describing what kind of artifact a version file is, so launchers can pick
+/// the right one among several files
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileType {
+    Installer,
+    Source,
+    Additional,
+}
+
+impl std::fmt::Display for FileType {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.as_str())
+    }
+}
+
+impl FileType {
+    // These are constant, so this can remove unneccessary allocations (`to_string`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileType::Installer => "installer",
+            FileType::Source => "source",
+            FileType::Additional => "additional",
+        }
+    }
+
+    pub fn from_str(string: &str) -> Option<FileType> {
+        match string {
+            "installer" => Some(FileType::Installer),
+            "source" => Some(FileType::Source),
+            "additional" => Some(FileType::Additional),
+            _ => None,
+        }
+    }
 }
 
 /// A dependency which describes what versions are required, break support, or are optional to the
@@ -355,5 +407,61 @@ pub struct SearchRequest {
     pub version: Option<String>,
     pub offset: Option<String>,
     pub index: Option<String>,
+    /// The order to sort results in, one of `relevance`, `downloads`, `follows`, `updated`
+    /// or `newest`. Invalid values fall back to `relevance`.
+    pub sort: Option<String>,
     pub limit: Option<String>,
+    /// Must be a json array of project ids to leave out of the results
+    pub excluded_ids: Option<String>,
+    /// Whether to include projects marked as sensitive, defaults to false
+    pub show_sensitive: Option<bool>,
+    /// Whether a project whose slug or title exactly matches the query should be
+    /// returned first, defaults to false
+    pub exact_first: Option<bool>,
+    /// Must be a json array of the fields to return for each result, defaults to all fields
+    pub fields: Option<String>,
+    /// Only return projects with this client side support, one of `required`, `optional`,
+    /// `unsupported` or `unknown`
+    pub client_side: Option<String>,
+    /// Only return projects with this server side support, one of `required`, `optional`,
+    /// `unsupported` or `unknown`
+    pub server_side: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_files_round_trip() {
+        let file = VersionFile {
+            hashes: std::collections::HashMap::new(),
+            url: "https://cdn.example.com/data/AABBCCDD/versions/1.0.0/mod-sources.jar".to_string(),
+            filename: "mod-sources.jar".to_string(),
+            primary: false,
+            size: Some(1024),
+            file_type: Some(FileType::Source),
+        };
+
+        let json = serde_json::to_value(&file).unwrap();
+        assert_eq!(json["file_type"], "source");
+
+        let file: VersionFile = serde_json::from_value(json).unwrap();
+        assert_eq!(file.file_type, Some(FileType::Source));
+
+        // The label is stored as text in the database
+        let stored = FileType::Source.to_string();
+        assert_eq!(FileType::from_str(&stored), Some(FileType::Source));
+    }
+
+    #[test]
+    fn files_are_unlabeled_by_default() {
+        let file: VersionFile = serde_json::from_str(
+            r#"{"hashes": {}, "url": "", "filename": "mod.jar", "primary": true, "size": null, "file_type": null}"#,
+        )
+        .unwrap();
+
+        assert_eq!(file.file_type, None);
+        assert!(serde_json::from_str::<FileType>("\"binary\"").is_err());
+    }
 }
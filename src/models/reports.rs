@@ -16,6 +16,7 @@ pub struct Report {
     pub item_type: ItemType,
     pub reporter: UserId,
     pub body: String,
+    #[serde(with = "crate::util::time::rfc3339")]
     pub created: DateTime<Utc>,
 }
 
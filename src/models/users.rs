@@ -17,6 +17,7 @@ pub struct User {
     pub email: Option<String>,
     pub avatar_url: Option<String>,
     pub bio: Option<String>,
+    #[serde(with = "crate::util::time::rfc3339")]
     pub created: chrono::DateTime<chrono::Utc>,
     pub role: Role,
 }
@@ -1,5 +1,5 @@
 use crate::database::models::{generate_state_id, User};
-use crate::models::error::ApiError;
+use crate::models::error::{ApiError, ErrorCode};
 use crate::models::ids::base62_impl::{parse_base62, to_base62};
 use crate::models::ids::DecodingError;
 use crate::models::users::Role;
@@ -53,14 +53,14 @@ impl actix_web::ResponseError for AuthorizationError {
     fn error_response(&self) -> HttpResponse {
         HttpResponse::build(self.status_code()).json(ApiError {
             error: match self {
-                AuthorizationError::EnvError(..) => "environment_error",
-                AuthorizationError::SqlxDatabaseError(..) => "database_error",
-                AuthorizationError::DatabaseError(..) => "database_error",
-                AuthorizationError::SerDeError(..) => "invalid_input",
-                AuthorizationError::GithubError(..) => "github_error",
-                AuthorizationError::InvalidCredentialsError => "invalid_credentials",
-                AuthorizationError::DecodingError(..) => "decoding_error",
-                AuthorizationError::AuthenticationError(..) => "authentication_error",
+                AuthorizationError::EnvError(..) => ErrorCode::EnvironmentError,
+                AuthorizationError::SqlxDatabaseError(..) => ErrorCode::DatabaseError,
+                AuthorizationError::DatabaseError(..) => ErrorCode::DatabaseError,
+                AuthorizationError::SerDeError(..) => ErrorCode::InvalidInput,
+                AuthorizationError::GithubError(..) => ErrorCode::GithubError,
+                AuthorizationError::InvalidCredentialsError => ErrorCode::InvalidCredentials,
+                AuthorizationError::DecodingError(..) => ErrorCode::DecodingError,
+                AuthorizationError::AuthenticationError(..) => ErrorCode::AuthenticationError,
             },
             description: &self.to_string(),
         })
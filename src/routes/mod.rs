@@ -25,6 +25,8 @@ pub use tags::config as tags_config;
 pub use self::index::index_get;
 pub use self::not_found::not_found;
 use crate::file_hosting::FileHostingError;
+use crate::models::error::ErrorCode;
+use crate::util::auth::AuthenticationError;
 
 pub fn v2_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -44,18 +46,40 @@ pub fn v2_config(cfg: &mut web::ServiceConfig) {
 pub fn projects_config(cfg: &mut web::ServiceConfig) {
     cfg.service(projects::project_search);
     cfg.service(projects::projects_get);
+    cfg.service(projects::projects_recent);
+    cfg.service(projects::projects_popular);
     cfg.service(project_creation::project_create);
 
     cfg.service(
         web::scope("project")
             .service(projects::project_get)
+            .service(projects::project_donations_get)
+            .service(projects::project_loaders_get)
+            .service(projects::project_game_versions_get)
+            .service(projects::project_channel_counts_get)
+            .service(projects::project_file_manifest_get)
+            .service(projects::project_card_get)
+            .service(projects::project_related_get)
+            .service(projects::project_summary_stats_get)
+            .service(projects::project_downloads_get)
             .service(projects::project_delete)
             .service(projects::project_edit)
+            .service(projects::project_withdraw)
+            .service(projects::project_clone)
+            .service(projects::project_featured_versions_edit)
             .service(projects::project_icon_edit)
+            .service(projects::delete_project_icon)
+            .service(projects::add_gallery_item)
+            .service(projects::project_translation_edit)
+            .service(projects::project_translation_delete)
             .service(projects::project_follow)
             .service(projects::project_unfollow)
             .service(teams::team_members_get_project)
-            .service(web::scope("{project_id}").service(versions::version_list))
+            .service(
+                web::scope("{project_id}")
+                    .service(versions::version_list)
+                    .service(versions::version_project_get),
+            )
             .service(projects::dependency_list),
     );
 }
@@ -71,7 +95,9 @@ pub fn versions_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("version")
             .service(versions::version_get)
+            .service(versions::version_dependencies_get)
             .service(versions::version_delete)
+            .service(versions::version_file_move)
             .service(version_creation::upload_file_to_version)
             .service(versions::version_edit),
     );
@@ -79,6 +105,7 @@ pub fn versions_config(cfg: &mut web::ServiceConfig) {
         web::scope("version_file")
             .service(version_file::delete_file)
             .service(version_file::get_version_from_hash)
+            .service(version_file::get_file_from_hash)
             .service(version_file::download_version)
             .service(version_file::get_update_from_hash),
     );
@@ -86,6 +113,7 @@ pub fn versions_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("version_files")
             .service(version_file::get_versions_from_hashes)
+            .service(version_file::hashes_exist)
             .service(version_file::download_files)
             .service(version_file::update_files),
     );
@@ -97,13 +125,21 @@ pub fn users_config(cfg: &mut web::ServiceConfig) {
     cfg.service(users::users_get);
     cfg.service(
         web::scope("user")
+            .service(users::user_permissions)
             .service(users::user_get)
             .service(users::projects_list)
             .service(users::user_delete)
             .service(users::user_edit)
+            .service(users::user_role_edit)
             .service(users::user_icon_edit)
             .service(users::user_notifications)
-            .service(users::user_follows),
+            .service(
+                web::resource("{id}/notifications/read")
+                    .route(web::post().to(users::user_notifications_read))
+                    .route(web::patch().to(users::user_notifications_read)),
+            )
+            .service(users::user_follows)
+            .service(users::user_activity),
     );
 }
 
@@ -114,28 +150,40 @@ pub fn teams_config(cfg: &mut web::ServiceConfig) {
             .service(teams::edit_team_member)
             .service(teams::add_team_member)
             .service(teams::join_team)
-            .service(teams::remove_team_member),
+            .service(teams::leave_team)
+            .service(teams::remove_team_member)
+            .service(teams::transfer_ownership),
     );
 }
 
 pub fn notifications_config(cfg: &mut web::ServiceConfig) {
     cfg.service(notifications::notifications_get);
-    cfg.service(notifications::notification_delete);
+    cfg.service(notifications::notifications_delete);
 
     cfg.service(
         web::scope("notification")
             .service(notifications::notification_get)
+            .service(notifications::notification_read)
             .service(notifications::notification_delete),
     );
 }
 
 pub fn moderation_config(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("moderation").service(moderation::get_projects));
+    cfg.service(
+        web::scope("moderation")
+            .service(moderation::get_projects)
+            .service(moderation::get_recent_projects)
+            .service(moderation::cleanup_drafts)
+            .service(moderation::approve_project)
+            .service(moderation::reject_project)
+            .service(moderation::reindex),
+    );
 }
 
 pub fn reports_config(cfg: &mut web::ServiceConfig) {
     cfg.service(reports::reports);
     cfg.service(reports::report_create);
+    cfg.service(reports::report_get);
     cfg.service(reports::delete_report);
 }
 
@@ -157,6 +205,8 @@ pub enum ApiError {
     AuthenticationError(#[from] crate::util::auth::AuthenticationError),
     #[error("Authentication Error: {0}")]
     CustomAuthenticationError(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
     #[error("Invalid Input: {0}")]
     InvalidInputError(String),
     #[error("Error while validating input: {0}")]
@@ -165,6 +215,10 @@ pub enum ApiError {
     SearchError(#[from] meilisearch_sdk::errors::Error),
     #[error("Indexing Error: {0}")]
     IndexingError(#[from] crate::search::indexing::IndexingError),
+    #[error("Rate limit exceeded: {0}")]
+    RateLimitError(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
 }
 
 impl actix_web::ResponseError for ApiError {
@@ -173,8 +227,12 @@ impl actix_web::ResponseError for ApiError {
             ApiError::EnvError(..) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::DatabaseError(..) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::SqlxDatabaseError(..) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::AuthenticationError(AuthenticationError::MissingRole) => {
+                actix_web::http::StatusCode::FORBIDDEN
+            }
             ApiError::AuthenticationError(..) => actix_web::http::StatusCode::UNAUTHORIZED,
             ApiError::CustomAuthenticationError(..) => actix_web::http::StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(..) => actix_web::http::StatusCode::FORBIDDEN,
             ApiError::XmlError(..) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::JsonError(..) => actix_web::http::StatusCode::BAD_REQUEST,
             ApiError::SearchError(..) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
@@ -182,6 +240,8 @@ impl actix_web::ResponseError for ApiError {
             ApiError::FileHostingError(..) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::InvalidInputError(..) => actix_web::http::StatusCode::BAD_REQUEST,
             ApiError::ValidationError(..) => actix_web::http::StatusCode::BAD_REQUEST,
+            ApiError::RateLimitError(..) => actix_web::http::StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Conflict(..) => actix_web::http::StatusCode::CONFLICT,
         }
     }
 
@@ -189,18 +249,24 @@ impl actix_web::ResponseError for ApiError {
         actix_web::web::HttpResponse::build(self.status_code()).json(
             crate::models::error::ApiError {
                 error: match self {
-                    ApiError::EnvError(..) => "environment_error",
-                    ApiError::SqlxDatabaseError(..) => "database_error",
-                    ApiError::DatabaseError(..) => "database_error",
-                    ApiError::AuthenticationError(..) => "unauthorized",
-                    ApiError::CustomAuthenticationError(..) => "unauthorized",
-                    ApiError::XmlError(..) => "xml_error",
-                    ApiError::JsonError(..) => "json_error",
-                    ApiError::SearchError(..) => "search_error",
-                    ApiError::IndexingError(..) => "indexing_error",
-                    ApiError::FileHostingError(..) => "file_hosting_error",
-                    ApiError::InvalidInputError(..) => "invalid_input",
-                    ApiError::ValidationError(..) => "invalid_input",
+                    ApiError::EnvError(..) => ErrorCode::EnvironmentError,
+                    ApiError::SqlxDatabaseError(..) => ErrorCode::DatabaseError,
+                    ApiError::DatabaseError(..) => ErrorCode::DatabaseError,
+                    ApiError::AuthenticationError(AuthenticationError::MissingRole) => {
+                        ErrorCode::Forbidden
+                    }
+                    ApiError::AuthenticationError(..) => ErrorCode::Unauthorized,
+                    ApiError::CustomAuthenticationError(..) => ErrorCode::Unauthorized,
+                    ApiError::Forbidden(..) => ErrorCode::Forbidden,
+                    ApiError::XmlError(..) => ErrorCode::XmlError,
+                    ApiError::JsonError(..) => ErrorCode::JsonError,
+                    ApiError::SearchError(..) => ErrorCode::SearchError,
+                    ApiError::IndexingError(..) => ErrorCode::IndexingError,
+                    ApiError::FileHostingError(..) => ErrorCode::FileHostingError,
+                    ApiError::InvalidInputError(..) => ErrorCode::InvalidInput,
+                    ApiError::ValidationError(..) => ErrorCode::InvalidInput,
+                    ApiError::RateLimitError(..) => ErrorCode::RateLimited,
+                    ApiError::Conflict(..) => ErrorCode::Conflict,
                 },
                 description: &self.to_string(),
             },
@@ -1,19 +1,28 @@
 use super::ApiError;
 use crate::database;
+use crate::file_hosting::{get_cdn_path, FileHost};
 use crate::models::projects::{Project, ProjectStatus};
+use crate::models::users::User;
+use crate::routes::projects::{check_has_files, notify_status_change, requires_files};
+use crate::search::indexing::queue::CreationQueue;
+use crate::search::indexing::{delete_from_index, IndexingGuard};
+use crate::search::SearchConfig;
 use crate::util::auth::check_is_moderator_from_headers;
-use actix_web::{get, web, HttpRequest, HttpResponse};
-use serde::Deserialize;
+use crate::util::pagination::PaginationConfig;
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
 
 #[derive(Deserialize)]
 pub struct ResultCount {
-    #[serde(default = "default_count")]
-    pub count: i16,
-}
-
-fn default_count() -> i16 {
-    100
+    pub count: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+    /// The status of the projects to return, defaults to `processing`
+    pub status: Option<String>,
 }
 
 #[get("projects")]
@@ -21,33 +30,617 @@ pub async fn get_projects(
     req: HttpRequest,
     pool: web::Data<PgPool>,
     count: web::Query<ResultCount>,
+    pagination: web::Data<PaginationConfig>,
 ) -> Result<HttpResponse, ApiError> {
     check_is_moderator_from_headers(req.headers(), &**pool).await?;
 
+    let status = match count.status.as_deref() {
+        Some(status) => {
+            let parsed = ProjectStatus::from_str(status);
+
+            if parsed.as_str() != status {
+                return Err(ApiError::InvalidInputError(format!(
+                    "Invalid project status: {}",
+                    status
+                )));
+            }
+
+            parsed
+        }
+        None => ProjectStatus::Processing,
+    };
+
     use futures::stream::TryStreamExt;
 
+    let (limit, offset) = queue_page(&count, &pagination);
+
+    // The queue is ordered oldest first, so projects that have waited longest are reviewed first
     let project_ids = sqlx::query!(
         "
         SELECT id FROM mods
         WHERE status = (
             SELECT id FROM statuses WHERE status = $1
         )
-        ORDER BY updated ASC
-        LIMIT $2;
+        ORDER BY published ASC, id ASC
+        LIMIT $2 OFFSET $3;
         ",
-        ProjectStatus::Processing.as_str(),
-        count.count as i64
+        status.as_str(),
+        limit,
+        offset,
     )
     .fetch_many(&**pool)
     .try_filter_map(|e| async { Ok(e.right().map(|m| database::models::ProjectId(m.id))) })
     .try_collect::<Vec<database::models::ProjectId>>()
     .await?;
 
-    let projects: Vec<Project> = database::Project::get_many_full(project_ids, &**pool)
-        .await?
+    let mut projects = database::Project::get_many_full(project_ids, &**pool).await?;
+
+    // get_many_full doesn't preserve the ordering from the query
+    sort_oldest_first(&mut projects);
+
+    let projects: Vec<Project> = projects
         .into_iter()
         .map(super::projects::convert_project)
         .collect();
 
     Ok(HttpResponse::Ok().json(projects))
 }
+
+/// Gets the limit and offset of a page of the moderation queue
+fn queue_page(count: &ResultCount, pagination: &PaginationConfig) -> (i64, i64) {
+    (
+        pagination.limit_or(count.count, 100) as i64,
+        i64::try_from(count.offset).unwrap_or(i64::MAX),
+    )
+}
+
+/// Orders projects oldest first like the moderation queue, breaking ties by id
+fn sort_oldest_first(projects: &mut [database::models::project_item::QueryProject]) {
+    projects.sort_by(|a, b| {
+        a.inner
+            .published
+            .cmp(&b.inner.published)
+            .then(a.inner.id.0.cmp(&b.inner.id.0))
+    });
+}
+
+/// Orders projects newest first like the recent projects query, breaking ties by id
+fn sort_newest_first(projects: &mut [database::models::project_item::QueryProject]) {
+    projects.sort_by(|a, b| {
+        b.inner
+            .published
+            .cmp(&a.inner.published)
+            .then(b.inner.id.0.cmp(&a.inner.id.0))
+    });
+}
+
+#[derive(Deserialize)]
+pub struct RecentProjectsPagination {
+    pub count: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// A recently created project, along with the user who created it
+#[derive(Serialize)]
+pub struct RecentProject {
+    pub project: Project,
+    pub owner: Option<User>,
+}
+
+#[get("projects/recent")]
+pub async fn get_recent_projects(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    page: web::Query<RecentProjectsPagination>,
+    pagination: web::Data<PaginationConfig>,
+) -> Result<HttpResponse, ApiError> {
+    check_is_moderator_from_headers(req.headers(), &**pool).await?;
+
+    use futures::stream::TryStreamExt;
+
+    let rows = sqlx::query!(
+        "
+        SELECT m.id, o.user_id \"user_id?\" FROM mods m
+        LEFT JOIN (
+            SELECT DISTINCT ON (tm.team_id) tm.team_id, tm.user_id FROM team_members tm
+            WHERE tm.role = $1 AND tm.accepted = TRUE
+            ORDER BY tm.team_id, tm.id
+        ) o ON o.team_id = m.team_id
+        ORDER BY m.published DESC, m.id DESC
+        LIMIT $2 OFFSET $3;
+        ",
+        crate::models::teams::OWNER_ROLE,
+        pagination.limit_or(page.count, 100) as i64,
+        i64::try_from(page.offset).unwrap_or(i64::MAX),
+    )
+    .fetch_many(&**pool)
+    .try_filter_map(|e| async {
+        Ok(e.right().map(|m| {
+            (
+                database::models::ProjectId(m.id),
+                m.user_id.map(database::models::UserId),
+            )
+        }))
+    })
+    .try_collect::<Vec<(
+        database::models::ProjectId,
+        Option<database::models::UserId>,
+    )>>()
+    .await?;
+
+    let project_ids = rows.iter().map(|x| x.0).collect::<Vec<_>>();
+    let owner_ids = rows.iter().filter_map(|x| x.1).collect::<Vec<_>>();
+
+    let owners: Vec<User> = database::models::User::get_many(owner_ids, &**pool)
+        .await?
+        .into_iter()
+        .map(super::users::convert_user)
+        .collect();
+    let mut projects = database::Project::get_many_full(project_ids, &**pool).await?;
+
+    // get_many_full doesn't preserve the ordering from the query
+    sort_newest_first(&mut projects);
+
+    let project_owners = rows
+        .into_iter()
+        .filter_map(|(project_id, owner_id)| Some((project_id.0, owner_id?.0)))
+        .collect::<HashMap<i64, i64>>();
+    let owners = owners
+        .into_iter()
+        .map(|owner| (database::models::UserId::from(owner.id).0, owner))
+        .collect::<HashMap<i64, User>>();
+
+    let response = projects
+        .into_iter()
+        .map(|project| {
+            let owner = project_owners
+                .get(&project.inner.id.0)
+                .and_then(|owner_id| owners.get(owner_id).cloned());
+
+            RecentProject {
+                project: super::projects::convert_project(project),
+                owner,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[derive(Deserialize)]
+pub struct DraftCleanupQuery {
+    pub older_than_days: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct DraftCleanupResult {
+    pub count: u64,
+}
+
+/// Deletes draft projects without any versions which haven't been updated in the given
+/// number of days, defaulting to `STALE_DRAFT_DAYS`
+#[post("cleanup_drafts")]
+pub async fn cleanup_drafts(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
+    query: web::Query<DraftCleanupQuery>,
+) -> Result<HttpResponse, ApiError> {
+    check_is_moderator_from_headers(req.headers(), &**pool).await?;
+
+    let older_than_days = query.older_than_days.unwrap_or_else(|| {
+        dotenv::var("STALE_DRAFT_DAYS")
+            .ok()
+            .and_then(|x| x.parse::<i32>().ok())
+            .unwrap_or(90)
+    });
+
+    if older_than_days < 1 {
+        return Err(ApiError::InvalidInputError(
+            "Drafts must be at least a day old to be cleaned up".to_string(),
+        ));
+    }
+
+    let cdn_url = dotenv::var("CDN_URL")?;
+
+    let mut transaction = pool.begin().await?;
+
+    // Without a cutoff the window reaches back further than any project could be
+    let cutoff =
+        chrono::Utc::now().checked_sub_signed(chrono::Duration::days(i64::from(older_than_days)));
+
+    let drafts = sqlx::query!(
+        "
+        SELECT m.id, m.icon_url, m.updated,
+        EXISTS(SELECT 1 FROM versions v WHERE v.mod_id = m.id) has_versions
+        FROM mods m
+        WHERE m.status = (
+            SELECT id FROM statuses WHERE status = $1
+        )
+        FOR UPDATE
+        ",
+        ProjectStatus::Draft.as_str(),
+    )
+    .fetch_all(&mut *transaction)
+    .await?
+    .into_iter()
+    .filter(|x| {
+        cutoff.map_or(false, |cutoff| {
+            is_stale_draft(x.updated, x.has_versions.unwrap_or(true), cutoff)
+        })
+    })
+    .collect::<Vec<_>>();
+
+    let mut count = 0;
+
+    for draft in &drafts {
+        let result = database::models::Project::remove_full(
+            database::models::ProjectId(draft.id),
+            &mut transaction,
+        )
+        .await?;
+
+        if result.is_some() {
+            count += 1;
+        }
+    }
+
+    transaction.commit().await?;
+
+    // The icons are only deleted once the projects are gone, so a failed deletion can't
+    // leave a project pointing at a missing icon. The drafts are already removed by now,
+    // so failing to delete an icon is only logged.
+    for icon_url in drafts.into_iter().filter_map(|x| x.icon_url) {
+        if let Some(icon_path) = get_cdn_path(&icon_url, &cdn_url) {
+            if let Err(err) = file_host.delete_file_version("", icon_path).await {
+                log::warn!(
+                    "Failed to delete the icon {} of a stale draft: {}",
+                    icon_url,
+                    err
+                );
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(DraftCleanupResult { count }))
+}
+
+/// Whether a draft can be removed by `cleanup_drafts`. Drafts with versions are never
+/// removed, however long ago they were last updated.
+fn is_stale_draft(
+    updated: chrono::DateTime<chrono::Utc>,
+    has_versions: bool,
+    cutoff: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    !has_versions && updated < cutoff
+}
+
+#[derive(Deserialize)]
+pub struct ProjectRejection {
+    /// The reason the project was rejected, which is sent to its team
+    pub reason: String,
+}
+
+#[post("project/{id}/approve")]
+pub async fn approve_project(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    config: web::Data<SearchConfig>,
+    indexing_queue: web::Data<Arc<CreationQueue>>,
+) -> Result<HttpResponse, ApiError> {
+    moderate_project(
+        req,
+        info.into_inner().0,
+        ProjectStatus::Approved,
+        None,
+        pool,
+        config,
+        indexing_queue,
+    )
+    .await
+}
+
+#[post("project/{id}/reject")]
+pub async fn reject_project(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    config: web::Data<SearchConfig>,
+    indexing_queue: web::Data<Arc<CreationQueue>>,
+    rejection: web::Json<ProjectRejection>,
+) -> Result<HttpResponse, ApiError> {
+    let reason = check_rejection_reason(&rejection.reason)?;
+
+    moderate_project(
+        req,
+        info.into_inner().0,
+        ProjectStatus::Rejected,
+        Some(reason),
+        pool,
+        config,
+        indexing_queue,
+    )
+    .await
+}
+
+/// Trims the reason a project is rejected for, which can't be empty
+fn check_rejection_reason(reason: &str) -> Result<String, ApiError> {
+    let reason = reason.trim();
+
+    if reason.is_empty() {
+        return Err(ApiError::InvalidInputError(
+            "A reason must be given to reject a project".to_string(),
+        ));
+    }
+
+    if reason.len() > 2000 {
+        return Err(ApiError::InvalidInputError(
+            "The rejection reason must be at most 2000 characters".to_string(),
+        ));
+    }
+
+    Ok(reason.to_string())
+}
+
+/// How the search index changes when a project's status changes
+#[derive(Debug, PartialEq)]
+enum IndexChange {
+    Add,
+    Remove,
+}
+
+fn index_change(old: &ProjectStatus, new: &ProjectStatus) -> Option<IndexChange> {
+    match (old.is_searchable(), new.is_searchable()) {
+        (false, true) => Some(IndexChange::Add),
+        (true, false) => Some(IndexChange::Remove),
+        _ => None,
+    }
+}
+
+/// Sets the status of a project on behalf of a moderator, notifying its team and updating
+/// the search index if the project became searchable or stopped being searchable
+async fn moderate_project(
+    req: HttpRequest,
+    project: String,
+    status: ProjectStatus,
+    reason: Option<String>,
+    pool: web::Data<PgPool>,
+    config: web::Data<SearchConfig>,
+    indexing_queue: web::Data<Arc<CreationQueue>>,
+) -> Result<HttpResponse, ApiError> {
+    check_is_moderator_from_headers(req.headers(), &**pool).await?;
+
+    let project_item =
+        database::models::Project::get_full_from_slug_or_project_id(project, &**pool).await?;
+
+    let project_item = match project_item {
+        Some(project_item) => project_item,
+        None => return Ok(HttpResponse::NotFound().body("")),
+    };
+
+    let id = project_item.inner.id;
+
+    let mut transaction = pool.begin().await?;
+
+    if requires_files(&project_item.status, &status) {
+        check_has_files(id, &status, &mut *transaction).await?;
+    }
+
+    let status_id = database::models::StatusId::get_id(&status, &mut *transaction)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInputError("No database entry for status provided.".to_string())
+        })?;
+
+    sqlx::query!(
+        "
+        UPDATE mods
+        SET status = $1, rejection_reason = $2, rejection_body = NULL
+        WHERE (id = $3)
+        ",
+        status_id as database::models::ids::StatusId,
+        reason.as_deref(),
+        id as database::models::ids::ProjectId,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    if status != project_item.status {
+        notify_status_change(
+            &project_item.inner,
+            &status,
+            reason.as_deref(),
+            &mut transaction,
+        )
+        .await?;
+    }
+
+    // The project is read for indexing while its new status is visible, but the index is
+    // only changed once the status change has been committed
+    let index_change = index_change(&project_item.status, &status);
+    let index_project = if index_change == Some(IndexChange::Add) {
+        Some(crate::search::indexing::local_import::query_one(id, &mut *transaction).await?)
+    } else {
+        None
+    };
+
+    transaction.commit().await?;
+
+    if let Some(index_project) = index_project {
+        indexing_queue.add(index_project);
+    } else if index_change == Some(IndexChange::Remove) {
+        delete_from_index(id.into(), &config).await?;
+    }
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// Acknowledges that a reindex has been started
+#[derive(Serialize)]
+pub struct ReindexStarted {
+    #[serde(with = "crate::util::time::rfc3339")]
+    pub started: chrono::DateTime<chrono::Utc>,
+}
+
+/// Rebuilds the search indices from the database, for when they have drifted. This runs
+/// in the background, since importing every project can take a while.
+#[post("reindex")]
+pub async fn reindex(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<SearchConfig>,
+    indexing_guard: web::Data<Arc<IndexingGuard>>,
+) -> Result<HttpResponse, ApiError> {
+    let user = check_is_moderator_from_headers(req.headers(), &**pool).await?;
+
+    if !indexing_guard.try_start() {
+        return Err(ApiError::Conflict(
+            "The local database is already being indexed".to_string(),
+        ));
+    }
+
+    log::info!("Reindexing local database, requested by {}", user.username);
+
+    let pool = (**pool).clone();
+    let config = (**config).clone();
+    let indexing_guard = (**indexing_guard).clone();
+
+    actix_rt::spawn(async move {
+        let settings = crate::search::indexing::IndexingSettings { index_local: true };
+
+        match crate::search::indexing::index_projects(pool, settings, &config).await {
+            Ok(()) => log::info!("Done reindexing local database"),
+            Err(err) => log::warn!("Reindexing local database failed: {:?}", err),
+        }
+
+        indexing_guard.finish();
+    });
+
+    Ok(HttpResponse::Accepted().json(ReindexStarted {
+        started: chrono::Utc::now(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::time::parse_timestamp;
+
+    fn queued_project(id: i64, published: &str) -> database::models::project_item::QueryProject {
+        let mut project = database::models::project_item::QueryProject::example(id);
+        project.inner.published = parse_timestamp(published).unwrap();
+        project.inner.updated = project.inner.published;
+        project.status = ProjectStatus::Processing;
+        project
+    }
+
+    fn queue() -> Vec<database::models::project_item::QueryProject> {
+        let mut projects = vec![
+            queued_project(5, "2021-03-01T00:00:00Z"),
+            queued_project(2, "2021-01-01T00:00:00Z"),
+            queued_project(4, "2021-02-01T00:00:00Z"),
+            queued_project(1, "2021-02-01T00:00:00Z"),
+            queued_project(3, "2021-01-01T00:00:00Z"),
+        ];
+        sort_oldest_first(&mut projects);
+        projects
+    }
+
+    #[test]
+    fn recent_projects_are_newest_first() {
+        let published = parse_timestamp("2021-02-01T00:00:00Z").unwrap();
+        let mut projects = queue();
+        for project in projects.iter_mut().filter(|x| x.inner.id.0 != 5) {
+            project.inner.published = published;
+        }
+
+        sort_newest_first(&mut projects);
+
+        let ids = projects.iter().map(|x| x.inner.id.0).collect::<Vec<_>>();
+        assert_eq!(ids, vec![5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn the_queue_is_oldest_first() {
+        let ids = queue().iter().map(|x| x.inner.id.0).collect::<Vec<_>>();
+
+        assert_eq!(ids, vec![2, 3, 1, 4, 5]);
+    }
+
+    #[test]
+    fn queue_offsets_are_clamped() {
+        let pagination = PaginationConfig {
+            default_page_size: 10,
+            max_page_size: 100,
+        };
+        let count = ResultCount {
+            count: Some(1000),
+            offset: usize::MAX,
+            status: None,
+        };
+
+        assert_eq!(queue_page(&count, &pagination), (100, i64::MAX));
+    }
+
+    #[test]
+    fn only_old_drafts_without_versions_are_stale() {
+        let cutoff = parse_timestamp("2021-04-01T00:00:00Z").unwrap();
+        let old = parse_timestamp("2021-01-01T00:00:00Z").unwrap();
+        let recent = parse_timestamp("2021-05-01T00:00:00Z").unwrap();
+
+        assert!(is_stale_draft(old, false, cutoff));
+        assert!(!is_stale_draft(old, true, cutoff));
+        assert!(!is_stale_draft(recent, false, cutoff));
+        assert!(!is_stale_draft(recent, true, cutoff));
+    }
+
+    #[test]
+    fn rejections_require_a_reason() {
+        assert!(matches!(
+            check_rejection_reason(""),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert!(matches!(
+            check_rejection_reason("   \n"),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert!(matches!(
+            check_rejection_reason(&"a".repeat(2001)),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert_eq!(
+            check_rejection_reason("  Missing a description \n").unwrap(),
+            "Missing a description"
+        );
+    }
+
+    #[test]
+    fn approving_a_project_indexes_it() {
+        assert_eq!(
+            index_change(&ProjectStatus::Processing, &ProjectStatus::Approved),
+            Some(IndexChange::Add)
+        );
+        assert_eq!(
+            index_change(&ProjectStatus::Rejected, &ProjectStatus::Approved),
+            Some(IndexChange::Add)
+        );
+        assert_eq!(
+            index_change(&ProjectStatus::Approved, &ProjectStatus::Approved),
+            None
+        );
+    }
+
+    #[test]
+    fn rejecting_a_project_removes_it_from_the_index() {
+        assert_eq!(
+            index_change(&ProjectStatus::Approved, &ProjectStatus::Rejected),
+            Some(IndexChange::Remove)
+        );
+        assert_eq!(
+            index_change(&ProjectStatus::Processing, &ProjectStatus::Rejected),
+            None
+        );
+    }
+}
@@ -1,9 +1,9 @@
-use crate::models::error::ApiError;
+use crate::models::error::{ApiError, ErrorCode};
 use actix_web::{HttpResponse, Responder};
 
 pub async fn not_found() -> impl Responder {
     let data = ApiError {
-        error: "not_found",
+        error: ErrorCode::NotFound,
         description: "the requested route does not exist",
     };
 
@@ -1,9 +1,10 @@
 use crate::database;
 use crate::models::ids::NotificationId;
 use crate::models::notifications::{Notification, NotificationAction};
+use crate::models::users::User;
 use crate::routes::ApiError;
 use crate::util::auth::get_user_from_headers;
-use actix_web::{delete, get, web, HttpRequest, HttpResponse};
+use actix_web::{delete, get, patch, web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
@@ -12,6 +13,11 @@ pub struct NotificationIds {
     pub ids: String,
 }
 
+/// Whether `user` may see or change a notification sent to `owner`
+fn can_access(user: &User, owner: database::models::ids::UserId) -> bool {
+    owner == user.id.into() || user.role.is_mod()
+}
+
 #[get("notifications")]
 pub async fn notifications_get(
     req: HttpRequest,
@@ -32,7 +38,7 @@ pub async fn notifications_get(
     let mut notifications: Vec<Notification> = Vec::new();
 
     for notification in notifications_data {
-        if notification.user_id == user.id.into() || user.role.is_mod() {
+        if can_access(&user, notification.user_id) {
             notifications.push(convert_notification(notification));
         }
     }
@@ -54,7 +60,7 @@ pub async fn notification_get(
         database::models::notification_item::Notification::get(id.into(), &**pool).await?;
 
     if let Some(data) = notification_data {
-        if user.id == data.user_id.into() || user.role.is_mod() {
+        if can_access(&user, data.user_id) {
             Ok(HttpResponse::Ok().json(convert_notification(data)))
         } else {
             Ok(HttpResponse::NotFound().body(""))
@@ -87,6 +93,34 @@ pub fn convert_notification(
     }
 }
 
+#[patch("{id}")]
+pub async fn notification_read(
+    req: HttpRequest,
+    info: web::Path<(NotificationId,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+
+    let id = info.into_inner().0;
+
+    let notification_data =
+        database::models::notification_item::Notification::get(id.into(), &**pool).await?;
+
+    if let Some(data) = notification_data {
+        if can_access(&user, data.user_id) {
+            database::models::notification_item::Notification::read(id.into(), &**pool).await?;
+
+            Ok(HttpResponse::NoContent().body(""))
+        } else {
+            Err(ApiError::Forbidden(
+                "You are not authorized to read this notification!".to_string(),
+            ))
+        }
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
 #[delete("{id}")]
 pub async fn notification_delete(
     req: HttpRequest,
@@ -101,7 +135,7 @@ pub async fn notification_delete(
         database::models::notification_item::Notification::get(id.into(), &**pool).await?;
 
     if let Some(data) = notification_data {
-        if data.user_id == user.id.into() || user.role.is_mod() {
+        if can_access(&user, data.user_id) {
             let mut transaction = pool.begin().await?;
 
             database::models::notification_item::Notification::remove(id.into(), &mut transaction)
@@ -111,7 +145,7 @@ pub async fn notification_delete(
 
             Ok(HttpResponse::NoContent().body(""))
         } else {
-            Err(ApiError::CustomAuthenticationError(
+            Err(ApiError::Forbidden(
                 "You are not authorized to delete this notification!".to_string(),
             ))
         }
@@ -142,7 +176,7 @@ pub async fn notifications_delete(
     let mut notifications: Vec<database::models::ids::NotificationId> = Vec::new();
 
     for notification in notifications_data {
-        if notification.user_id == user.id.into() || user.role.is_mod() {
+        if can_access(&user, notification.user_id) {
             notifications.push(notification.id);
         }
     }
@@ -154,3 +188,39 @@ pub async fn notifications_delete(
 
     Ok(HttpResponse::NoContent().body(""))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::users::{Role, UserId};
+
+    fn user(id: u64, role: Role) -> User {
+        User {
+            id: UserId(id),
+            github_id: None,
+            username: "user".to_string(),
+            name: None,
+            email: None,
+            avatar_url: None,
+            bio: None,
+            created: chrono::Utc::now(),
+            role,
+        }
+    }
+
+    #[test]
+    fn notifications_are_private_to_their_owner() {
+        let owner = database::models::ids::UserId(1);
+
+        assert!(can_access(&user(1, Role::Developer), owner));
+        assert!(!can_access(&user(2, Role::Developer), owner));
+    }
+
+    #[test]
+    fn moderators_can_access_any_notification() {
+        let owner = database::models::ids::UserId(1);
+
+        assert!(can_access(&user(2, Role::Moderator), owner));
+        assert!(can_access(&user(2, Role::Admin), owner));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::database::models;
 use crate::file_hosting::{FileHost, FileHostingError};
-use crate::models::error::ApiError;
+use crate::models::error::{ApiError, ErrorCode, ValidationApiError};
 use crate::models::projects::{
     DonationLink, License, ProjectId, ProjectStatus, SideType, VersionId,
 };
@@ -8,7 +8,11 @@ use crate::models::users::UserId;
 use crate::routes::version_creation::InitialVersionData;
 use crate::search::indexing::IndexingError;
 use crate::util::auth::{get_user_from_headers, AuthenticationError};
-use crate::util::validate::validation_errors_to_string;
+use crate::util::validate::{
+    check_category_count, max_categories, validation_errors_to_fields, validation_errors_to_string,
+    ProjectCheckConfig,
+};
+use crate::util::webhook::{send_moderation_webhook, WebhookConfig};
 use actix_multipart::{Field, Multipart};
 use actix_web::http::StatusCode;
 use actix_web::web::Data;
@@ -34,8 +38,8 @@ pub enum CreateError {
     MultipartError(actix_multipart::MultipartError),
     #[error("Error while parsing JSON: {0}")]
     SerDeError(#[from] serde_json::Error),
-    #[error("Error while validating input: {0}")]
-    ValidationError(String),
+    #[error("Error while validating input: {}", validation_errors_to_string(.0.clone(), None))]
+    ValidationError(validator::ValidationErrors),
     #[error("Error while uploading file")]
     FileHostingError(#[from] FileHostingError),
     #[error("Error while validating uploaded file: {0}")]
@@ -58,8 +62,10 @@ pub enum CreateError {
     SlugCollision,
     #[error("Authentication Error: {0}")]
     Unauthorized(#[from] AuthenticationError),
-    #[error("Authentication Error: {0}")]
-    CustomAuthenticationError(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Duplicate file: {0}")]
+    DuplicateFile(String),
 }
 
 impl actix_web::ResponseError for CreateError {
@@ -80,35 +86,47 @@ impl actix_web::ResponseError for CreateError {
             CreateError::InvalidCategory(..) => StatusCode::BAD_REQUEST,
             CreateError::InvalidFileType(..) => StatusCode::BAD_REQUEST,
             CreateError::Unauthorized(..) => StatusCode::UNAUTHORIZED,
-            CreateError::CustomAuthenticationError(..) => StatusCode::UNAUTHORIZED,
+            CreateError::Forbidden(..) => StatusCode::FORBIDDEN,
             CreateError::SlugCollision => StatusCode::BAD_REQUEST,
             CreateError::ValidationError(..) => StatusCode::BAD_REQUEST,
             CreateError::FileValidationError(..) => StatusCode::BAD_REQUEST,
+            CreateError::DuplicateFile(..) => StatusCode::CONFLICT,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
+        if let CreateError::ValidationError(errors) = self {
+            return HttpResponse::build(self.status_code()).json(ValidationApiError {
+                error: ApiError {
+                    error: ErrorCode::InvalidInput,
+                    description: &self.to_string(),
+                },
+                fields: validation_errors_to_fields(errors),
+            });
+        }
+
         HttpResponse::build(self.status_code()).json(ApiError {
             error: match self {
-                CreateError::EnvError(..) => "environment_error",
-                CreateError::SqlxDatabaseError(..) => "database_error",
-                CreateError::DatabaseError(..) => "database_error",
-                CreateError::IndexingError(..) => "indexing_error",
-                CreateError::FileHostingError(..) => "file_hosting_error",
-                CreateError::SerDeError(..) => "invalid_input",
-                CreateError::MultipartError(..) => "invalid_input",
-                CreateError::MissingValueError(..) => "invalid_input",
-                CreateError::InvalidIconFormat(..) => "invalid_input",
-                CreateError::InvalidInput(..) => "invalid_input",
-                CreateError::InvalidGameVersion(..) => "invalid_input",
-                CreateError::InvalidLoader(..) => "invalid_input",
-                CreateError::InvalidCategory(..) => "invalid_input",
-                CreateError::InvalidFileType(..) => "invalid_input",
-                CreateError::Unauthorized(..) => "unauthorized",
-                CreateError::CustomAuthenticationError(..) => "unauthorized",
-                CreateError::SlugCollision => "invalid_input",
-                CreateError::ValidationError(..) => "invalid_input",
-                CreateError::FileValidationError(..) => "invalid_input",
+                CreateError::EnvError(..) => ErrorCode::EnvironmentError,
+                CreateError::SqlxDatabaseError(..) => ErrorCode::DatabaseError,
+                CreateError::DatabaseError(..) => ErrorCode::DatabaseError,
+                CreateError::IndexingError(..) => ErrorCode::IndexingError,
+                CreateError::FileHostingError(..) => ErrorCode::FileHostingError,
+                CreateError::SerDeError(..) => ErrorCode::InvalidInput,
+                CreateError::MultipartError(..) => ErrorCode::InvalidInput,
+                CreateError::MissingValueError(..) => ErrorCode::InvalidInput,
+                CreateError::InvalidIconFormat(..) => ErrorCode::InvalidInput,
+                CreateError::InvalidInput(..) => ErrorCode::InvalidInput,
+                CreateError::InvalidGameVersion(..) => ErrorCode::InvalidInput,
+                CreateError::InvalidLoader(..) => ErrorCode::InvalidInput,
+                CreateError::InvalidCategory(..) => ErrorCode::InvalidInput,
+                CreateError::InvalidFileType(..) => ErrorCode::InvalidInput,
+                CreateError::Unauthorized(..) => ErrorCode::Unauthorized,
+                CreateError::Forbidden(..) => ErrorCode::Forbidden,
+                CreateError::SlugCollision => ErrorCode::InvalidInput,
+                CreateError::ValidationError(..) => ErrorCode::InvalidInput,
+                CreateError::FileValidationError(..) => ErrorCode::InvalidInput,
+                CreateError::DuplicateFile(..) => ErrorCode::DuplicateFile,
             },
             description: &self.to_string(),
         })
@@ -154,7 +172,6 @@ struct ProjectCreateData {
     #[validate]
     /// A list of initial versions to upload with the created project
     pub initial_versions: Vec<InitialVersionData>,
-    #[validate(length(max = 3))]
     /// A list of the categories that the project is in.
     pub categories: Vec<String>,
 
@@ -188,6 +205,22 @@ struct ProjectCreateData {
     pub gallery_items: Option<Vec<String>>,
 }
 
+impl ProjectCreateData {
+    /// Gets every external link of the project, including its donation links
+    fn external_urls(&self) -> impl Iterator<Item = &str> {
+        vec![
+            &self.issues_url,
+            &self.source_url,
+            &self.wiki_url,
+            &self.license_url,
+            &self.discord_url,
+        ]
+        .into_iter()
+        .filter_map(|x| x.as_deref())
+        .chain(self.donation_urls.iter().flatten().map(|x| &*x.url))
+    }
+}
+
 pub struct UploadedFile {
     pub file_id: String,
     pub file_name: String,
@@ -211,6 +244,8 @@ pub async fn project_create(
     payload: Multipart,
     client: Data<PgPool>,
     file_host: Data<Arc<dyn FileHost + Send + Sync>>,
+    check_config: Data<ProjectCheckConfig>,
+    webhook_config: Data<WebhookConfig>,
 ) -> Result<HttpResponse, CreateError> {
     let mut transaction = client.begin().await?;
     let mut uploaded_files = Vec::new();
@@ -221,24 +256,34 @@ pub async fn project_create(
         &mut transaction,
         &***file_host,
         &mut uploaded_files,
+        &check_config,
     )
     .await;
 
-    if result.is_err() {
-        let undo_result = undo_uploads(&***file_host, &uploaded_files).await;
-        let rollback_result = transaction.rollback().await;
+    match result {
+        Ok(project) => {
+            transaction.commit().await?;
+
+            // Only sent once the project has been created, so moderators aren't notified
+            // about projects which were rolled back
+            send_moderation_webhook(&webhook_config, project.clone());
 
-        if let Err(e) = undo_result {
-            return Err(e);
+            Ok(HttpResponse::Ok().json(project))
         }
-        if let Err(e) = rollback_result {
-            return Err(e.into());
+        Err(err) => {
+            let undo_result = undo_uploads(&***file_host, &uploaded_files).await;
+            let rollback_result = transaction.rollback().await;
+
+            if let Err(e) = undo_result {
+                return Err(e);
+            }
+            if let Err(e) = rollback_result {
+                return Err(e.into());
+            }
+
+            Err(err)
         }
-    } else {
-        transaction.commit().await?;
     }
-
-    result
 }
 
 /*
@@ -277,7 +322,8 @@ pub async fn project_create_inner(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     file_host: &dyn FileHost,
     uploaded_files: &mut Vec<UploadedFile>,
-) -> Result<HttpResponse, CreateError> {
+    check_config: &ProjectCheckConfig,
+) -> Result<crate::models::projects::Project, CreateError> {
     // The base URL for files uploaded to backblaze
     let cdn_url = dotenv::var("CDN_URL")?;
 
@@ -325,11 +371,34 @@ pub async fn project_create_inner(
         while let Some(chunk) = field.next().await {
             data.extend_from_slice(&chunk.map_err(CreateError::MultipartError)?);
         }
-        let create_data: ProjectCreateData = serde_json::from_slice(&data)?;
+        let mut create_data: ProjectCreateData = serde_json::from_slice(&data)?;
 
         create_data
             .validate()
-            .map_err(|err| CreateError::InvalidInput(validation_errors_to_string(err, None)))?;
+            .map_err(CreateError::ValidationError)?;
+
+        create_data.body =
+            crate::util::sanitize::sanitize_body(&create_data.body).ok_or_else(|| {
+                CreateError::InvalidInput(format!(
+                    "The body must be at most {} characters once sanitized",
+                    crate::util::sanitize::MAX_BODY_LENGTH
+                ))
+            })?;
+
+        if let Some(reason) = crate::util::validate::check_description_quality(
+            check_config,
+            &current_user.role,
+            &create_data.description,
+        ) {
+            return Err(CreateError::InvalidInput(reason));
+        }
+
+        if let Some(reason) = crate::util::validate::check_https_urls(
+            check_config.https_only_urls,
+            create_data.external_urls(),
+        ) {
+            return Err(CreateError::InvalidInput(reason));
+        }
 
         let slug_project_id_option: Option<ProjectId> =
             serde_json::from_str(&*format!("\"{}\"", create_data.slug)).ok();
@@ -495,6 +564,8 @@ pub async fn project_create_inner(
             version_data.game_versions.clone(),
             &all_game_versions,
             false,
+            version_data.file_types.get(name).copied(),
+            super::version_creation::max_file_size(),
         )
         .await?;
     }
@@ -513,6 +584,14 @@ pub async fn project_create_inner(
             }
         }
 
+        if let Some(reason) = check_category_count(
+            &project_create_data.project_type,
+            project_create_data.categories.len(),
+            max_categories(&project_create_data.project_type),
+        ) {
+            return Err(CreateError::InvalidInput(reason));
+        }
+
         // Convert the list of category names to actual categories
         let mut categories = Vec::with_capacity(project_create_data.categories.len());
         for category in &project_create_data.categories {
@@ -629,6 +708,7 @@ pub async fn project_create_inner(
                 .map(|x| models::project_item::GalleryItem {
                     project_id: project_id.into(),
                     image_url: x.to_string(),
+                    featured: false,
                 })
                 .collect(),
         };
@@ -669,20 +749,14 @@ pub async fn project_create_inner(
             wiki_url: project_builder.wiki_url.clone(),
             discord_url: project_builder.discord_url.clone(),
             donation_urls: project_create_data.donation_urls.clone(),
+            featured_gallery_url: project_builder.icon_url.clone(),
             gallery: gallery_urls,
+            sensitive: false,
         };
 
         let _project_id = project_builder.insert(&mut *transaction).await?;
 
-        if status == ProjectStatus::Processing {
-            if let Ok(webhook_url) = dotenv::var("MODERATION_DISCORD_WEBHOOK") {
-                crate::util::webhook::send_discord_webhook(response.clone(), webhook_url)
-                    .await
-                    .ok();
-            }
-        }
-
-        Ok(HttpResponse::Ok().json(response))
+        Ok(response)
     }
 }
 
@@ -703,7 +777,7 @@ async fn create_initial_version(
 
     version_data
         .validate()
-        .map_err(|err| CreateError::ValidationError(validation_errors_to_string(err, None)))?;
+        .map_err(CreateError::ValidationError)?;
 
     // Randomly generate a new id to be used for the version
     let version_id: VersionId = models::generate_version_id(transaction).await?.into();
@@ -751,6 +825,18 @@ async fn create_initial_version(
         })
         .collect::<Vec<_>>();
 
+    if let Some(missing) = models::version_item::DependencyBuilder::find_missing_version(
+        &dependencies,
+        &mut *transaction,
+    )
+    .await?
+    {
+        return Err(CreateError::InvalidInput(format!(
+            "The dependency version {} does not exist",
+            VersionId::from(missing)
+        )));
+    }
+
     let version = models::version_item::VersionBuilder {
         version_id: version_id.into(),
         project_id: project_id.into(),
@@ -792,11 +878,14 @@ async fn process_icon_upload(
             )));
         }
 
+        let icon = crate::util::icon::process_icon(data, file_extension, content_type)
+            .map_err(|err| CreateError::InvalidInput(err.to_string()))?;
+
         let upload_data = file_host
             .upload_file(
-                content_type,
-                &format!("data/{}/icon.{}", project_id, file_extension),
-                data,
+                icon.content_type,
+                &format!("data/{}/icon.{}", project_id, icon.extension),
+                icon.data,
             )
             .await?;
 
@@ -810,3 +899,64 @@ async fn process_icon_upload(
         Err(CreateError::InvalidIconFormat(file_extension.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::validate::check_https_urls;
+
+    fn create_data(url: &str) -> ProjectCreateData {
+        serde_json::from_value(serde_json::json!({
+            "title": "Example",
+            "slug": "example",
+            "description": "An example project",
+            "body": "",
+            "client_side": "required",
+            "server_side": "optional",
+            "initial_versions": [],
+            "categories": [],
+            "issues_url": format!("{}/issues", url),
+            "source_url": format!("{}/source", url),
+            "wiki_url": format!("{}/wiki", url),
+            "license_url": format!("{}/license", url),
+            "discord_url": format!("{}/discord", url),
+            "donation_urls": [{
+                "id": "patreon",
+                "platform": "Patreon",
+                "url": format!("{}/donate", url),
+            }],
+            "license_id": "MIT",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn every_external_link_is_checked() {
+        let data = create_data("https://example.com");
+
+        assert_eq!(data.external_urls().count(), 6);
+        assert!(check_https_urls(true, data.external_urls()).is_none());
+    }
+
+    #[test]
+    fn http_links_are_rejected_when_https_is_required() {
+        let data = create_data("http://example.com");
+
+        assert_eq!(
+            check_https_urls(true, data.external_urls()),
+            Some("The link http://example.com/issues must use HTTPS.".to_string())
+        );
+        assert!(check_https_urls(false, data.external_urls()).is_none());
+    }
+
+    #[test]
+    fn http_donation_links_are_rejected_when_https_is_required() {
+        let mut data = create_data("https://example.com");
+        data.donation_urls.as_mut().unwrap()[0].url = "http://example.com/donate".to_string();
+
+        assert_eq!(
+            check_https_urls(true, data.external_urls()),
+            Some("The link http://example.com/donate must use HTTPS.".to_string())
+        );
+    }
+}
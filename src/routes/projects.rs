@@ -1,21 +1,32 @@
 use crate::database;
-use crate::file_hosting::FileHost;
+use crate::database::models::notification_item::NotificationBuilder;
+use crate::file_hosting::{get_cdn_path, FileHost};
 use crate::models;
 use crate::models::projects::{
     DonationLink, License, ProjectId, ProjectStatus, RejectionReason, SearchRequest, SideType,
 };
 use crate::models::teams::Permissions;
 use crate::routes::ApiError;
+use crate::search::indexing::delete_from_index;
 use crate::search::indexing::queue::CreationQueue;
-use crate::search::{search_for_project, SearchConfig, SearchError};
+use crate::search::{
+    project_fields, search_for_project, ResultSearchProject, SearchConfig, SearchError,
+};
 use crate::util::auth::get_user_from_headers;
-use crate::util::validate::validation_errors_to_string;
+use crate::util::pagination::PaginationConfig;
+use crate::util::sanitize::{sanitize_body, MAX_BODY_LENGTH};
+use crate::util::validate::{
+    check_category_count, max_categories, validation_errors_to_string, ProjectCheckConfig,
+};
+use crate::util::webhook::{send_moderation_webhook, WebhookConfig};
 use actix_web::web::Data;
-use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use actix_web::{delete, get, patch, post, put, web, HttpRequest, HttpResponse};
+use async_trait::async_trait;
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::sync::Arc;
 use validator::Validate;
 
@@ -23,8 +34,16 @@ use validator::Validate;
 pub async fn project_search(
     web::Query(info): web::Query<SearchRequest>,
     config: web::Data<SearchConfig>,
+    pagination: web::Data<PaginationConfig>,
 ) -> Result<HttpResponse, SearchError> {
-    let results = search_for_project(&info, &**config).await?;
+    if let Some(fields) = info.fields.as_deref() {
+        let results = project_fields(&info, &**config, &**pagination, fields).await?;
+
+        return Ok(HttpResponse::Ok().json(results));
+    }
+
+    let results = search_for_project(&info, &**config, &**pagination).await?;
+
     Ok(HttpResponse::Ok().json(results))
 }
 
@@ -82,6 +101,117 @@ pub async fn projects_get(
     Ok(HttpResponse::Ok().json(projects))
 }
 
+#[derive(Deserialize)]
+pub struct ProjectListPagination {
+    pub count: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// The statuses of projects which are included in project listings. Unlisted projects
+/// are visible by URL, but shouldn't show up anywhere else.
+fn listed_statuses() -> Vec<String> {
+    vec![
+        ProjectStatus::Approved.to_string(),
+        ProjectStatus::Archived.to_string(),
+    ]
+}
+
+/// The orders projects can be listed in
+#[derive(Clone, Copy, PartialEq)]
+enum ProjectListOrder {
+    /// From newest to oldest
+    Published,
+    /// From most to least downloaded
+    Downloads,
+}
+
+impl ProjectListOrder {
+    /// Sorts projects in this order like the listing query
+    fn sort(self, projects: &mut [database::models::project_item::QueryProject]) {
+        projects.sort_by(|a, b| {
+            let ordering = match self {
+                ProjectListOrder::Published => std::cmp::Ordering::Equal,
+                ProjectListOrder::Downloads => b.inner.downloads.cmp(&a.inner.downloads),
+            };
+
+            ordering
+                .then(b.inner.published.cmp(&a.inner.published))
+                .then(b.inner.id.0.cmp(&a.inner.id.0))
+        });
+    }
+}
+
+/// Gets the limit and offset of a page of a project listing
+fn list_page(page: &ProjectListPagination, pagination: &PaginationConfig) -> (i64, i64) {
+    (
+        pagination.limit(page.count) as i64,
+        i64::try_from(page.offset).unwrap_or(i64::MAX),
+    )
+}
+
+/// Lists projects from newest to oldest
+#[get("projects/recent")]
+pub async fn projects_recent(
+    web::Query(page): web::Query<ProjectListPagination>,
+    pool: web::Data<PgPool>,
+    pagination: web::Data<PaginationConfig>,
+) -> Result<HttpResponse, ApiError> {
+    list_projects(ProjectListOrder::Published, &page, &**pool, &pagination).await
+}
+
+/// Lists projects from most to least downloaded
+#[get("projects/popular")]
+pub async fn projects_popular(
+    web::Query(page): web::Query<ProjectListPagination>,
+    pool: web::Data<PgPool>,
+    pagination: web::Data<PaginationConfig>,
+) -> Result<HttpResponse, ApiError> {
+    list_projects(ProjectListOrder::Downloads, &page, &**pool, &pagination).await
+}
+
+async fn list_projects(
+    order: ProjectListOrder,
+    page: &ProjectListPagination,
+    pool: &PgPool,
+    pagination: &PaginationConfig,
+) -> Result<HttpResponse, ApiError> {
+    use futures::stream::TryStreamExt;
+
+    let (limit, offset) = list_page(page, pagination);
+
+    // Ties are broken by publish date and then by ID, so pages don't overlap
+    let project_ids = sqlx::query!(
+        "
+        SELECT m.id FROM mods m
+        INNER JOIN statuses s ON s.id = m.status
+        WHERE s.status = ANY($1)
+        ORDER BY CASE WHEN $4 THEN m.downloads ELSE 0 END DESC, m.published DESC, m.id DESC
+        LIMIT $2 OFFSET $3
+        ",
+        &listed_statuses(),
+        limit,
+        offset,
+        order == ProjectListOrder::Downloads,
+    )
+    .fetch_many(pool)
+    .try_filter_map(|e| async { Ok(e.right().map(|m| database::models::ProjectId(m.id))) })
+    .try_collect::<Vec<database::models::ProjectId>>()
+    .await?;
+
+    let mut projects = database::models::Project::get_many_full(project_ids, pool).await?;
+
+    // get_many_full doesn't preserve the ordering from the query
+    order.sort(&mut projects);
+
+    let projects = projects
+        .into_iter()
+        .map(convert_project)
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(projects))
+}
+
 #[get("{id}")]
 pub async fn project_get(
     req: HttpRequest,
@@ -97,31 +227,56 @@ pub async fn project_get(
     let user_option = get_user_from_headers(req.headers(), &**pool).await.ok();
 
     if let Some(data) = project_data {
-        let mut authorized = !data.status.is_hidden();
+        let is_mod = user_option
+            .as_ref()
+            .map(|x| x.role.is_mod())
+            .unwrap_or(false);
 
-        if let Some(user) = user_option {
-            if !authorized {
-                if user.role.is_mod() {
-                    authorized = true;
-                } else {
-                    let user_id: database::models::ids::UserId = user.id.into();
+        // Membership only matters for hidden projects, so it isn't looked up otherwise
+        let is_member = match &user_option {
+            Some(user) if data.status.is_hidden() && !is_mod => {
+                let user_id: database::models::ids::UserId = user.id.into();
 
-                    let project_exists = sqlx::query!(
-                        "SELECT EXISTS(SELECT 1 FROM team_members WHERE team_id = $1 AND user_id = $2)",
-                        data.inner.team_id as database::models::ids::TeamId,
-                        user_id as database::models::ids::UserId,
-                    )
-                    .fetch_one(&**pool)
-                    .await?
-                    .exists;
+                sqlx::query!(
+                    "SELECT EXISTS(SELECT 1 FROM team_members WHERE team_id = $1 AND user_id = $2)",
+                    data.inner.team_id as database::models::ids::TeamId,
+                    user_id as database::models::ids::UserId,
+                )
+                .fetch_one(&**pool)
+                .await?
+                .exists
+                .unwrap_or(false)
+            }
+            _ => false,
+        };
 
-                    authorized = project_exists.unwrap_or(false);
+        if can_see_project(&data.status, is_mod, is_member) {
+            let id = data.inner.id;
+            let mut project = convert_project(data);
+
+            let accept_language = req
+                .headers()
+                .get(actix_web::http::header::ACCEPT_LANGUAGE)
+                .and_then(|x| x.to_str().ok());
+
+            if let Some(accept_language) = accept_language {
+                let translations =
+                    database::models::project_item::ProjectTranslation::get_project(id, &**pool)
+                        .await?;
+
+                if let Some(translation) =
+                    best_translation(accept_language, DEFAULT_LOCALE, &translations)
+                {
+                    project.title = translation.title.clone();
+                    project.description = translation.description.clone();
                 }
             }
-        }
 
-        if authorized {
-            return Ok(HttpResponse::Ok().json(convert_project(data)));
+            // The title and description depend on the Accept-Language header, so caches
+            // must not serve one language's response for another
+            return Ok(HttpResponse::Ok()
+                .header(actix_web::http::header::VARY, "Accept-Language")
+                .json(project));
         }
 
         Ok(HttpResponse::NotFound().body(""))
@@ -130,101 +285,879 @@ pub async fn project_get(
     }
 }
 
-struct DependencyInfo {
-    pub project: Option<models::projects::Project>,
-    pub version: Option<models::projects::Version>,
+/// Whether a user can see a project with `status`. Hidden projects are only shown to
+/// moderators and members of the project's team.
+pub fn can_see_project(status: &ProjectStatus, is_mod: bool, is_member: bool) -> bool {
+    !status.is_hidden() || is_mod || is_member
 }
 
-#[get("dependencies")]
-pub async fn dependency_list(
+#[get("{id}/donations")]
+pub async fn project_donations_get(
+    req: HttpRequest,
     info: web::Path<(String,)>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, ApiError> {
-    let string = info.into_inner().0;
+    let project = match get_visible_project(&req, info.into_inner().0, &**pool).await? {
+        Some(project) => project,
+        None => return Ok(HttpResponse::NotFound().body("")),
+    };
 
-    let result = database::models::Project::get_from_slug_or_project_id(string, &**pool).await?;
+    use futures::stream::TryStreamExt;
 
-    if let Some(project) = result {
-        let id = project.id;
+    let donations = sqlx::query!(
+        "
+        SELECT dp.short, dp.name, md.url FROM mods_donations md
+        INNER JOIN donation_platforms dp ON dp.id = md.joining_platform_id
+        WHERE md.joining_mod_id = $1
+        ",
+        project.id as database::models::ids::ProjectId,
+    )
+    .fetch_many(&**pool)
+    .try_filter_map(|e| async {
+        Ok(e.right().map(|d| DonationLink {
+            id: d.short,
+            platform: d.name,
+            url: d.url,
+        }))
+    })
+    .try_collect::<Vec<DonationLink>>()
+    .await?;
 
-        use futures::stream::TryStreamExt;
+    Ok(HttpResponse::Ok().json(donations))
+}
 
-        let dependencies = sqlx::query!(
-            "
-            SELECT d.dependent_id, d.dependency_id, d.mod_dependency_id
-            FROM versions v
-            INNER JOIN dependencies d ON d.dependent_id = v.id
-            WHERE v.mod_id = $1
-            ",
-            id as database::models::ProjectId
-        )
-        .fetch_many(&**pool)
-        .try_filter_map(|e| async {
-            Ok(e.right().map(|x| {
-                (
-                    database::models::VersionId(x.dependent_id),
-                    x.dependency_id.map(database::models::VersionId),
-                    x.mod_dependency_id.map(database::models::ProjectId),
-                )
-            }))
+#[get("{id}/loaders")]
+pub async fn project_loaders_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let project = match get_visible_project(&req, info.into_inner().0, &**pool).await? {
+        Some(project) => project,
+        None => return Ok(HttpResponse::NotFound().body("")),
+    };
+
+    use futures::stream::TryStreamExt;
+
+    let loaders = sqlx::query!(
+        "
+        SELECT DISTINCT l.loader FROM loaders l
+        INNER JOIN loaders_versions lv ON lv.loader_id = l.id
+        INNER JOIN versions v ON v.id = lv.version_id
+        WHERE v.mod_id = $1
+        ORDER BY l.loader
+        ",
+        project.id as database::models::ids::ProjectId,
+    )
+    .fetch_many(&**pool)
+    .try_filter_map(|e| async { Ok(e.right().map(|c| models::projects::Loader(c.loader))) })
+    .try_collect::<Vec<models::projects::Loader>>()
+    .await?;
+
+    Ok(HttpResponse::Ok().json(loaders))
+}
+
+#[get("{id}/game_versions")]
+pub async fn project_game_versions_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let project = match get_visible_project(&req, info.into_inner().0, &**pool).await? {
+        Some(project) => project,
+        None => return Ok(HttpResponse::NotFound().body("")),
+    };
+
+    use futures::stream::TryStreamExt;
+
+    let game_versions = sqlx::query!(
+        "
+        SELECT DISTINCT gv.version, gv.created FROM game_versions gv
+        INNER JOIN game_versions_versions gvv ON gvv.game_version_id = gv.id
+        INNER JOIN versions v ON v.id = gvv.joining_version_id
+        WHERE v.mod_id = $1
+        ORDER BY gv.created DESC
+        ",
+        project.id as database::models::ids::ProjectId,
+    )
+    .fetch_many(&**pool)
+    .try_filter_map(|e| async { Ok(e.right().map(|c| models::projects::GameVersion(c.version))) })
+    .try_collect::<Vec<models::projects::GameVersion>>()
+    .await?;
+
+    Ok(HttpResponse::Ok().json(game_versions))
+}
+
+#[get("{id}/channel_counts")]
+pub async fn project_channel_counts_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let project = match get_visible_project(&req, info.into_inner().0, &**pool).await? {
+        Some(project) => project,
+        None => return Ok(HttpResponse::NotFound().body("")),
+    };
+
+    use futures::stream::TryStreamExt;
+
+    let channels = sqlx::query!(
+        "
+        SELECT rc.channel FROM versions v
+        INNER JOIN release_channels rc ON rc.id = v.release_channel
+        WHERE v.mod_id = $1
+        ",
+        project.id as database::models::ids::ProjectId,
+    )
+    .fetch_many(&**pool)
+    .try_filter_map(|e| async { Ok(e.right().map(|c| c.channel)) });
+
+    let counts = count_channels(channels).await?;
+
+    Ok(HttpResponse::Ok().json(counts))
+}
+
+/// Counts how many versions were released on each channel, given the channel of every version
+async fn count_channels<S>(channels: S) -> Result<HashMap<String, u32>, sqlx::Error>
+where
+    S: futures::Stream<Item = Result<String, sqlx::Error>>,
+{
+    use futures::stream::TryStreamExt;
+
+    channels
+        .try_fold(HashMap::new(), |mut counts, channel| async move {
+            *counts.entry(channel).or_insert(0) += 1;
+            Ok(counts)
         })
-        .try_collect::<Vec<(
-            database::models::VersionId,
-            Option<database::models::VersionId>,
-            Option<database::models::ProjectId>,
-        )>>()
-        .await?;
+        .await
+}
 
-        let projects = database::Project::get_many_full(
-            dependencies.iter().map(|x| x.2).flatten().collect(),
-            &**pool,
-        )
-        .await?;
-        let versions = database::Version::get_many_full(
-            dependencies.iter().map(|x| x.1).flatten().collect(),
-            &**pool,
-        )
-        .await?;
+/// A file of a project, as listed in its file manifest
+#[derive(Serialize)]
+pub struct ManifestFile {
+    pub version_id: models::ids::VersionId,
+    pub filename: String,
+    pub size: Option<u64>,
+    pub hashes: HashMap<String, String>,
+}
 
-        let mut response: HashMap<models::projects::VersionId, DependencyInfo> = HashMap::new();
+#[get("{id}/file_manifest")]
+pub async fn project_file_manifest_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let project = match get_visible_project(&req, info.into_inner().0, &**pool).await? {
+        Some(project) => project,
+        None => return Ok(HttpResponse::NotFound().body("")),
+    };
+
+    let (mut sender, receiver) = futures::channel::mpsc::channel(16);
+    let pool = (**pool).clone();
+    let project_id = project.id;
+
+    // Projects with many versions have large manifests, so files are sent as they
+    // are read instead of being collected first
+    actix_rt::spawn(async move {
+        if let Err(err) = send_file_manifest(project_id, &pool, &mut sender).await {
+            use futures::SinkExt;
+
+            let _ = sender.send(Err(err)).await;
+        }
+    });
 
-        for dependency in dependencies {
-            response.insert(
-                dependency.0.into(),
-                DependencyInfo {
-                    project: if let Some(id) = dependency.2 {
-                        projects
-                            .iter()
-                            .find(|x| x.inner.id == id)
-                            .map(|x| convert_project(x.clone()))
-                    } else {
-                        None
-                    },
-                    version: if let Some(id) = dependency.1 {
-                        versions
-                            .iter()
-                            .find(|x| x.id == id)
-                            .map(|x| super::versions::convert_version(x.clone()))
-                    } else {
-                        None
-                    },
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .streaming(receiver))
+}
+
+type ManifestSender = futures::channel::mpsc::Sender<Result<web::Bytes, ApiError>>;
+
+/// Sends the files of a project as a JSON array, stopping early if the client disconnects
+async fn send_file_manifest(
+    project_id: database::models::ids::ProjectId,
+    pool: &PgPool,
+    sender: &mut ManifestSender,
+) -> Result<(), ApiError> {
+    use futures::stream::TryStreamExt;
+
+    // The rows are ordered by file, so each file's hashes are next to each other
+    let rows = sqlx::query!(
+        "
+        SELECT f.id, f.version_id, f.filename, f.size, h.algorithm \"algorithm?\", h.hash \"hash?\"
+        FROM files f
+        INNER JOIN versions v ON v.id = f.version_id
+        LEFT OUTER JOIN hashes h ON h.file_id = f.id
+        WHERE v.mod_id = $1
+        ORDER BY v.date_published ASC, f.id ASC
+        ",
+        project_id as database::models::ids::ProjectId,
+    )
+    .fetch(pool)
+    .map_ok(|row| ManifestRow {
+        file_id: row.id,
+        version_id: row.version_id,
+        filename: row.filename,
+        size: row.size,
+        algorithm: row.algorithm,
+        hash: row.hash,
+    })
+    .map_err(ApiError::from);
+
+    send_manifest_rows(rows, sender).await
+}
+
+/// A hash of a file of a project, or the file alone if it has no hashes
+struct ManifestRow {
+    file_id: i64,
+    version_id: i64,
+    filename: String,
+    size: Option<i64>,
+    algorithm: Option<String>,
+    hash: Option<Vec<u8>>,
+}
+
+/// Groups rows ordered by file into the files of a manifest and sends them as a JSON array
+async fn send_manifest_rows<S>(rows: S, sender: &mut ManifestSender) -> Result<(), ApiError>
+where
+    S: futures::Stream<Item = Result<ManifestRow, ApiError>>,
+{
+    use futures::stream::TryStreamExt;
+    use futures::SinkExt;
+
+    if sender
+        .send(Ok(web::Bytes::from_static(b"[")))
+        .await
+        .is_err()
+    {
+        return Ok(());
+    }
+
+    let mut current: Option<(i64, ManifestFile)> = None;
+    let mut first = true;
+
+    futures::pin_mut!(rows);
+
+    while let Some(row) = rows.try_next().await? {
+        if current.as_ref().map(|x| x.0) != Some(row.file_id) {
+            if let Some((_, file)) = current.take() {
+                if !send_manifest_file(sender, &file, &mut first).await? {
+                    return Ok(());
+                }
+            }
+
+            current = Some((
+                row.file_id,
+                ManifestFile {
+                    version_id: database::models::ids::VersionId(row.version_id).into(),
+                    filename: row.filename,
+                    size: row.size.and_then(|x| u64::try_from(x).ok()),
+                    hashes: HashMap::new(),
                 },
-            );
+            ));
         }
 
-        Ok(HttpResponse::NotFound().body(""))
+        // There is always an entry for the file of the current row at this point
+        if let (Some((_, file)), Some(algorithm), Some(hash)) =
+            (current.as_mut(), row.algorithm, row.hash)
+        {
+            file.hashes.insert(algorithm, hex::encode(hash));
+        }
+    }
+
+    if let Some((_, file)) = current {
+        if !send_manifest_file(sender, &file, &mut first).await? {
+            return Ok(());
+        }
+    }
+
+    let _ = sender.send(Ok(web::Bytes::from_static(b"]"))).await;
+
+    Ok(())
+}
+
+/// Sends a single file of a manifest, returning whether the client is still connected
+async fn send_manifest_file(
+    sender: &mut ManifestSender,
+    file: &ManifestFile,
+    first: &mut bool,
+) -> Result<bool, ApiError> {
+    use futures::SinkExt;
+
+    let mut chunk = if *first { Vec::new() } else { vec![b','] };
+    *first = false;
+
+    serde_json::to_writer(&mut chunk, file)?;
+
+    Ok(sender.send(Ok(web::Bytes::from(chunk))).await.is_ok())
+}
+
+/// The information needed to show a preview of a project, such as for link embeds
+#[derive(Serialize)]
+pub struct ProjectCard {
+    pub title: String,
+    pub description: String,
+    pub image_url: Option<String>,
+}
+
+#[get("{id}/card")]
+pub async fn project_card_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let project = match get_visible_project(&req, info.into_inner().0, &**pool).await? {
+        Some(project) => project,
+        None => return Ok(HttpResponse::NotFound().body("")),
+    };
+
+    let featured_gallery_url = sqlx::query!(
+        "
+        SELECT image_url FROM mods_gallery
+        WHERE mod_id = $1 AND featured = TRUE
+        ORDER BY id
+        LIMIT 1
+        ",
+        project.id as database::models::ids::ProjectId,
+    )
+    .fetch_optional(&**pool)
+    .await?
+    .map(|x| x.image_url);
+
+    Ok(HttpResponse::Ok().json(ProjectCard {
+        title: project.title,
+        description: project.description,
+        image_url: card_image_url(featured_gallery_url, project.icon_url),
+    }))
+}
+
+/// Gets the image shown on a project's card, which is its featured gallery image if it has
+/// one and otherwise its icon
+fn card_image_url(
+    featured_gallery_url: Option<String>,
+    icon_url: Option<String>,
+) -> Option<String> {
+    featured_gallery_url.or(icon_url)
+}
+
+/// Gets a project from its id or slug, treating it as missing if the user making the request
+/// isn't allowed to see it. Hidden projects are only visible to moderators and members of the
+/// project's team.
+async fn get_visible_project(
+    req: &HttpRequest,
+    id_or_slug: String,
+    pool: &PgPool,
+) -> Result<Option<database::models::Project>, ApiError> {
+    let project =
+        match database::models::Project::get_from_slug_or_project_id(id_or_slug, pool).await? {
+            Some(project) => project,
+            None => return Ok(None),
+        };
+
+    let status = sqlx::query!(
+        "SELECT status FROM statuses WHERE id = $1",
+        project.status as database::models::ids::StatusId,
+    )
+    .fetch_one(pool)
+    .await?
+    .status;
+
+    if !ProjectStatus::from_str(&status).is_hidden() {
+        return Ok(Some(project));
+    }
+
+    let user = match get_user_from_headers(req.headers(), pool).await.ok() {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+
+    if user.role.is_mod() {
+        return Ok(Some(project));
+    }
+
+    let user_id: database::models::ids::UserId = user.id.into();
+
+    let is_member = sqlx::query!(
+        "SELECT EXISTS(SELECT 1 FROM team_members WHERE team_id = $1 AND user_id = $2)",
+        project.team_id as database::models::ids::TeamId,
+        user_id as database::models::ids::UserId,
+    )
+    .fetch_one(pool)
+    .await?
+    .exists;
+
+    if is_member.unwrap_or(false) {
+        Ok(Some(project))
     } else {
-        Ok(HttpResponse::NotFound().body(""))
+        Ok(None)
     }
 }
 
-pub fn convert_project(
-    data: database::models::project_item::QueryProject,
-) -> models::projects::Project {
-    let m = data.inner;
+#[derive(Serialize)]
+pub struct ProjectSummaryStats {
+    pub downloads: u32,
+    pub followers: u32,
+    pub versions: u32,
+    #[serde(with = "crate::util::time::rfc3339")]
+    pub updated: chrono::DateTime<chrono::Utc>,
+}
 
-    models::projects::Project {
-        id: m.id.into(),
+impl ProjectSummaryStats {
+    /// Builds the statistics from the counts in the database, which are never negative
+    fn from_counts(
+        downloads: i32,
+        followers: i32,
+        versions: Option<i64>,
+        updated: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        ProjectSummaryStats {
+            downloads: u32::try_from(downloads).unwrap_or(0),
+            followers: u32::try_from(followers).unwrap_or(0),
+            versions: versions.and_then(|x| u32::try_from(x).ok()).unwrap_or(0),
+            updated,
+        }
+    }
+}
+
+#[get("{id}/summary_stats")]
+pub async fn project_summary_stats_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let string = info.into_inner().0;
+
+    let project_item =
+        database::models::Project::get_from_slug_or_project_id(string, &**pool).await?;
+
+    if let Some(project) = project_item {
+        if !user.role.is_mod() {
+            database::models::TeamMember::get_from_user_id(
+                project.team_id,
+                user.id.into(),
+                &**pool,
+            )
+            .await?
+            .ok_or_else(|| {
+                ApiError::Forbidden(
+                    "You don't have permission to view this project's statistics!".to_string(),
+                )
+            })?;
+        }
+
+        let stats = sqlx::query!(
+            "
+            SELECT m.downloads, m.follows, m.updated, COUNT(v.id) version_count
+            FROM mods m
+            LEFT OUTER JOIN versions v ON v.mod_id = m.id
+            WHERE m.id = $1
+            GROUP BY m.id
+            ",
+            project.id as database::models::ids::ProjectId,
+        )
+        .fetch_one(&**pool)
+        .await?;
+
+        Ok(HttpResponse::Ok().json(ProjectSummaryStats::from_counts(
+            stats.downloads,
+            stats.follows,
+            stats.version_count,
+            stats.updated,
+        )))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DownloadAnalyticsQuery {
+    /// The size of each bucket of downloads, either `day` or `week`
+    pub resolution: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DownloadBucket {
+    /// The start of the bucket
+    #[serde(with = "crate::util::time::rfc3339")]
+    pub date: chrono::DateTime<chrono::Utc>,
+    pub downloads: u32,
+}
+
+/// The size of each bucket of download analytics
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DownloadResolution {
+    Day,
+    Week,
+}
+
+impl DownloadResolution {
+    /// Parses a resolution, defaulting to a day
+    pub fn parse(resolution: Option<&str>) -> Result<Self, ApiError> {
+        match resolution {
+            None | Some("day") => Ok(DownloadResolution::Day),
+            Some("week") => Ok(DownloadResolution::Week),
+            Some(resolution) => Err(ApiError::InvalidInputError(format!(
+                "Invalid resolution {}, expected `day` or `week`",
+                resolution
+            ))),
+        }
+    }
+}
+
+/// Groups the downloads of each day, ordered by day, into buckets of the given resolution.
+/// Weeks start on Monday, like they do for `date_trunc`.
+fn bucket_downloads(
+    days: Vec<(chrono::DateTime<chrono::Utc>, i64)>,
+    resolution: DownloadResolution,
+) -> Vec<DownloadBucket> {
+    use chrono::Datelike;
+
+    let mut buckets: Vec<DownloadBucket> = Vec::new();
+
+    for (day, downloads) in days {
+        let date = match resolution {
+            DownloadResolution::Day => day,
+            DownloadResolution::Week => {
+                day - chrono::Duration::days(day.weekday().num_days_from_monday() as i64)
+            }
+        };
+        let downloads = u32::try_from(downloads).unwrap_or(u32::MAX);
+
+        match buckets.last_mut() {
+            Some(bucket) if bucket.date == date => {
+                bucket.downloads = bucket.downloads.saturating_add(downloads)
+            }
+            _ => buckets.push(DownloadBucket { date, downloads }),
+        }
+    }
+
+    buckets
+}
+
+#[get("{id}/downloads")]
+pub async fn project_downloads_get(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    web::Query(query): web::Query<DownloadAnalyticsQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let string = info.into_inner().0;
+
+    let resolution = DownloadResolution::parse(query.resolution.as_deref())?;
+
+    let (from, to) = crate::util::time::TimestampRange {
+        start: query.from.clone(),
+        end: query.to.clone(),
+    }
+    .parse()
+    .map_err(|err| ApiError::InvalidInputError(err.to_string()))?;
+
+    let project_item =
+        database::models::Project::get_from_slug_or_project_id(string, &**pool).await?;
+
+    if let Some(project) = project_item {
+        if !user.role.is_mod() {
+            database::models::TeamMember::get_from_user_id(
+                project.team_id,
+                user.id.into(),
+                &**pool,
+            )
+            .await?
+            .ok_or_else(|| {
+                ApiError::Forbidden(
+                    "You don't have permission to view this project's statistics!".to_string(),
+                )
+            })?;
+        }
+
+        // Downloads are counted by day, and the days are then grouped into weeks if needed
+        let days = sqlx::query!(
+            "
+            SELECT date_trunc('day', d.date) \"date!\", COUNT(d.id) \"downloads!\"
+            FROM downloads d
+            INNER JOIN versions v ON v.id = d.version_id
+            WHERE v.mod_id = $1
+            AND ($2::timestamptz IS NULL OR d.date >= $2)
+            AND ($3::timestamptz IS NULL OR d.date < $3)
+            GROUP BY 1
+            ORDER BY 1
+            ",
+            project.id as database::models::ids::ProjectId,
+            from,
+            to,
+        )
+        .fetch_all(&**pool)
+        .await?
+        .into_iter()
+        .map(|x| (x.date, x.downloads))
+        .collect::<Vec<_>>();
+
+        Ok(HttpResponse::Ok().json(bucket_downloads(days, resolution)))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
+// The number of projects returned by the related projects endpoint
+const RELATED_PROJECTS_COUNT: usize = 5;
+
+/// Orders projects by how many categories they share with a project, then by downloads,
+/// keeping only the most related ones
+fn rank_related_projects(
+    mut projects: Vec<ResultSearchProject>,
+    categories: &[String],
+) -> Vec<ResultSearchProject> {
+    let overlap = |project: &ResultSearchProject| {
+        project
+            .categories
+            .iter()
+            .filter(|x| categories.contains(x))
+            .count()
+    };
+
+    projects.sort_by(|a, b| {
+        overlap(b)
+            .cmp(&overlap(a))
+            .then(b.downloads.cmp(&a.downloads))
+    });
+    projects.truncate(RELATED_PROJECTS_COUNT);
+
+    projects
+}
+
+#[get("{id}/related")]
+pub async fn project_related_get(
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    config: web::Data<SearchConfig>,
+    pagination: web::Data<PaginationConfig>,
+) -> Result<HttpResponse, ApiError> {
+    let string = info.into_inner().0;
+
+    let project_data =
+        database::models::Project::get_full_from_slug_or_project_id(string.clone(), &**pool)
+            .await?;
+
+    if let Some(data) = project_data {
+        if data.status.is_hidden() {
+            return Ok(HttpResponse::NotFound().body(""));
+        }
+
+        if data.categories.is_empty() {
+            return Ok(HttpResponse::Ok().json(Vec::<ResultSearchProject>::new()));
+        }
+
+        let id: ProjectId = data.inner.id.into();
+
+        let facets = vec![
+            data.categories
+                .iter()
+                .map(|x| format!("categories:{}", x))
+                .collect::<Vec<_>>(),
+            vec![format!("project_type:{}", data.project_type)],
+        ];
+
+        // Fetch more results than needed, since they are ranked by category overlap here
+        let request = SearchRequest {
+            query: None,
+            facets: Some(serde_json::to_string(&facets)?),
+            filters: None,
+            version: None,
+            offset: None,
+            index: Some("downloads".to_string()),
+            sort: None,
+            limit: Some(pagination.max_page_size.to_string()),
+            excluded_ids: Some(serde_json::to_string(&vec![id])?),
+            show_sensitive: None,
+            exact_first: None,
+            fields: None,
+            client_side: None,
+            server_side: None,
+        };
+
+        let results = search_for_project(&request, &**config, &**pagination)
+            .await
+            .map_err(|err| match err {
+                SearchError::MeiliSearchError(err) => ApiError::SearchError(err),
+                err => ApiError::InvalidInputError(err.to_string()),
+            })?;
+
+        Ok(HttpResponse::Ok().json(rank_related_projects(results.hits, &data.categories)))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
+/// The locale of a project's own title and description
+const DEFAULT_LOCALE: &str = "en";
+
+/// Picks the translation best matching an `Accept-Language` header. Exact locale
+/// matches are preferred, falling back to matching only the primary language subtag.
+/// If the default locale is preferred over the translations, `None` is returned so
+/// that the project's own text is used.
+fn best_translation<'a>(
+    accept_language: &str,
+    default_locale: &str,
+    translations: &'a [database::models::project_item::ProjectTranslation],
+) -> Option<&'a database::models::project_item::ProjectTranslation> {
+    let mut languages = accept_language
+        .split(',')
+        .filter_map(|x| {
+            let mut parts = x.trim().split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if tag.is_empty() || tag == "*" || quality <= 0.0 {
+                None
+            } else {
+                Some((tag, quality))
+            }
+        })
+        .collect::<Vec<(&str, f32)>>();
+
+    languages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    let default_primary = default_locale.split('-').next().unwrap_or(default_locale);
+
+    for (tag, _) in languages {
+        let exact = translations
+            .iter()
+            .find(|x| x.locale.eq_ignore_ascii_case(tag));
+
+        if exact.is_some() {
+            return exact;
+        }
+
+        let primary = tag.split('-').next().unwrap_or(tag);
+
+        if primary.eq_ignore_ascii_case(default_primary) {
+            return None;
+        }
+        let partial = translations.iter().find(|x| {
+            x.locale
+                .split('-')
+                .next()
+                .map(|x| x.eq_ignore_ascii_case(primary))
+                .unwrap_or(false)
+        });
+
+        if partial.is_some() {
+            return partial;
+        }
+    }
+
+    None
+}
+
+struct DependencyInfo {
+    pub project: Option<models::projects::Project>,
+    pub version: Option<models::projects::Version>,
+}
+
+#[get("dependencies")]
+pub async fn dependency_list(
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let string = info.into_inner().0;
+
+    let result = database::models::Project::get_from_slug_or_project_id(string, &**pool).await?;
+
+    if let Some(project) = result {
+        let id = project.id;
+
+        use futures::stream::TryStreamExt;
+
+        let dependencies = sqlx::query!(
+            "
+            SELECT d.dependent_id, d.dependency_id, d.mod_dependency_id
+            FROM versions v
+            INNER JOIN dependencies d ON d.dependent_id = v.id
+            WHERE v.mod_id = $1
+            ",
+            id as database::models::ProjectId
+        )
+        .fetch_many(&**pool)
+        .try_filter_map(|e| async {
+            Ok(e.right().map(|x| {
+                (
+                    database::models::VersionId(x.dependent_id),
+                    x.dependency_id.map(database::models::VersionId),
+                    x.mod_dependency_id.map(database::models::ProjectId),
+                )
+            }))
+        })
+        .try_collect::<Vec<(
+            database::models::VersionId,
+            Option<database::models::VersionId>,
+            Option<database::models::ProjectId>,
+        )>>()
+        .await?;
+
+        let projects = database::Project::get_many_full(
+            dependencies.iter().map(|x| x.2).flatten().collect(),
+            &**pool,
+        )
+        .await?;
+        let versions = database::Version::get_many_full(
+            dependencies.iter().map(|x| x.1).flatten().collect(),
+            &**pool,
+        )
+        .await?;
+
+        let mut response: HashMap<models::projects::VersionId, DependencyInfo> = HashMap::new();
+
+        for dependency in dependencies {
+            response.insert(
+                dependency.0.into(),
+                DependencyInfo {
+                    project: if let Some(id) = dependency.2 {
+                        projects
+                            .iter()
+                            .find(|x| x.inner.id == id)
+                            .map(|x| convert_project(x.clone()))
+                    } else {
+                        None
+                    },
+                    version: if let Some(id) = dependency.1 {
+                        versions
+                            .iter()
+                            .find(|x| x.id == id)
+                            .map(|x| super::versions::convert_version(x.clone()))
+                    } else {
+                        None
+                    },
+                },
+            );
+        }
+
+        Ok(HttpResponse::NotFound().body(""))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
+pub fn convert_project(
+    data: database::models::project_item::QueryProject,
+) -> models::projects::Project {
+    let m = data.inner;
+
+    let featured_gallery_url = data
+        .gallery_items
+        .iter()
+        .find(|x| x.featured)
+        .map(|x| x.image_url.clone())
+        .or_else(|| m.icon_url.clone());
+
+    models::projects::Project {
+        id: m.id.into(),
         slug: m.slug,
         project_type: data.project_type,
         team: m.team_id.into(),
@@ -274,6 +1207,8 @@ pub fn convert_project(
             .into_iter()
             .map(|x| x.image_url)
             .collect(),
+        featured_gallery_url,
+        sensitive: m.sensitive,
     }
 }
 
@@ -286,7 +1221,6 @@ pub struct EditProject {
     pub description: Option<String>,
     #[validate(length(max = 65536))]
     pub body: Option<String>,
-    #[validate(length(max = 3))]
     pub categories: Option<Vec<String>>,
     #[serde(
         default,
@@ -353,6 +1287,23 @@ pub struct EditProject {
     )]
     #[validate(length(max = 65536))]
     pub rejection_body: Option<Option<String>>,
+    pub sensitive: Option<bool>,
+}
+
+impl EditProject {
+    /// Gets every external link set by the edit, including its donation links
+    fn external_urls(&self) -> impl Iterator<Item = &str> {
+        vec![
+            &self.issues_url,
+            &self.source_url,
+            &self.wiki_url,
+            &self.license_url,
+            &self.discord_url,
+        ]
+        .into_iter()
+        .filter_map(|x| x.as_ref().and_then(|x| x.as_deref()))
+        .chain(self.donation_urls.iter().flatten().map(|x| &*x.url))
+    }
 }
 
 #[patch("{id}")]
@@ -363,6 +1314,8 @@ pub async fn project_edit(
     config: web::Data<SearchConfig>,
     new_project: web::Json<EditProject>,
     indexing_queue: Data<Arc<CreationQueue>>,
+    check_config: Data<ProjectCheckConfig>,
+    webhook_config: Data<WebhookConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(req.headers(), &**pool).await?;
 
@@ -370,6 +1323,13 @@ pub async fn project_edit(
         .validate()
         .map_err(|err| ApiError::ValidationError(validation_errors_to_string(err, None)))?;
 
+    if let Some(reason) = crate::util::validate::check_https_urls(
+        check_config.https_only_urls,
+        new_project.external_urls(),
+    ) {
+        return Err(ApiError::InvalidInputError(reason));
+    }
+
     let string = info.into_inner().0;
     let result =
         database::models::Project::get_full_from_slug_or_project_id(string.clone(), &**pool)
@@ -396,10 +1356,11 @@ pub async fn project_edit(
 
         if let Some(perms) = permissions {
             let mut transaction = pool.begin().await?;
+            let mut submitted_for_review = false;
 
             if let Some(title) = &new_project.title {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the title of this project!"
                             .to_string(),
                     ));
@@ -420,12 +1381,20 @@ pub async fn project_edit(
 
             if let Some(description) = &new_project.description {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the description of this project!"
                             .to_string(),
                     ));
                 }
 
+                if let Some(reason) = crate::util::validate::check_description_quality(
+                    &check_config,
+                    &user.role,
+                    description,
+                ) {
+                    return Err(ApiError::InvalidInputError(reason));
+                }
+
                 sqlx::query!(
                     "
                     UPDATE mods
@@ -441,7 +1410,7 @@ pub async fn project_edit(
 
             if let Some(status) = &new_project.status {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the status of this project!"
                             .to_string(),
                     ));
@@ -450,11 +1419,15 @@ pub async fn project_edit(
                 if (status == &ProjectStatus::Rejected || status == &ProjectStatus::Approved)
                     && !user.role.is_mod()
                 {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You don't have permission to set this status".to_string(),
                     ));
                 }
 
+                if requires_files(&project_item.status, status) {
+                    check_has_files(id, status, &mut *transaction).await?;
+                }
+
                 if status == &ProjectStatus::Processing {
                     if project_item.versions.is_empty() {
                         return Err(ApiError::InvalidInputError(String::from(
@@ -484,14 +1457,7 @@ pub async fn project_edit(
                     .execute(&mut *transaction)
                     .await?;
 
-                    if let Ok(webhook_url) = dotenv::var("MODERATION_DISCORD_WEBHOOK") {
-                        crate::util::webhook::send_discord_webhook(
-                            convert_project(project_item.clone()),
-                            webhook_url,
-                        )
-                        .await
-                        .ok();
-                    }
+                    submitted_for_review = true;
                 }
 
                 let status_id = database::models::StatusId::get_id(&status, &mut *transaction)
@@ -514,8 +1480,20 @@ pub async fn project_edit(
                 .execute(&mut *transaction)
                 .await?;
 
+                if (status == &ProjectStatus::Rejected || status == &ProjectStatus::Approved)
+                    && status != &project_item.status
+                {
+                    let reason = new_project
+                        .rejection_reason
+                        .as_ref()
+                        .and_then(|x| x.as_deref());
+
+                    notify_status_change(&project_item.inner, status, reason, &mut transaction)
+                        .await?;
+                }
+
                 if project_item.status.is_searchable() && !status.is_searchable() {
-                    delete_from_index(id.into(), config).await?;
+                    delete_from_index(id.into(), &config).await?;
                 } else if !project_item.status.is_searchable() && status.is_searchable() {
                     let index_project =
                         crate::search::indexing::local_import::query_one(id, &mut *transaction)
@@ -527,12 +1505,37 @@ pub async fn project_edit(
 
             if let Some(categories) = &new_project.categories {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the categories of this project!"
                             .to_string(),
                     ));
                 }
 
+                if let Some(reason) = check_category_count(
+                    &project_item.project_type,
+                    categories.len(),
+                    max_categories(&project_item.project_type),
+                ) {
+                    return Err(ApiError::InvalidInputError(reason));
+                }
+
+                // Every category is resolved before the existing ones are removed, so a
+                // single unknown category fails the edit without touching the project
+                let mut category_ids = Vec::with_capacity(categories.len());
+
+                for category in categories {
+                    category_ids.push((
+                        category.as_str(),
+                        database::models::categories::Category::get_id(
+                            &category,
+                            &mut *transaction,
+                        )
+                        .await?,
+                    ));
+                }
+
+                let category_ids = require_all_exist("Category", category_ids)?;
+
                 sqlx::query!(
                     "
                     DELETE FROM mods_categories
@@ -543,19 +1546,7 @@ pub async fn project_edit(
                 .execute(&mut *transaction)
                 .await?;
 
-                for category in categories {
-                    let category_id = database::models::categories::Category::get_id(
-                        &category,
-                        &mut *transaction,
-                    )
-                    .await?
-                    .ok_or_else(|| {
-                        ApiError::InvalidInputError(format!(
-                            "Category {} does not exist.",
-                            category.clone()
-                        ))
-                    })?;
-
+                for category_id in category_ids {
                     sqlx::query!(
                         "
                         INSERT INTO mods_categories (joining_mod_id, joining_category_id)
@@ -571,7 +1562,7 @@ pub async fn project_edit(
 
             if let Some(issues_url) = &new_project.issues_url {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the issues URL of this project!"
                             .to_string(),
                     ));
@@ -592,7 +1583,7 @@ pub async fn project_edit(
 
             if let Some(source_url) = &new_project.source_url {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the source URL of this project!"
                             .to_string(),
                     ));
@@ -613,7 +1604,7 @@ pub async fn project_edit(
 
             if let Some(wiki_url) = &new_project.wiki_url {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the wiki URL of this project!"
                             .to_string(),
                     ));
@@ -634,7 +1625,7 @@ pub async fn project_edit(
 
             if let Some(license_url) = &new_project.license_url {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the license URL of this project!"
                             .to_string(),
                     ));
@@ -655,7 +1646,7 @@ pub async fn project_edit(
 
             if let Some(discord_url) = &new_project.discord_url {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the discord URL of this project!"
                             .to_string(),
                     ));
@@ -676,18 +1667,14 @@ pub async fn project_edit(
 
             if let Some(slug) = &new_project.slug {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the slug of this project!"
                             .to_string(),
                     ));
                 }
 
                 if let Some(slug) = slug {
-                    let slug_project_id_option: Option<ProjectId> =
-                        serde_json::from_str(&*format!("\"{}\"", slug)).ok();
-                    if let Some(slug_project_id) = slug_project_id_option {
-                        let slug_project_id: database::models::ids::ProjectId =
-                            slug_project_id.into();
+                    if let Some(slug_project_id) = slug_collision_id(slug, id) {
                         let results = sqlx::query!(
                             "
                             SELECT EXISTS(SELECT 1 FROM mods WHERE id=$1)
@@ -697,7 +1684,7 @@ pub async fn project_edit(
                         .fetch_one(&mut *transaction)
                         .await?;
 
-                        if results.exists.unwrap_or(true) {
+                        if results.exists.unwrap_or(false) {
                             return Err(ApiError::InvalidInputError(
                                 "Slug collides with other project's id!".to_string(),
                             ));
@@ -720,7 +1707,7 @@ pub async fn project_edit(
 
             if let Some(new_side) = &new_project.client_side {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the side type of this mod!"
                             .to_string(),
                     ));
@@ -746,7 +1733,7 @@ pub async fn project_edit(
 
             if let Some(new_side) = &new_project.server_side {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the side type of this project!"
                             .to_string(),
                     ));
@@ -772,7 +1759,7 @@ pub async fn project_edit(
 
             if let Some(license) = &new_project.license_id {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the license of this project!"
                             .to_string(),
                     ));
@@ -798,12 +1785,27 @@ pub async fn project_edit(
 
             if let Some(donations) = &new_project.donation_urls {
                 if !perms.contains(Permissions::EDIT_DETAILS) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the donation links of this project!"
                             .to_string(),
                     ));
                 }
 
+                let mut platform_ids = Vec::with_capacity(donations.len());
+
+                for donation in donations {
+                    platform_ids.push((
+                        donation.id.as_str(),
+                        database::models::DonationPlatformId::get_id(
+                            &donation.id,
+                            &mut *transaction,
+                        )
+                        .await?,
+                    ));
+                }
+
+                let platform_ids = require_all_exist("Platform", platform_ids)?;
+
                 sqlx::query!(
                     "
                     DELETE FROM mods_donations
@@ -814,19 +1816,7 @@ pub async fn project_edit(
                 .execute(&mut *transaction)
                 .await?;
 
-                for donation in donations {
-                    let platform_id = database::models::DonationPlatformId::get_id(
-                        &donation.id,
-                        &mut *transaction,
-                    )
-                    .await?
-                    .ok_or_else(|| {
-                        ApiError::InvalidInputError(format!(
-                            "Platform {} does not exist.",
-                            donation.id.clone()
-                        ))
-                    })?;
-
+                for (donation, platform_id) in donations.iter().zip(platform_ids) {
                     sqlx::query!(
                         "
                         INSERT INTO mods_donations (joining_mod_id, joining_platform_id, url)
@@ -843,7 +1833,7 @@ pub async fn project_edit(
 
             if let Some(rejection_reason) = &new_project.rejection_reason {
                 if !user.role.is_mod() {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the rejection reason of this project!"
                             .to_string(),
                     ));
@@ -864,7 +1854,7 @@ pub async fn project_edit(
 
             if let Some(rejection_body) = &new_project.rejection_body {
                 if !user.role.is_mod() {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the rejection body of this project!"
                             .to_string(),
                     ));
@@ -885,12 +1875,19 @@ pub async fn project_edit(
 
             if let Some(body) = &new_project.body {
                 if !perms.contains(Permissions::EDIT_BODY) {
-                    return Err(ApiError::CustomAuthenticationError(
+                    return Err(ApiError::Forbidden(
                         "You do not have the permissions to edit the body of this project!"
                             .to_string(),
                     ));
                 }
 
+                let body = sanitize_body(body).ok_or_else(|| {
+                    ApiError::InvalidInputError(format!(
+                        "The body must be at most {} characters once sanitized",
+                        MAX_BODY_LENGTH
+                    ))
+                })?;
+
                 sqlx::query!(
                     "
                     UPDATE mods
@@ -904,10 +1901,53 @@ pub async fn project_edit(
                 .await?;
             }
 
+            if let Some(sensitive) = new_project.sensitive {
+                if !perms.contains(Permissions::EDIT_DETAILS) {
+                    return Err(ApiError::Forbidden(
+                        "You do not have the permissions to edit the sensitivity of this project!"
+                            .to_string(),
+                    ));
+                }
+
+                sqlx::query!(
+                    "
+                    UPDATE mods
+                    SET sensitive = $1
+                    WHERE (id = $2)
+                    ",
+                    sensitive,
+                    id as database::models::ids::ProjectId,
+                )
+                .execute(&mut *transaction)
+                .await?;
+
+                // Update the project in the search index so that it can be filtered properly
+                if new_project
+                    .status
+                    .as_ref()
+                    .unwrap_or(&project_item.status)
+                    .is_searchable()
+                {
+                    let index_project =
+                        crate::search::indexing::local_import::query_one(id, &mut *transaction)
+                            .await?;
+
+                    indexing_queue.add(index_project);
+                }
+            }
+
             transaction.commit().await?;
+
+            // Only sent once the edit has been committed, like for newly created projects
+            if submitted_for_review {
+                if let Some(project) = database::models::Project::get_full(id, &**pool).await? {
+                    send_moderation_webhook(&webhook_config, convert_project(project));
+                }
+            }
+
             Ok(HttpResponse::NoContent().body(""))
         } else {
-            Err(ApiError::CustomAuthenticationError(
+            Err(ApiError::Forbidden(
                 "You do not have permission to edit this project!".to_string(),
             ))
         }
@@ -916,11 +1956,274 @@ pub async fn project_edit(
     }
 }
 
+#[post("{id}/withdraw")]
+pub async fn project_withdraw(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let string = info.into_inner().0;
+
+    let project_item =
+        database::models::Project::get_full_from_slug_or_project_id(string.clone(), &**pool)
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInputError("The specified project does not exist!".to_string())
+            })?;
+
+    if !user.role.is_mod() {
+        let team_member = database::models::TeamMember::get_from_user_id(
+            project_item.inner.team_id,
+            user.id.into(),
+            &**pool,
+        )
+        .await
+        .map_err(ApiError::DatabaseError)?
+        .ok_or_else(|| {
+            ApiError::InvalidInputError("The specified project does not exist!".to_string())
+        })?;
+
+        if !team_member.permissions.contains(Permissions::EDIT_DETAILS) {
+            return Err(ApiError::Forbidden(
+                "You don't have permission to withdraw this project from review!".to_string(),
+            ));
+        }
+    }
+
+    let withdrawn_status = withdrawn_status(&project_item.status)?;
+
+    use sqlx::Done;
+
+    let mut transaction = pool.begin().await?;
+
+    let status_id = database::models::StatusId::get_id(&withdrawn_status, &mut *transaction)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInputError("No database entry for status provided.".to_string())
+        })?;
+
+    // The status is checked again here so a moderator approving the project
+    // concurrently can't have their decision overwritten
+    let result = sqlx::query!(
+        "
+        UPDATE mods
+        SET status = $1
+        WHERE (id = $2) AND status = (SELECT id FROM statuses WHERE status = $3)
+        ",
+        status_id as database::models::ids::StatusId,
+        project_item.inner.id as database::models::ids::ProjectId,
+        ProjectStatus::Processing.as_str(),
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::InvalidInputError(
+            "Only projects which are being processed can be withdrawn from review!".to_string(),
+        ));
+    }
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// Gets the status a project with `status` returns to when it is withdrawn from review
+fn withdrawn_status(status: &ProjectStatus) -> Result<ProjectStatus, ApiError> {
+    if *status != ProjectStatus::Processing {
+        return Err(ApiError::InvalidInputError(
+            "Only projects which are being processed can be withdrawn from review!".to_string(),
+        ));
+    }
+
+    Ok(ProjectStatus::Draft)
+}
+
+/// Gets a slug for the `attempt`th try at cloning a project, such as `example-copy` and
+/// then `example-copy-2`
+fn clone_slug(source: &database::models::Project, attempt: u32) -> String {
+    let base_slug = format!(
+        "{}-copy",
+        source
+            .slug
+            .clone()
+            .unwrap_or_else(|| ProjectId::from(source.id).to_string())
+            .chars()
+            .take(50)
+            .collect::<String>()
+    );
+
+    if attempt > 1 {
+        format!("{}-{}", base_slug, attempt)
+    } else {
+        base_slug
+    }
+}
+
+/// Copies a project's metadata, categories and links into a new project with the given
+/// status. Versions, the icon and gallery images aren't copied.
+fn clone_project_builder(
+    source: database::models::project_item::QueryProject,
+    project_id: database::models::ProjectId,
+    team_id: database::models::TeamId,
+    status: database::models::StatusId,
+    categories: Vec<database::models::ids::CategoryId>,
+    slug: String,
+) -> database::models::project_item::ProjectBuilder {
+    let inner = source.inner;
+
+    database::models::project_item::ProjectBuilder {
+        project_id,
+        project_type_id: inner.project_type,
+        team_id,
+        title: format!(
+            "{} (Copy)",
+            inner.title.chars().take(248).collect::<String>()
+        ),
+        description: inner.description,
+        body: inner.body,
+        icon_url: None,
+        issues_url: inner.issues_url,
+        source_url: inner.source_url,
+        wiki_url: inner.wiki_url,
+        license_url: inner.license_url,
+        discord_url: inner.discord_url,
+        categories,
+        initial_versions: vec![],
+        status,
+        client_side: inner.client_side,
+        server_side: inner.server_side,
+        license: inner.license,
+        slug: Some(slug),
+        donation_urls: source.donation_urls,
+        gallery_items: vec![],
+    }
+}
+
+#[post("{id}/clone")]
+pub async fn project_clone(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let string = info.into_inner().0;
+
+    let project_item =
+        database::models::Project::get_full_from_slug_or_project_id(string.clone(), &**pool)
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInputError("The specified project does not exist!".to_string())
+            })?;
+
+    database::models::TeamMember::get_from_user_id(
+        project_item.inner.team_id,
+        user.id.into(),
+        &**pool,
+    )
+    .await
+    .map_err(ApiError::DatabaseError)?
+    .ok_or_else(|| {
+        ApiError::Forbidden("You don't have permission to clone this project!".to_string())
+    })?;
+
+    let mut transaction = pool.begin().await?;
+
+    let project_id = database::models::generate_project_id(&mut transaction).await?;
+
+    // Find a free slug based on the original one, such as `example-copy-2`
+    let mut attempt = 1;
+    let mut slug = clone_slug(&project_item.inner, attempt);
+
+    loop {
+        let exists = sqlx::query!(
+            "
+            SELECT EXISTS(SELECT 1 FROM mods WHERE LOWER(slug) = LOWER($1))
+            ",
+            slug
+        )
+        .fetch_one(&mut *transaction)
+        .await?
+        .exists
+        .unwrap_or(true);
+
+        if !exists {
+            break;
+        }
+
+        attempt += 1;
+        slug = clone_slug(&project_item.inner, attempt);
+    }
+
+    use futures::stream::TryStreamExt;
+
+    let categories = sqlx::query!(
+        "
+        SELECT joining_category_id FROM mods_categories
+        WHERE joining_mod_id = $1
+        ",
+        project_item.inner.id as database::models::ids::ProjectId,
+    )
+    .fetch_many(&mut *transaction)
+    .try_filter_map(|e| async {
+        Ok(e.right()
+            .map(|c| database::models::ids::CategoryId(c.joining_category_id)))
+    })
+    .try_collect::<Vec<database::models::ids::CategoryId>>()
+    .await?;
+
+    let team_id = database::models::team_item::TeamBuilder {
+        members: vec![database::models::team_item::TeamMemberBuilder {
+            user_id: user.id.into(),
+            role: crate::models::teams::OWNER_ROLE.to_owned(),
+            permissions: Permissions::ALL,
+            accepted: true,
+        }],
+    }
+    .insert(&mut transaction)
+    .await?;
+
+    let status_id = database::models::StatusId::get_id(&ProjectStatus::Draft, &mut *transaction)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInputError("No database entry for status provided.".to_string())
+        })?;
+
+    clone_project_builder(
+        project_item,
+        project_id,
+        team_id,
+        status_id,
+        categories,
+        slug,
+    )
+    .insert(&mut transaction)
+    .await?;
+
+    let project = database::models::Project::get_full(project_id, &mut *transaction)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInputError("The cloned project could not be found!".to_string())
+        })?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(convert_project(project)))
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Extension {
     pub ext: String,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct GalleryCreateQuery {
+    pub ext: String,
+    #[serde(default)]
+    pub featured: bool,
+}
+
 #[patch("{id}/icon")]
 pub async fn project_icon_edit(
     web::Query(ext): web::Query<Extension>,
@@ -955,20 +2258,12 @@ pub async fn project_icon_edit(
             })?;
 
             if !team_member.permissions.contains(Permissions::EDIT_DETAILS) {
-                return Err(ApiError::CustomAuthenticationError(
+                return Err(ApiError::Forbidden(
                     "You don't have permission to edit this project's icon.".to_string(),
                 ));
             }
         }
 
-        if let Some(icon) = project_item.icon_url {
-            let name = icon.split('/').next();
-
-            if let Some(icon_path) = name {
-                file_host.delete_file_version("", icon_path).await?;
-            }
-        }
-
         let mut bytes = web::BytesMut::new();
         while let Some(item) = payload.next().await {
             bytes.extend_from_slice(&item.map_err(|_| {
@@ -982,33 +2277,59 @@ pub async fn project_icon_edit(
             )));
         }
 
-        let hash = sha1::Sha1::from(&bytes).hexdigest();
+        let icon = crate::util::icon::process_icon(bytes.to_vec(), &ext.ext, content_type)
+            .map_err(|err| ApiError::InvalidInputError(err.to_string()))?;
+
+        let hash = sha1::Sha1::from(&icon.data).hexdigest();
 
         let project_id: ProjectId = project_item.id.into();
 
         let upload_data = file_host
             .upload_file(
-                content_type,
-                &format!("data/{}/{}.{}", project_id, hash, ext.ext),
-                bytes.to_vec(),
+                icon.content_type,
+                &format!("data/{}/{}.{}", project_id, hash, icon.extension),
+                icon.data,
             )
             .await?;
 
-        let mut transaction = pool.begin().await?;
+        let icon_url = format!("{}/{}", cdn_url, upload_data.file_name);
 
-        sqlx::query!(
-            "
-            UPDATE mods
-            SET icon_url = $1
-            WHERE (id = $2)
-            ",
-            format!("{}/{}", cdn_url, upload_data.file_name),
-            project_item.id as database::models::ids::ProjectId,
+        let update = async {
+            let mut transaction = pool.begin().await?;
+
+            sqlx::query!(
+                "
+                UPDATE mods
+                SET icon_url = $1
+                WHERE (id = $2)
+                ",
+                icon_url,
+                project_item.id as database::models::ids::ProjectId,
+            )
+            .execute(&mut *transaction)
+            .await?;
+
+            transaction.commit().await
+        };
+
+        update_icon(
+            &***file_host,
+            &upload_data,
+            &icon_url,
+            project_item.icon_url.as_deref(),
+            update,
         )
-        .execute(&mut *transaction)
         .await?;
 
-        transaction.commit().await?;
+        // The previous icon is only removed once nothing points to it anymore. An icon with
+        // the same contents has the same path, so it must not be deleted.
+        if let Some(old_icon) = project_item.icon_url.filter(|x| x != &icon_url) {
+            if let Some(icon_path) = get_cdn_path(&old_icon, &cdn_url) {
+                if let Err(err) = file_host.delete_file_version("", icon_path).await {
+                    log::warn!("Failed to delete old project icon {}: {}", icon_path, err);
+                }
+            }
+        }
 
         Ok(HttpResponse::NoContent().body(""))
     } else {
@@ -1019,6 +2340,36 @@ pub async fn project_icon_edit(
     }
 }
 
+/// Points a project at its newly uploaded icon with `update`. If the project couldn't be
+/// updated the new icon would never be referenced, so it's deleted again, unless the project
+/// already used it since an icon with the same contents has the same path.
+async fn update_icon(
+    file_host: &dyn FileHost,
+    upload_data: &crate::file_hosting::UploadFileData,
+    icon_url: &str,
+    old_icon_url: Option<&str>,
+    update: impl std::future::Future<Output = Result<(), sqlx::Error>>,
+) -> Result<(), ApiError> {
+    if let Err(err) = update.await {
+        if old_icon_url != Some(icon_url) {
+            if let Err(delete_err) = file_host
+                .delete_file_version(&upload_data.file_id, &upload_data.file_name)
+                .await
+            {
+                log::warn!(
+                    "Failed to delete unused project icon {}: {}",
+                    upload_data.file_name,
+                    delete_err
+                );
+            }
+        }
+
+        return Err(err.into());
+    }
+
+    Ok(())
+}
+
 #[delete("{id}/icon")]
 pub async fn delete_project_icon(
     req: HttpRequest,
@@ -1049,41 +2400,64 @@ pub async fn delete_project_icon(
         })?;
 
         if !team_member.permissions.contains(Permissions::EDIT_DETAILS) {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You don't have permission to edit this project's icon.".to_string(),
             ));
         }
     }
 
-    if let Some(icon) = project_item.icon_url {
-        let name = icon.split('/').next();
+    let icon = match project_item.icon_url {
+        Some(icon) => icon,
+        None => return Ok(HttpResponse::Ok().body("")),
+    };
 
-        if let Some(icon_path) = name {
-            file_host.delete_file_version("", icon_path).await?;
-        }
-    }
+    let cdn_url = dotenv::var("CDN_URL")?;
 
-    let mut transaction = pool.begin().await?;
+    let clear = async {
+        let mut transaction = pool.begin().await?;
 
-    sqlx::query!(
-        "
-        UPDATE mods
-        SET icon_url = NULL
-        WHERE (id = $1)
-        ",
-        project_item.id as database::models::ids::ProjectId,
-    )
-    .execute(&mut *transaction)
-    .await?;
+        sqlx::query!(
+            "
+            UPDATE mods
+            SET icon_url = NULL
+            WHERE (id = $1)
+            ",
+            project_item.id as database::models::ids::ProjectId,
+        )
+        .execute(&mut *transaction)
+        .await?;
 
-    transaction.commit().await?;
+        transaction.commit().await
+    };
+
+    remove_icon(&***file_host, &icon, &cdn_url, clear).await?;
 
     Ok(HttpResponse::NoContent().body(""))
 }
 
+/// Removes a project's icon, clearing it from the project with `clear` before deleting it
+/// from the CDN. The project no longer references the icon by then, so failing to delete it
+/// only leaves an unused file behind and is logged instead of failing the request.
+async fn remove_icon(
+    file_host: &dyn FileHost,
+    icon_url: &str,
+    cdn_url: &str,
+    clear: impl std::future::Future<Output = Result<(), sqlx::Error>>,
+) -> Result<(), ApiError> {
+    clear.await?;
+
+    if let Some(icon_path) = get_cdn_path(icon_url, cdn_url) {
+        if let Err(err) = file_host.delete_file_version("", icon_path).await {
+            log::warn!("Failed to delete project icon {}: {}", icon_path, err);
+        }
+    }
+
+    Ok(())
+}
+
 #[post("{id}/gallery")]
 pub async fn add_gallery_item(
-    web::Query(ext): web::Query<Extension>,
+    web::Query(ext): web::Query<GalleryCreateQuery>,
     req: HttpRequest,
     info: web::Path<(String,)>,
     pool: web::Data<PgPool>,
@@ -1115,7 +2489,7 @@ pub async fn add_gallery_item(
             })?;
 
             if !team_member.permissions.contains(Permissions::EDIT_DETAILS) {
-                return Err(ApiError::CustomAuthenticationError(
+                return Err(ApiError::Forbidden(
                     "You don't have permission to edit this project's gallery.".to_string(),
                 ));
             }
@@ -1146,13 +2520,30 @@ pub async fn add_gallery_item(
 
         let mut transaction = pool.begin().await?;
 
+        // Only a single gallery image can be featured at a time
+        if ext.featured {
+            sqlx::query!(
+                "
+                UPDATE mods_gallery
+                SET featured = FALSE
+                WHERE mod_id = $1
+                ",
+                project_item.id as database::models::ids::ProjectId,
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+
         database::models::project_item::GalleryItem {
             project_id: project_item.id,
             image_url: format!("{}/{}", cdn_url, url),
+            featured: ext.featured,
         }
         .insert(&mut transaction)
         .await?;
 
+        transaction.commit().await?;
+
         Ok(HttpResponse::NoContent().body(""))
     } else {
         Err(ApiError::InvalidInputError(format!(
@@ -1162,21 +2553,45 @@ pub async fn add_gallery_item(
     }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct GalleryItem {
-    pub item: String,
+#[derive(Serialize, Deserialize, Validate)]
+pub struct EditTranslation {
+    #[validate(length(min = 3, max = 256))]
+    pub title: String,
+    #[validate(length(min = 3, max = 2048))]
+    pub description: String,
 }
 
-#[delete("{id}/gallery")]
-pub async fn delete_gallery_item(
+fn validate_locale(locale: &str) -> Result<(), ApiError> {
+    if locale.is_empty()
+        || locale.len() > 32
+        || !locale
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(ApiError::InvalidInputError(format!(
+            "Invalid locale: {}",
+            locale
+        )));
+    }
+
+    Ok(())
+}
+
+#[put("{id}/translations/{locale}")]
+pub async fn project_translation_edit(
     req: HttpRequest,
-    web::Query(item): web::Query<GalleryItem>,
-    info: web::Path<(String,)>,
+    info: web::Path<(String, String)>,
     pool: web::Data<PgPool>,
-    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
+    new_translation: web::Json<EditTranslation>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(req.headers(), &**pool).await?;
-    let string = info.into_inner().0;
+
+    new_translation
+        .validate()
+        .map_err(|err| ApiError::ValidationError(validation_errors_to_string(err, None)))?;
+
+    let (string, locale) = info.into_inner();
+    validate_locale(&locale)?;
 
     let project_item =
         database::models::Project::get_from_slug_or_project_id(string.clone(), &**pool)
@@ -1198,15 +2613,126 @@ pub async fn delete_gallery_item(
         })?;
 
         if !team_member.permissions.contains(Permissions::EDIT_DETAILS) {
-            return Err(ApiError::CustomAuthenticationError(
-                "You don't have permission to edit this project's icon.".to_string(),
+            return Err(ApiError::Forbidden(
+                "You don't have permission to edit this project's translations.".to_string(),
             ));
         }
     }
+
     let mut transaction = pool.begin().await?;
 
-    let id = sqlx::query!(
-        "
+    database::models::project_item::ProjectTranslation {
+        project_id: project_item.id,
+        locale,
+        title: new_translation.title.clone(),
+        description: new_translation.description.clone(),
+    }
+    .insert(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+#[delete("{id}/translations/{locale}")]
+pub async fn project_translation_delete(
+    req: HttpRequest,
+    info: web::Path<(String, String)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let (string, locale) = info.into_inner();
+
+    let project_item =
+        database::models::Project::get_from_slug_or_project_id(string.clone(), &**pool)
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInputError("The specified project does not exist!".to_string())
+            })?;
+
+    if !user.role.is_mod() {
+        let team_member = database::models::TeamMember::get_from_user_id(
+            project_item.team_id,
+            user.id.into(),
+            &**pool,
+        )
+        .await
+        .map_err(ApiError::DatabaseError)?
+        .ok_or_else(|| {
+            ApiError::InvalidInputError("The specified project does not exist!".to_string())
+        })?;
+
+        if !team_member.permissions.contains(Permissions::EDIT_DETAILS) {
+            return Err(ApiError::Forbidden(
+                "You don't have permission to edit this project's translations.".to_string(),
+            ));
+        }
+    }
+
+    let mut transaction = pool.begin().await?;
+
+    let result = database::models::project_item::ProjectTranslation::remove(
+        project_item.id,
+        &locale,
+        &mut transaction,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    if result.is_some() {
+        Ok(HttpResponse::NoContent().body(""))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GalleryItem {
+    pub item: String,
+}
+
+#[delete("{id}/gallery")]
+pub async fn delete_gallery_item(
+    req: HttpRequest,
+    web::Query(item): web::Query<GalleryItem>,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    file_host: web::Data<Arc<dyn FileHost + Send + Sync>>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let string = info.into_inner().0;
+
+    let project_item =
+        database::models::Project::get_from_slug_or_project_id(string.clone(), &**pool)
+            .await?
+            .ok_or_else(|| {
+                ApiError::InvalidInputError("The specified project does not exist!".to_string())
+            })?;
+
+    if !user.role.is_mod() {
+        let team_member = database::models::TeamMember::get_from_user_id(
+            project_item.team_id,
+            user.id.into(),
+            &**pool,
+        )
+        .await
+        .map_err(ApiError::DatabaseError)?
+        .ok_or_else(|| {
+            ApiError::InvalidInputError("The specified project does not exist!".to_string())
+        })?;
+
+        if !team_member.permissions.contains(Permissions::EDIT_DETAILS) {
+            return Err(ApiError::Forbidden(
+                "You don't have permission to edit this project's icon.".to_string(),
+            ));
+        }
+    }
+    let mut transaction = pool.begin().await?;
+
+    let id = sqlx::query!(
+        "
         SELECT id FROM mods_gallery
         WHERE image_url = $1
         ",
@@ -1222,14 +2748,6 @@ pub async fn delete_gallery_item(
     })?
     .id;
 
-    let name = item.item.split('/').next();
-
-    if let Some(item_path) = name {
-        file_host.delete_file_version("", item_path).await?;
-    }
-
-    let mut transaction = pool.begin().await?;
-
     sqlx::query!(
         "
         DELETE FROM mods_gallery
@@ -1242,6 +2760,12 @@ pub async fn delete_gallery_item(
 
     transaction.commit().await?;
 
+    let cdn_url = dotenv::var("CDN_URL")?;
+
+    if let Some(item_path) = get_cdn_path(&item.item, &cdn_url) {
+        file_host.delete_file_version("", item_path).await?;
+    }
+
     Ok(HttpResponse::NoContent().body(""))
 }
 
@@ -1277,7 +2801,7 @@ pub async fn project_delete(
             .permissions
             .contains(Permissions::DELETE_PROJECT)
         {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You don't have permission to delete this project!".to_string(),
             ));
         }
@@ -1289,7 +2813,7 @@ pub async fn project_delete(
 
     transaction.commit().await?;
 
-    delete_from_index(project.id.into(), config).await?;
+    delete_from_index(project.id.into(), &config).await?;
 
     if result.is_some() {
         Ok(HttpResponse::NoContent().body(""))
@@ -1316,21 +2840,121 @@ pub async fn project_follow(
     let user_id: database::models::ids::UserId = user.id.into();
     let project_id: database::models::ids::ProjectId = result.id;
 
-    let following = sqlx::query!(
-        "
-        SELECT EXISTS(SELECT 1 FROM mod_follows mf WHERE mf.follower_id = $1 AND mf.mod_id = $2)
-        ",
-        user_id as database::models::ids::UserId,
-        project_id as database::models::ids::ProjectId
-    )
-    .fetch_one(&**pool)
-    .await?
-    .exists
-    .unwrap_or(false);
+    let mut transaction = pool.begin().await?;
 
-    if !following {
-        let mut transaction = pool.begin().await?;
+    follow_project(&mut transaction, user_id, project_id).await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+#[delete("{id}/follow")]
+pub async fn project_unfollow(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let string = info.into_inner().0;
+
+    let result = database::models::Project::get_from_slug_or_project_id(string, &**pool)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInputError("The specified project does not exist!".to_string())
+        })?;
+
+    let user_id: database::models::ids::UserId = user.id.into();
+    let project_id = result.id;
+
+    let mut transaction = pool.begin().await?;
+
+    unfollow_project(&mut transaction, user_id, project_id).await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// The follows of projects and the follow counts kept alongside them
+#[async_trait]
+trait Follows {
+    /// Adds a follow, returning how many rows were inserted
+    async fn insert_follow(
+        &mut self,
+        user_id: database::models::ids::UserId,
+        project_id: database::models::ids::ProjectId,
+    ) -> Result<u64, sqlx::Error>;
+
+    /// Removes a follow, returning how many rows were deleted
+    async fn delete_follow(
+        &mut self,
+        user_id: database::models::ids::UserId,
+        project_id: database::models::ids::ProjectId,
+    ) -> Result<u64, sqlx::Error>;
+
+    async fn increment_follows(
+        &mut self,
+        project_id: database::models::ids::ProjectId,
+    ) -> Result<(), sqlx::Error>;
+
+    async fn decrement_follows(
+        &mut self,
+        project_id: database::models::ids::ProjectId,
+    ) -> Result<(), sqlx::Error>;
+}
+
+#[async_trait]
+impl<'c> Follows for sqlx::Transaction<'c, sqlx::Postgres> {
+    async fn insert_follow(
+        &mut self,
+        user_id: database::models::ids::UserId,
+        project_id: database::models::ids::ProjectId,
+    ) -> Result<u64, sqlx::Error> {
+        use sqlx::Done;
 
+        let inserted = sqlx::query!(
+            "
+            INSERT INTO mod_follows (follower_id, mod_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            ",
+            user_id as database::models::ids::UserId,
+            project_id as database::models::ids::ProjectId
+        )
+        .execute(&mut *self)
+        .await?
+        .rows_affected();
+
+        Ok(inserted)
+    }
+
+    async fn delete_follow(
+        &mut self,
+        user_id: database::models::ids::UserId,
+        project_id: database::models::ids::ProjectId,
+    ) -> Result<u64, sqlx::Error> {
+        use sqlx::Done;
+
+        let deleted = sqlx::query!(
+            "
+            DELETE FROM mod_follows
+            WHERE follower_id = $1 AND mod_id = $2
+            ",
+            user_id as database::models::ids::UserId,
+            project_id as database::models::ids::ProjectId
+        )
+        .execute(&mut *self)
+        .await?
+        .rows_affected();
+
+        Ok(deleted)
+    }
+
+    async fn increment_follows(
+        &mut self,
+        project_id: database::models::ids::ProjectId,
+    ) -> Result<(), sqlx::Error> {
         sqlx::query!(
             "
             UPDATE mods
@@ -1339,105 +2963,1143 @@ pub async fn project_follow(
             ",
             project_id as database::models::ids::ProjectId,
         )
-        .execute(&mut *transaction)
+        .execute(&mut *self)
         .await?;
 
+        Ok(())
+    }
+
+    async fn decrement_follows(
+        &mut self,
+        project_id: database::models::ids::ProjectId,
+    ) -> Result<(), sqlx::Error> {
         sqlx::query!(
             "
-            INSERT INTO mod_follows (follower_id, mod_id)
-            VALUES ($1, $2)
+            UPDATE mods
+            SET follows = follows - 1
+            WHERE id = $1
             ",
-            user_id as database::models::ids::UserId,
-            project_id as database::models::ids::ProjectId
+            project_id as database::models::ids::ProjectId,
         )
-        .execute(&mut *transaction)
+        .execute(&mut *self)
         .await?;
 
-        transaction.commit().await?;
+        Ok(())
+    }
+}
 
-        Ok(HttpResponse::NoContent().body(""))
-    } else {
-        Err(ApiError::InvalidInputError(
+/// Follows a project for a user. Concurrent requests can't both follow the project, since
+/// only one of them can insert the row, and only that one counts the follow.
+async fn follow_project(
+    follows: &mut impl Follows,
+    user_id: database::models::ids::UserId,
+    project_id: database::models::ids::ProjectId,
+) -> Result<(), ApiError> {
+    let inserted = follows.insert_follow(user_id, project_id).await?;
+
+    check_follow_inserted(inserted)?;
+
+    follows.increment_follows(project_id).await?;
+
+    Ok(())
+}
+
+/// Unfollows a project for a user. Only the request which actually deleted the row counts
+/// the unfollow.
+async fn unfollow_project(
+    follows: &mut impl Follows,
+    user_id: database::models::ids::UserId,
+    project_id: database::models::ids::ProjectId,
+) -> Result<(), ApiError> {
+    let deleted = follows.delete_follow(user_id, project_id).await?;
+
+    check_follow_deleted(deleted)?;
+
+    follows.decrement_follows(project_id).await?;
+
+    Ok(())
+}
+
+/// Checks that a follow row was inserted. Following is idempotent because of the unique
+/// constraint on `mod_follows`, so a request which didn't insert a row is already following.
+fn check_follow_inserted(inserted: u64) -> Result<(), ApiError> {
+    if inserted == 0 {
+        return Err(ApiError::InvalidInputError(
             "You are already following this project!".to_string(),
-        ))
+        ));
     }
+
+    Ok(())
 }
 
-#[delete("{id}/follow")]
-pub async fn project_unfollow(
+/// Checks that a follow row was deleted, so a follow is only ever uncounted once
+fn check_follow_deleted(deleted: u64) -> Result<(), ApiError> {
+    if deleted == 0 {
+        return Err(ApiError::InvalidInputError(
+            "You are not following this project!".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct FeaturedVersions {
+    pub versions: Vec<models::ids::VersionId>,
+}
+
+/// Features exactly the given versions of a project, unfeaturing all of its other versions
+#[post("{id}/versions/featured")]
+pub async fn project_featured_versions_edit(
     req: HttpRequest,
     info: web::Path<(String,)>,
     pool: web::Data<PgPool>,
+    featured: web::Json<FeaturedVersions>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(req.headers(), &**pool).await?;
     let string = info.into_inner().0;
 
-    let result = database::models::Project::get_from_slug_or_project_id(string, &**pool)
+    let project = database::models::Project::get_from_slug_or_project_id(string, &**pool)
         .await?
         .ok_or_else(|| {
             ApiError::InvalidInputError("The specified project does not exist!".to_string())
         })?;
 
-    let user_id: database::models::ids::UserId = user.id.into();
-    let project_id = result.id;
+    // Moderators can feature the versions of any project
+    let permissions = if user.role.is_mod() {
+        Some(Permissions::ALL)
+    } else {
+        database::models::TeamMember::get_from_user_id(project.team_id, user.id.into(), &**pool)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .map(|x| x.permissions)
+    };
 
-    let following = sqlx::query!(
+    check_can_feature_versions(permissions)?;
+
+    let version_ids = featured
+        .versions
+        .iter()
+        .map(|x| x.0 as i64)
+        .collect::<Vec<_>>();
+
+    let mut transaction = pool.begin().await?;
+
+    let project_versions = sqlx::query!(
         "
-        SELECT EXISTS(SELECT 1 FROM mod_follows mf WHERE mf.follower_id = $1 AND mf.mod_id = $2)
+        SELECT COUNT(DISTINCT id) count FROM versions
+        WHERE mod_id = $1 AND id = ANY($2)
         ",
-        user_id as database::models::ids::UserId,
-        project_id as database::models::ids::ProjectId
+        project.id as database::models::ids::ProjectId,
+        &version_ids[..],
     )
-    .fetch_one(&**pool)
+    .fetch_one(&mut *transaction)
+    .await?
+    .count
+    .unwrap_or(0);
+
+    check_featured_versions_in_project(&version_ids, project_versions)?;
+
+    // The position in the list is kept, so featured versions are listed in the given order
+    sqlx::query!(
+        "
+        UPDATE versions
+        SET featured = (id = ANY($2)), featured_order = array_position($2, id)
+        WHERE mod_id = $1
+        ",
+        project.id as database::models::ids::ProjectId,
+        &version_ids[..],
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// Gets the id a slug would be mistaken for, which must not be the id of another project.
+/// A slug may match the id of `project_id`, the project it is given to.
+fn slug_collision_id(
+    slug: &str,
+    project_id: database::models::ids::ProjectId,
+) -> Option<database::models::ids::ProjectId> {
+    serde_json::from_str::<ProjectId>(&*format!("\"{}\"", slug))
+        .ok()
+        .map(database::models::ids::ProjectId::from)
+        .filter(|x| *x != project_id)
+}
+
+/// Unwraps the ids looked up for each named item, failing with an error naming the first
+/// item which doesn't exist
+fn require_all_exist<T>(kind: &str, ids: Vec<(&str, Option<T>)>) -> Result<Vec<T>, ApiError> {
+    ids.into_iter()
+        .map(|(name, id)| {
+            id.ok_or_else(|| {
+                ApiError::InvalidInputError(format!("{} {} does not exist.", kind, name))
+            })
+        })
+        .collect()
+}
+
+/// Checks that a team member with the given permissions, if any, can choose which versions
+/// of a project are featured
+fn check_can_feature_versions(permissions: Option<Permissions>) -> Result<(), ApiError> {
+    match permissions {
+        Some(permissions) if permissions.contains(Permissions::EDIT_DETAILS) => Ok(()),
+        _ => Err(ApiError::Forbidden(
+            "You don't have permission to edit this project's featured versions.".to_string(),
+        )),
+    }
+}
+
+/// Checks that every version to feature belongs to the project, given the number of them
+/// which were found among the project's versions
+fn check_featured_versions_in_project(
+    version_ids: &[i64],
+    project_versions: i64,
+) -> Result<(), ApiError> {
+    let mut unique_ids = version_ids.to_vec();
+    unique_ids.sort_unstable();
+    unique_ids.dedup();
+
+    if project_versions as usize != unique_ids.len() {
+        return Err(ApiError::InvalidInputError(
+            "Only versions of this project can be featured".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that a project has at least one version with a file, so that projects can't be
+/// made searchable without anything to download. `status` is the status the project is
+/// being moved to, which is named in the error.
+pub async fn check_has_files<'a, E>(
+    id: database::models::ids::ProjectId,
+    status: &ProjectStatus,
+    executor: E,
+) -> Result<(), ApiError>
+where
+    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
+{
+    let has_files = sqlx::query!(
+        "
+        SELECT EXISTS(
+            SELECT 1 FROM versions v
+            INNER JOIN files f ON f.version_id = v.id
+            WHERE v.mod_id = $1
+        )
+        ",
+        id as database::models::ids::ProjectId,
+    )
+    .fetch_one(executor)
     .await?
     .exists
     .unwrap_or(false);
 
-    if following {
-        let mut transaction = pool.begin().await?;
+    check_files_for_status(has_files, status)
+}
 
-        sqlx::query!(
-            "
-            UPDATE mods
-            SET follows = follows - 1
-            WHERE id = $1
-            ",
-            project_id as database::models::ids::ProjectId,
-        )
-        .execute(&mut *transaction)
-        .await?;
+/// Whether moving a project from one status to another requires it to have files. Only
+/// projects which haven't been published yet are checked, so published projects can still
+/// be moved between statuses after their files are deleted.
+pub fn requires_files(old: &ProjectStatus, new: &ProjectStatus) -> bool {
+    new.is_searchable() && (*old == ProjectStatus::Draft || *old == ProjectStatus::Processing)
+}
 
-        sqlx::query!(
-            "
-            DELETE FROM mod_follows
-            WHERE follower_id = $1 AND mod_id = $2
-            ",
-            user_id as database::models::ids::UserId,
-            project_id as database::models::ids::ProjectId
-        )
-        .execute(&mut *transaction)
+fn check_files_for_status(has_files: bool, status: &ProjectStatus) -> Result<(), ApiError> {
+    if has_files {
+        Ok(())
+    } else {
+        Err(ApiError::InvalidInputError(format!(
+            "The project must have at least one version with a file before its status can be set to {}",
+            status
+        )))
+    }
+}
+
+/// Notifies every accepted member of a project's team that the moderators changed the
+/// status of the project, including the reason given for rejections
+pub async fn notify_status_change(
+    project: &database::models::Project,
+    status: &ProjectStatus,
+    reason: Option<&str>,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), ApiError> {
+    let members = database::models::TeamMember::get_from_team(project.team_id, &mut *transaction)
+        .await?
+        .into_iter()
+        .filter(|x| x.accepted)
+        .map(|x| x.user_id)
+        .collect();
+
+    status_change_notification(project, status, reason)
+        .insert_many(members, transaction)
         .await?;
 
-        transaction.commit().await?;
+    Ok(())
+}
 
-        Ok(HttpResponse::NoContent().body(""))
-    } else {
-        Err(ApiError::InvalidInputError(
-            "You are not following this project!".to_string(),
-        ))
+fn status_change_notification(
+    project: &database::models::Project,
+    status: &ProjectStatus,
+    reason: Option<&str>,
+) -> NotificationBuilder {
+    let text = match reason {
+        Some(reason) if status == &ProjectStatus::Rejected => format!(
+            "Your project, {}, has been rejected by the moderators: {}",
+            project.title, reason
+        ),
+        _ => format!(
+            "Your project, {}, has been {} by the moderators.",
+            project.title,
+            status.as_str()
+        ),
+    };
+
+    NotificationBuilder {
+        notification_type: Some("status_change".to_string()),
+        title: format!("**{}** has been {}", project.title, status.as_str()),
+        text,
+        link: format!("project/{}/settings", ProjectId::from(project.id)),
+        actions: vec![],
     }
 }
 
-pub async fn delete_from_index(
-    id: crate::models::projects::ProjectId,
-    config: web::Data<SearchConfig>,
-) -> Result<(), meilisearch_sdk::errors::Error> {
-    let client = meilisearch_sdk::client::Client::new(&*config.address, &*config.key);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::models::project_item::ProjectTranslation;
+
+    fn translations(locales: &[&str]) -> Vec<ProjectTranslation> {
+        locales
+            .iter()
+            .map(|locale| ProjectTranslation {
+                project_id: database::models::ids::ProjectId(1),
+                locale: locale.to_string(),
+                title: format!("Title ({})", locale),
+                description: format!("Description ({})", locale),
+            })
+            .collect()
+    }
 
-    let indexes: Vec<meilisearch_sdk::indexes::Index> = client.get_indexes().await?;
-    for index in indexes {
-        index.delete_document(format!("local-{}", id)).await?;
+    fn best_locale<'a>(
+        accept_language: &str,
+        translations: &'a [ProjectTranslation],
+    ) -> Option<&'a str> {
+        best_translation(accept_language, "en", translations).map(|x| x.locale.as_str())
     }
 
-    Ok(())
+    #[test]
+    fn prefers_exact_locales() {
+        let translations = translations(&["pt", "pt-BR"]);
+
+        assert_eq!(best_locale("pt-BR", &translations), Some("pt-BR"));
+        assert_eq!(best_locale("pt-PT, pt;q=0.5", &translations), Some("pt"));
+    }
+
+    #[test]
+    fn follows_quality_values() {
+        let translations = translations(&["de", "fr"]);
+
+        assert_eq!(best_locale("de;q=0.5, fr;q=0.9", &translations), Some("fr"));
+        assert_eq!(best_locale("fr;q=0, de", &translations), Some("de"));
+        assert_eq!(best_locale("*, es", &translations), None);
+    }
+
+    #[test]
+    fn keeps_the_default_locale_when_preferred() {
+        let translations = translations(&["fr", "en-GB"]);
+
+        assert_eq!(best_locale("en-US, fr;q=0.8", &translations), None);
+        assert_eq!(best_locale("en-GB, fr;q=0.8", &translations), Some("en-GB"));
+        assert_eq!(
+            best_locale("es, fr;q=0.8, en;q=0.5", &translations),
+            Some("fr")
+        );
+    }
+
+    fn search_project(id: &str, categories: &[&str], downloads: i32) -> ResultSearchProject {
+        ResultSearchProject {
+            project_id: id.to_string(),
+            project_type: "mod".to_string(),
+            slug: None,
+            author: "author".to_string(),
+            title: id.to_string(),
+            description: String::new(),
+            categories: categories.iter().map(|x| x.to_string()).collect(),
+            versions: Vec::new(),
+            downloads,
+            follows: 0,
+            icon_url: String::new(),
+            date_created: String::new(),
+            date_modified: String::new(),
+            latest_version: String::new(),
+            license: "mit".to_string(),
+            client_side: "required".to_string(),
+            server_side: "required".to_string(),
+            sensitive: false,
+        }
+    }
+
+    #[test]
+    fn related_projects_are_ranked_by_shared_categories() {
+        let categories = vec!["magic".to_string(), "storage".to_string()];
+        let projects = vec![
+            search_project("unrelated", &["food"], 1_000_000),
+            search_project("one", &["magic", "food"], 100),
+            search_project("both", &["storage", "magic"], 10),
+            search_project("popular", &["storage"], 1000),
+        ];
+
+        let ids = rank_related_projects(projects, &categories)
+            .into_iter()
+            .map(|x| x.project_id)
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids, vec!["both", "popular", "one", "unrelated"]);
+    }
+
+    #[test]
+    fn related_projects_are_limited() {
+        let categories = vec!["magic".to_string()];
+        let projects = (0..RELATED_PROJECTS_COUNT + 3)
+            .map(|x| search_project(&x.to_string(), &["magic"], x as i32))
+            .collect();
+
+        let related = rank_related_projects(projects, &categories);
+
+        assert_eq!(related.len(), RELATED_PROJECTS_COUNT);
+        assert_eq!(related[0].downloads, RELATED_PROJECTS_COUNT as i32 + 2);
+    }
+
+    #[test]
+    fn summary_stats_match_the_project_counts() {
+        let updated = crate::util::time::parse_timestamp("2021-07-20T12:00:00Z").unwrap();
+
+        let stats = ProjectSummaryStats::from_counts(1500, 30, Some(4), updated);
+
+        assert_eq!(
+            serde_json::to_value(&stats).unwrap(),
+            serde_json::json!({
+                "downloads": 1500,
+                "followers": 30,
+                "versions": 4,
+                "updated": "2021-07-20T12:00:00.000Z",
+            })
+        );
+    }
+
+    #[test]
+    fn summary_stats_count_projects_without_versions() {
+        let stats = ProjectSummaryStats::from_counts(0, 0, None, chrono::Utc::now());
+
+        assert_eq!(stats.versions, 0);
+        assert_eq!(stats.downloads, 0);
+    }
+
+    fn project(slug: Option<&str>) -> database::models::project_item::QueryProject {
+        let mut project = database::models::project_item::QueryProject::example(1000);
+        project.inner.slug = slug.map(|x| x.to_string());
+        project
+    }
+
+    #[test]
+    fn clones_get_a_new_slug() {
+        let source = project(Some("example"));
+
+        assert_eq!(clone_slug(&source.inner, 1), "example-copy");
+        assert_eq!(clone_slug(&source.inner, 2), "example-copy-2");
+
+        let source = project(None);
+        let id = ProjectId::from(source.inner.id).to_string();
+
+        assert_eq!(clone_slug(&source.inner, 1), format!("{}-copy", id));
+    }
+
+    #[test]
+    fn clones_are_drafts_without_versions() {
+        use database::models::ids::*;
+
+        let builder = clone_project_builder(
+            project(Some("example")),
+            ProjectId(1001),
+            TeamId(2001),
+            StatusId(5),
+            vec![CategoryId(7)],
+            "example-copy".to_string(),
+        );
+
+        assert_eq!(builder.project_id, ProjectId(1001));
+        assert_eq!(builder.team_id.0, 2001);
+        assert_eq!(builder.status.0, 5);
+        assert_eq!(builder.slug.as_deref(), Some("example-copy"));
+        assert_eq!(builder.title, "Example (Copy)");
+        assert_eq!(
+            builder.issues_url.as_deref(),
+            Some("https://example.com/issues")
+        );
+        assert!(builder.initial_versions.is_empty());
+        assert!(builder.gallery_items.is_empty());
+        assert!(builder.icon_url.is_none());
+    }
+
+    #[test]
+    fn description_errors_report_the_real_bounds() {
+        let edit: EditProject = serde_json::from_value(serde_json::json!({
+            "description": "a".repeat(2049),
+        }))
+        .unwrap();
+
+        let error = validation_errors_to_string(edit.validate().unwrap_err(), None);
+
+        assert!(error.contains("description"));
+        assert!(error.contains("2048"));
+        assert!(!error.contains("256"));
+    }
+
+    #[test]
+    fn rejection_notifications_contain_the_reason() {
+        let project = project(Some("example")).inner;
+
+        let notification = status_change_notification(
+            &project,
+            &ProjectStatus::Rejected,
+            Some("The description is missing"),
+        );
+
+        assert_eq!(
+            notification.notification_type.as_deref(),
+            Some("status_change")
+        );
+        assert!(notification.text.contains("The description is missing"));
+        assert_eq!(
+            notification.link,
+            format!("project/{}/settings", ProjectId::from(project.id))
+        );
+    }
+
+    #[test]
+    fn approval_notifications_have_no_reason() {
+        let project = project(Some("example")).inner;
+
+        let notification =
+            status_change_notification(&project, &ProjectStatus::Approved, Some("Looks good"));
+
+        assert!(!notification.text.contains("Looks good"));
+        assert!(notification.text.contains("approved"));
+    }
+
+    /// Stands in for the `mod_follows` table, where each follower can only follow a
+    /// project once, and the follow counts of projects
+    #[derive(Default)]
+    struct MemoryFollows {
+        follows: std::collections::HashSet<(i64, i64)>,
+        counts: HashMap<i64, i32>,
+    }
+
+    #[async_trait]
+    impl Follows for MemoryFollows {
+        async fn insert_follow(
+            &mut self,
+            user_id: database::models::ids::UserId,
+            project_id: database::models::ids::ProjectId,
+        ) -> Result<u64, sqlx::Error> {
+            Ok(self.follows.insert((user_id.0, project_id.0)) as u64)
+        }
+
+        async fn delete_follow(
+            &mut self,
+            user_id: database::models::ids::UserId,
+            project_id: database::models::ids::ProjectId,
+        ) -> Result<u64, sqlx::Error> {
+            Ok(self.follows.remove(&(user_id.0, project_id.0)) as u64)
+        }
+
+        async fn increment_follows(
+            &mut self,
+            project_id: database::models::ids::ProjectId,
+        ) -> Result<(), sqlx::Error> {
+            *self.counts.entry(project_id.0).or_insert(0) += 1;
+            Ok(())
+        }
+
+        async fn decrement_follows(
+            &mut self,
+            project_id: database::models::ids::ProjectId,
+        ) -> Result<(), sqlx::Error> {
+            *self.counts.entry(project_id.0).or_insert(0) -= 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn following_twice_is_counted_once() {
+        let mut follows = MemoryFollows::default();
+        let user_id = database::models::ids::UserId(1);
+        let project_id = database::models::ids::ProjectId(1000);
+
+        // Both requests get past the existence check of the project before either inserts
+        let first = futures::executor::block_on(follow_project(&mut follows, user_id, project_id));
+        let second = futures::executor::block_on(follow_project(&mut follows, user_id, project_id));
+
+        assert!(first.is_ok());
+        assert!(matches!(second, Err(ApiError::InvalidInputError(_))));
+        assert_eq!(follows.counts[&1000], 1);
+    }
+
+    #[test]
+    fn unfollowing_twice_is_counted_once() {
+        let mut follows = MemoryFollows::default();
+        let user_id = database::models::ids::UserId(1);
+        let project_id = database::models::ids::ProjectId(1000);
+
+        futures::executor::block_on(follow_project(&mut follows, user_id, project_id)).unwrap();
+
+        let first =
+            futures::executor::block_on(unfollow_project(&mut follows, user_id, project_id));
+        let second =
+            futures::executor::block_on(unfollow_project(&mut follows, user_id, project_id));
+
+        assert!(first.is_ok());
+        assert!(matches!(second, Err(ApiError::InvalidInputError(_))));
+        assert_eq!(follows.counts[&1000], 0);
+        assert!(follows.follows.is_empty());
+    }
+
+    #[test]
+    fn featuring_versions_requires_edit_details() {
+        assert!(check_can_feature_versions(Some(Permissions::EDIT_DETAILS)).is_ok());
+        assert!(check_can_feature_versions(Some(Permissions::ALL)).is_ok());
+        assert!(matches!(
+            check_can_feature_versions(Some(Permissions::UPLOAD_VERSION)),
+            Err(ApiError::Forbidden(_))
+        ));
+        assert!(matches!(
+            check_can_feature_versions(None),
+            Err(ApiError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn versions_of_other_projects_cannot_be_featured() {
+        // Only two of the three versions belong to the project
+        assert!(matches!(
+            check_featured_versions_in_project(&[1, 2, 3], 2),
+            Err(ApiError::InvalidInputError(_))
+        ));
+
+        assert!(check_featured_versions_in_project(&[1, 2, 3], 3).is_ok());
+        assert!(check_featured_versions_in_project(&[1, 2, 2], 2).is_ok());
+        assert!(check_featured_versions_in_project(&[], 0).is_ok());
+    }
+
+    fn manifest_row(file_id: i64, version_id: i64, hash: Option<(&str, &[u8])>) -> ManifestRow {
+        ManifestRow {
+            file_id,
+            version_id,
+            filename: format!("file-{}.jar", file_id),
+            size: Some(file_id * 100),
+            algorithm: hash.map(|x| x.0.to_string()),
+            hash: hash.map(|x| x.1.to_vec()),
+        }
+    }
+
+    #[test]
+    fn file_manifests_list_every_file_with_its_hashes() {
+        use futures::StreamExt;
+
+        let rows = vec![
+            manifest_row(1, 10, Some(("sha1", b"\x01"))),
+            manifest_row(1, 10, Some(("sha512", b"\x02"))),
+            manifest_row(2, 10, Some(("sha1", b"\x03"))),
+            manifest_row(3, 20, None),
+        ];
+
+        let (mut sender, receiver) = futures::channel::mpsc::channel(16);
+        futures::executor::block_on(send_manifest_rows(
+            futures::stream::iter(rows.into_iter().map(Ok)),
+            &mut sender,
+        ))
+        .unwrap();
+        drop(sender);
+
+        let body = futures::executor::block_on(receiver.collect::<Vec<_>>())
+            .into_iter()
+            .map(|x| x.unwrap())
+            .fold(Vec::new(), |mut body, chunk| {
+                body.extend_from_slice(&chunk);
+                body
+            });
+        let files: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(files.len(), 3);
+
+        assert_eq!(files[0]["filename"], "file-1.jar");
+        assert_eq!(files[0]["size"], 100);
+        assert_eq!(files[0]["hashes"]["sha1"], "01");
+        assert_eq!(files[0]["hashes"]["sha512"], "02");
+
+        assert_eq!(files[1]["filename"], "file-2.jar");
+        assert_eq!(files[1]["hashes"]["sha1"], "03");
+        assert_eq!(files[0]["version_id"], files[1]["version_id"]);
+
+        assert_eq!(files[2]["filename"], "file-3.jar");
+        assert_eq!(files[2]["hashes"], serde_json::json!({}));
+        assert_ne!(files[2]["version_id"], files[0]["version_id"]);
+    }
+
+    #[test]
+    fn unknown_categories_fail_before_any_are_replaced() {
+        let mut categories = vec![1];
+
+        let result = require_all_exist("Category", vec![("technology", Some(2)), ("tech", None)]);
+        if let Ok(category_ids) = &result {
+            categories = category_ids.clone();
+        }
+
+        match result {
+            Err(ApiError::InvalidInputError(message)) => {
+                assert_eq!(message, "Category tech does not exist.")
+            }
+            _ => panic!("an unknown category was accepted"),
+        }
+        assert_eq!(categories, vec![1]);
+
+        assert_eq!(
+            require_all_exist(
+                "Category",
+                vec![("technology", Some(2)), ("magic", Some(3))]
+            )
+            .unwrap(),
+            vec![2, 3]
+        );
+    }
+
+    fn day(date: &str, downloads: i64) -> (chrono::DateTime<chrono::Utc>, i64) {
+        (crate::util::time::parse_timestamp(date).unwrap(), downloads)
+    }
+
+    fn bucket_counts(buckets: &[DownloadBucket]) -> Vec<(String, u32)> {
+        buckets
+            .iter()
+            .map(|x| (x.date.format("%Y-%m-%d").to_string(), x.downloads))
+            .collect()
+    }
+
+    #[test]
+    fn downloads_are_bucketed_by_day_and_week() {
+        // 2021-07-19 is a Monday
+        let days = vec![
+            day("2021-07-16T00:00:00Z", 1),
+            day("2021-07-19T00:00:00Z", 2),
+            day("2021-07-21T00:00:00Z", 3),
+            day("2021-07-25T00:00:00Z", 4),
+            day("2021-07-26T00:00:00Z", 5),
+        ];
+
+        let by_day = bucket_downloads(days.clone(), DownloadResolution::Day);
+        assert_eq!(by_day.len(), 5);
+        assert_eq!(by_day[2].downloads, 3);
+
+        let by_week = bucket_downloads(days, DownloadResolution::Week);
+        assert_eq!(
+            bucket_counts(&by_week),
+            vec![
+                ("2021-07-12".to_string(), 1),
+                ("2021-07-19".to_string(), 9),
+                ("2021-07-26".to_string(), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn download_resolutions_are_validated() {
+        assert_eq!(
+            DownloadResolution::parse(None).unwrap(),
+            DownloadResolution::Day
+        );
+        assert_eq!(
+            DownloadResolution::parse(Some("week")).unwrap(),
+            DownloadResolution::Week
+        );
+        assert!(matches!(
+            DownloadResolution::parse(Some("month")),
+            Err(ApiError::InvalidInputError(_))
+        ));
+    }
+
+    #[test]
+    fn slugs_may_match_their_own_project_id() {
+        let project_id = database::models::ids::ProjectId(1000);
+        let own_id = ProjectId::from(project_id).to_string();
+
+        assert_eq!(slug_collision_id(&own_id, project_id), None);
+    }
+
+    #[test]
+    fn slugs_matching_other_project_ids_are_checked() {
+        let project_id = database::models::ids::ProjectId(1000);
+        let other_id = database::models::ids::ProjectId(2000);
+
+        assert_eq!(
+            slug_collision_id(&ProjectId::from(other_id).to_string(), project_id),
+            Some(other_id)
+        );
+
+        // Slugs which can't be ids never collide
+        assert_eq!(slug_collision_id("my-project", project_id), None);
+    }
+
+    fn uploaded_icon() -> crate::file_hosting::UploadFileData {
+        crate::file_hosting::UploadFileData {
+            file_id: "new".to_string(),
+            file_name: "data/AAAA/new.png".to_string(),
+            content_length: 100,
+            content_sha512: String::new(),
+            content_sha1: String::new(),
+            content_md5: None,
+            content_type: "image/png".to_string(),
+            upload_timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn new_icons_are_deleted_if_the_project_cant_be_updated() {
+        let file_host = crate::file_hosting::RecordingHost::new(false);
+
+        let result = futures::executor::block_on(update_icon(
+            &file_host,
+            &uploaded_icon(),
+            "https://cdn.example.com/data/AAAA/new.png",
+            Some("https://cdn.example.com/data/AAAA/old.png"),
+            futures::future::ready(Err(sqlx::Error::PoolTimedOut)),
+        ));
+
+        assert!(matches!(
+            result,
+            Err(ApiError::SqlxDatabaseError(sqlx::Error::PoolTimedOut))
+        ));
+        assert_eq!(
+            file_host.deleted.into_inner().unwrap(),
+            vec!["data/AAAA/new.png"]
+        );
+    }
+
+    #[test]
+    fn icons_the_project_already_used_are_kept() {
+        let file_host = crate::file_hosting::RecordingHost::new(false);
+
+        let result = futures::executor::block_on(update_icon(
+            &file_host,
+            &uploaded_icon(),
+            "https://cdn.example.com/data/AAAA/new.png",
+            Some("https://cdn.example.com/data/AAAA/new.png"),
+            futures::future::ready(Err(sqlx::Error::PoolTimedOut)),
+        ));
+
+        assert!(result.is_err());
+        assert!(file_host.deleted.into_inner().unwrap().is_empty());
+    }
+
+    #[test]
+    fn failed_icon_cleanups_return_the_database_error() {
+        let file_host = crate::file_hosting::RecordingHost::new(true);
+
+        let result = futures::executor::block_on(update_icon(
+            &file_host,
+            &uploaded_icon(),
+            "https://cdn.example.com/data/AAAA/new.png",
+            None,
+            futures::future::ready(Err(sqlx::Error::PoolTimedOut)),
+        ));
+
+        assert!(matches!(
+            result,
+            Err(ApiError::SqlxDatabaseError(sqlx::Error::PoolTimedOut))
+        ));
+    }
+
+    #[test]
+    fn new_icons_are_kept_once_the_project_is_updated() {
+        let file_host = crate::file_hosting::RecordingHost::new(false);
+
+        let result = futures::executor::block_on(update_icon(
+            &file_host,
+            &uploaded_icon(),
+            "https://cdn.example.com/data/AAAA/new.png",
+            None,
+            futures::future::ready(Ok(())),
+        ));
+
+        assert!(result.is_ok());
+        assert!(file_host.deleted.into_inner().unwrap().is_empty());
+    }
+
+    #[test]
+    fn removed_icons_are_cleared_and_deleted() {
+        let file_host = crate::file_hosting::RecordingHost::new(false);
+        let cleared = std::cell::Cell::new(false);
+
+        let result = futures::executor::block_on(remove_icon(
+            &file_host,
+            "https://cdn.example.com/data/AAAA/icon.png",
+            "https://cdn.example.com",
+            async {
+                cleared.set(true);
+                Ok(())
+            },
+        ));
+
+        assert!(result.is_ok());
+        assert!(cleared.get());
+        assert_eq!(
+            file_host.deleted.into_inner().unwrap(),
+            vec!["data/AAAA/icon.png"]
+        );
+    }
+
+    #[test]
+    fn failed_icon_deletions_are_only_logged() {
+        let file_host = crate::file_hosting::RecordingHost::new(true);
+
+        let result = futures::executor::block_on(remove_icon(
+            &file_host,
+            "https://cdn.example.com/data/AAAA/icon.png",
+            "https://cdn.example.com",
+            futures::future::ready(Ok(())),
+        ));
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn icons_are_kept_if_the_project_cant_be_updated() {
+        let file_host = crate::file_hosting::RecordingHost::new(false);
+
+        let result = futures::executor::block_on(remove_icon(
+            &file_host,
+            "https://cdn.example.com/data/AAAA/icon.png",
+            "https://cdn.example.com",
+            futures::future::ready(Err(sqlx::Error::PoolTimedOut)),
+        ));
+
+        assert!(result.is_err());
+        assert!(file_host.deleted.into_inner().unwrap().is_empty());
+    }
+
+    fn listed_project(
+        id: i64,
+        downloads: i32,
+        published: &str,
+    ) -> database::models::project_item::QueryProject {
+        let mut project = database::models::project_item::QueryProject::example(id);
+        project.inner.downloads = downloads;
+        project.inner.published = crate::util::time::parse_timestamp(published).unwrap();
+        project
+    }
+
+    fn listing(order: ProjectListOrder) -> Vec<i64> {
+        let mut projects = vec![
+            listed_project(1, 50, "2021-01-01T00:00:00Z"),
+            listed_project(2, 900, "2021-02-01T00:00:00Z"),
+            listed_project(3, 50, "2021-03-01T00:00:00Z"),
+            listed_project(4, 50, "2021-03-01T00:00:00Z"),
+            listed_project(5, 10, "2021-04-01T00:00:00Z"),
+        ];
+        order.sort(&mut projects);
+        projects.into_iter().map(|x| x.inner.id.0).collect()
+    }
+
+    #[test]
+    fn recent_projects_are_newest_first() {
+        assert_eq!(listing(ProjectListOrder::Published), vec![5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn popular_projects_are_most_downloaded_first() {
+        // Projects with as many downloads are ordered like recent projects
+        assert_eq!(listing(ProjectListOrder::Downloads), vec![2, 4, 3, 1, 5]);
+    }
+
+    #[test]
+    fn hidden_projects_are_not_listed() {
+        let listed = listed_statuses();
+
+        for status in [
+            ProjectStatus::Approved,
+            ProjectStatus::Archived,
+            ProjectStatus::Unlisted,
+            ProjectStatus::Draft,
+            ProjectStatus::Processing,
+            ProjectStatus::Rejected,
+            ProjectStatus::Unknown,
+        ]
+        .iter()
+        {
+            let expected = matches!(status, ProjectStatus::Approved | ProjectStatus::Archived);
+            assert_eq!(listed.contains(&status.to_string()), expected, "{}", status);
+        }
+    }
+
+    #[test]
+    fn project_listing_pages_are_capped() {
+        let pagination = PaginationConfig {
+            default_page_size: 10,
+            max_page_size: 100,
+        };
+
+        let page = ProjectListPagination {
+            count: None,
+            offset: 0,
+        };
+        assert_eq!(list_page(&page, &pagination), (10, 0));
+
+        let page = ProjectListPagination {
+            count: Some(1000),
+            offset: usize::MAX,
+        };
+        assert_eq!(list_page(&page, &pagination), (100, i64::MAX));
+    }
+
+    #[test]
+    fn fileless_drafts_cannot_be_published() {
+        assert!(requires_files(
+            &ProjectStatus::Draft,
+            &ProjectStatus::Approved
+        ));
+        assert!(requires_files(
+            &ProjectStatus::Processing,
+            &ProjectStatus::Approved
+        ));
+        assert!(matches!(
+            check_files_for_status(false, &ProjectStatus::Approved),
+            Err(ApiError::InvalidInputError(_))
+        ));
+    }
+
+    #[test]
+    fn drafts_with_files_can_be_published() {
+        assert!(check_files_for_status(true, &ProjectStatus::Approved).is_ok());
+    }
+
+    #[test]
+    fn only_unpublished_projects_need_files() {
+        assert!(!requires_files(
+            &ProjectStatus::Unlisted,
+            &ProjectStatus::Approved
+        ));
+        assert!(!requires_files(
+            &ProjectStatus::Archived,
+            &ProjectStatus::Approved
+        ));
+        assert!(!requires_files(
+            &ProjectStatus::Draft,
+            &ProjectStatus::Rejected
+        ));
+    }
+
+    #[test]
+    fn processing_projects_are_withdrawn_to_drafts() {
+        assert!(matches!(
+            withdrawn_status(&ProjectStatus::Processing),
+            Ok(ProjectStatus::Draft)
+        ));
+    }
+
+    #[test]
+    fn approved_projects_cannot_be_withdrawn() {
+        assert!(matches!(
+            withdrawn_status(&ProjectStatus::Approved),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert!(withdrawn_status(&ProjectStatus::Draft).is_err());
+    }
+
+    #[test]
+    fn featured_gallery_images_are_shown_over_icons() {
+        assert_eq!(
+            card_image_url(
+                Some("https://cdn.example.com/gallery.png".to_string()),
+                Some("https://cdn.example.com/icon.png".to_string()),
+            )
+            .as_deref(),
+            Some("https://cdn.example.com/gallery.png")
+        );
+    }
+
+    #[test]
+    fn cards_fall_back_to_the_icon() {
+        assert_eq!(
+            card_image_url(None, Some("https://cdn.example.com/icon.png".to_string())).as_deref(),
+            Some("https://cdn.example.com/icon.png")
+        );
+        assert_eq!(card_image_url(None, None), None);
+    }
+
+    #[test]
+    fn edited_links_must_use_https_when_required() {
+        let edit: EditProject = serde_json::from_value(serde_json::json!({
+            "issues_url": "https://example.com/issues",
+            "source_url": null,
+            "wiki_url": "https://example.com/wiki",
+            "license_url": "https://example.com/license",
+            "discord_url": "http://example.com/discord",
+            "donation_urls": [{
+                "id": "patreon",
+                "platform": "Patreon",
+                "url": "https://example.com/donate",
+            }],
+        }))
+        .unwrap();
+
+        assert_eq!(edit.external_urls().count(), 5);
+        assert_eq!(
+            crate::util::validate::check_https_urls(true, edit.external_urls()),
+            Some("The link http://example.com/discord must use HTTPS.".to_string())
+        );
+        assert!(crate::util::validate::check_https_urls(false, edit.external_urls()).is_none());
+    }
+
+    #[test]
+    fn edits_with_https_links_are_accepted() {
+        let edit: EditProject = serde_json::from_value(serde_json::json!({
+            "issues_url": "https://example.com/issues",
+            "donation_urls": [{
+                "id": "patreon",
+                "platform": "Patreon",
+                "url": "https://example.com/donate",
+            }],
+        }))
+        .unwrap();
+
+        assert_eq!(edit.external_urls().count(), 2);
+        assert!(crate::util::validate::check_https_urls(true, edit.external_urls()).is_none());
+    }
+
+    #[test]
+    fn versions_are_counted_per_channel() {
+        let channels = futures::stream::iter(
+            vec!["release", "beta", "release", "alpha", "release", "beta"]
+                .into_iter()
+                .map(|x| Ok(x.to_string())),
+        );
+
+        let counts = futures::executor::block_on(count_channels(channels)).unwrap();
+
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts["release"], 3);
+        assert_eq!(counts["beta"], 2);
+        assert_eq!(counts["alpha"], 1);
+    }
+
+    #[test]
+    fn projects_without_versions_have_no_channels() {
+        let channels = futures::stream::iter(Vec::<Result<String, sqlx::Error>>::new());
+
+        assert!(futures::executor::block_on(count_channels(channels))
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -1,11 +1,14 @@
 use crate::models::ids::{ProjectId, UserId, VersionId};
 use crate::models::reports::{ItemType, Report};
+use crate::models::users::User;
 use crate::routes::ApiError;
 use crate::util::auth::{check_is_moderator_from_headers, get_user_from_headers};
+use crate::util::pagination::PaginationConfig;
 use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
 use futures::StreamExt;
 use serde::Deserialize;
 use sqlx::PgPool;
+use std::convert::TryFrom;
 
 #[derive(Deserialize)]
 pub struct CreateReport {
@@ -53,52 +56,68 @@ pub async fn report_create(
         created: chrono::Utc::now(),
     };
 
-    match new_report.item_type {
+    set_report_target(&mut report, &new_report.item_type, &new_report.item_id)?;
+
+    report.insert(&mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(Report {
+        id: id.into(),
+        report_type: new_report.report_type.clone(),
+        item_id: new_report.item_id.clone(),
+        item_type: new_report.item_type.clone(),
+        reporter: current_user.id,
+        body: new_report.body.clone(),
+        created: chrono::Utc::now(),
+    }))
+}
+
+/// Points `report` at the item it was filed against, which must be set
+fn set_report_target(
+    report: &mut crate::database::models::report_item::Report,
+    item_type: &ItemType,
+    item_id: &str,
+) -> Result<(), ApiError> {
+    if item_id.is_empty() {
+        return Err(ApiError::InvalidInputError(
+            "A report must have an item to report!".to_string(),
+        ));
+    }
+
+    match item_type {
         ItemType::Project => {
-            report.project_id = Some(
-                serde_json::from_str::<ProjectId>(&*format!("\"{}\"", new_report.item_id))?.into(),
-            )
+            report.project_id =
+                Some(serde_json::from_str::<ProjectId>(&*format!("\"{}\"", item_id))?.into())
         }
         ItemType::Version => {
-            report.version_id = Some(
-                serde_json::from_str::<VersionId>(&*format!("\"{}\"", new_report.item_id))?.into(),
-            )
+            report.version_id =
+                Some(serde_json::from_str::<VersionId>(&*format!("\"{}\"", item_id))?.into())
         }
         ItemType::User => {
-            report.user_id = Some(
-                serde_json::from_str::<UserId>(&*format!("\"{}\"", new_report.item_id))?.into(),
-            )
+            report.user_id =
+                Some(serde_json::from_str::<UserId>(&*format!("\"{}\"", item_id))?.into())
         }
         ItemType::Unknown => {
             return Err(ApiError::InvalidInputError(format!(
                 "Invalid report item type: {}",
-                new_report.item_type.as_str()
+                item_type.as_str()
             )))
         }
     }
 
-    report.insert(&mut transaction).await?;
-    transaction.commit().await?;
+    Ok(())
+}
 
-    Ok(HttpResponse::Ok().json(Report {
-        id: id.into(),
-        report_type: new_report.report_type.clone(),
-        item_id: new_report.item_id.clone(),
-        item_type: new_report.item_type.clone(),
-        reporter: current_user.id,
-        body: new_report.body.clone(),
-        created: chrono::Utc::now(),
-    }))
+/// Whether `user` may view a report filed by `reporter`
+fn can_view_report(user: &User, reporter: crate::database::models::ids::UserId) -> bool {
+    user.role.is_mod() || user.id == reporter.into()
 }
 
 #[derive(Deserialize)]
 pub struct ResultCount {
-    #[serde(default = "default_count")]
-    count: i16,
-}
-
-fn default_count() -> i16 {
-    100
+    count: Option<usize>,
+    #[serde(default)]
+    offset: usize,
 }
 
 #[get("report")]
@@ -106,6 +125,7 @@ pub async fn reports(
     req: HttpRequest,
     pool: web::Data<PgPool>,
     count: web::Query<ResultCount>,
+    pagination: web::Data<PaginationConfig>,
 ) -> Result<HttpResponse, ApiError> {
     check_is_moderator_from_headers(req.headers(), &**pool).await?;
 
@@ -115,9 +135,10 @@ pub async fn reports(
         "
         SELECT id FROM reports
         ORDER BY created ASC
-        LIMIT $1;
+        LIMIT $1 OFFSET $2;
         ",
-        count.count as i64
+        pagination.limit_or(count.count, 100) as i64,
+        i64::try_from(count.offset).unwrap_or(i64::MAX),
     )
     .fetch_many(&**pool)
     .try_filter_map(|e| async {
@@ -130,35 +151,63 @@ pub async fn reports(
     let query_reports =
         crate::database::models::report_item::Report::get_many(report_ids, &**pool).await?;
 
-    let mut reports = Vec::new();
-
-    for x in query_reports {
-        let mut item_id = "".to_string();
-        let mut item_type = ItemType::Unknown;
-
-        if let Some(project_id) = x.project_id {
-            item_id = serde_json::to_string::<ProjectId>(&project_id.into())?;
-            item_type = ItemType::Project;
-        } else if let Some(version_id) = x.version_id {
-            item_id = serde_json::to_string::<VersionId>(&version_id.into())?;
-            item_type = ItemType::Version;
-        } else if let Some(user_id) = x.user_id {
-            item_id = serde_json::to_string::<UserId>(&user_id.into())?;
-            item_type = ItemType::User;
+    let reports = query_reports
+        .into_iter()
+        .map(convert_report)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(HttpResponse::Ok().json(reports))
+}
+
+#[get("report/{id}")]
+pub async fn report_get(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    info: web::Path<(crate::models::reports::ReportId,)>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+
+    let report =
+        crate::database::models::report_item::Report::get(info.into_inner().0.into(), &**pool)
+            .await?;
+
+    if let Some(report) = report {
+        if !can_view_report(&user, report.reporter) {
+            return Ok(HttpResponse::NotFound().body(""));
         }
 
-        reports.push(Report {
-            id: x.id.into(),
-            report_type: x.report_type,
-            item_id,
-            item_type,
-            reporter: x.reporter.into(),
-            body: x.body,
-            created: x.created,
-        })
+        Ok(HttpResponse::Ok().json(convert_report(report)?))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
     }
+}
 
-    Ok(HttpResponse::Ok().json(reports))
+fn convert_report(
+    x: crate::database::models::report_item::QueryReport,
+) -> Result<Report, serde_json::Error> {
+    let mut item_id = "".to_string();
+    let mut item_type = ItemType::Unknown;
+
+    if let Some(project_id) = x.project_id {
+        item_id = serde_json::to_string::<ProjectId>(&project_id.into())?;
+        item_type = ItemType::Project;
+    } else if let Some(version_id) = x.version_id {
+        item_id = serde_json::to_string::<VersionId>(&version_id.into())?;
+        item_type = ItemType::Version;
+    } else if let Some(user_id) = x.user_id {
+        item_id = serde_json::to_string::<UserId>(&user_id.into())?;
+        item_type = ItemType::User;
+    }
+
+    Ok(Report {
+        id: x.id.into(),
+        report_type: x.report_type,
+        item_id,
+        item_type,
+        reporter: x.reporter.into(),
+        body: x.body,
+        created: x.created,
+    })
 }
 
 #[delete("report/{id}")]
@@ -181,3 +230,87 @@ pub async fn delete_report(
         Ok(HttpResponse::NotFound().body(""))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::ids::{ReportId, ReportTypeId};
+    use crate::models::users::Role;
+
+    fn report() -> crate::database::models::report_item::Report {
+        crate::database::models::report_item::Report {
+            id: ReportId(1),
+            report_type_id: ReportTypeId(1),
+            project_id: None,
+            version_id: None,
+            user_id: None,
+            body: "Spam".to_string(),
+            reporter: crate::database::models::ids::UserId(1),
+            created: chrono::Utc::now(),
+        }
+    }
+
+    fn user(id: u64, role: Role) -> User {
+        User {
+            id: UserId(id),
+            github_id: None,
+            username: "user".to_string(),
+            name: None,
+            email: None,
+            avatar_url: None,
+            bio: None,
+            created: chrono::Utc::now(),
+            role,
+        }
+    }
+
+    #[test]
+    fn reports_need_a_target() {
+        let mut report = report();
+
+        assert!(set_report_target(&mut report, &ItemType::Project, "").is_err());
+        assert!(report.project_id.is_none());
+    }
+
+    #[test]
+    fn reports_have_exactly_one_target() {
+        let mut report = report();
+        set_report_target(&mut report, &ItemType::Version, "AABBCCDD").unwrap();
+
+        assert!(report.project_id.is_none());
+        assert!(report.version_id.is_some());
+        assert!(report.user_id.is_none());
+
+        let new_report: CreateReport = serde_json::from_str(
+            r#"{"report_type": "spam", "item_id": "AABBCCDD", "item_type": "user", "body": ""}"#,
+        )
+        .unwrap();
+        let mut report = self::report();
+        set_report_target(&mut report, &new_report.item_type, &new_report.item_id).unwrap();
+
+        assert!(report.project_id.is_none());
+        assert!(report.version_id.is_none());
+        assert!(report.user_id.is_some());
+    }
+
+    #[test]
+    fn reports_reject_unknown_item_types() {
+        let mut report = report();
+
+        assert!(set_report_target(&mut report, &ItemType::Unknown, "AABBCCDD").is_err());
+        assert!(serde_json::from_str::<CreateReport>(
+            r#"{"report_type": "spam", "item_id": "AABBCCDD", "item_type": "mod", "body": ""}"#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn reports_are_visible_to_their_reporter_and_moderators() {
+        let reporter = crate::database::models::ids::UserId(1);
+
+        assert!(can_view_report(&user(1, Role::Developer), reporter));
+        assert!(!can_view_report(&user(2, Role::Developer), reporter));
+        assert!(can_view_report(&user(2, Role::Moderator), reporter));
+        assert!(can_view_report(&user(2, Role::Admin), reporter));
+    }
+}
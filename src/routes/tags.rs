@@ -37,13 +37,36 @@ pub struct CategoryData {
     project_type: String,
 }
 
-// TODO: searching / filtering? Could be used to implement a live
-// searching category list
+#[derive(serde::Deserialize)]
+pub struct ProjectTypeQuery {
+    /// Only include the tags which can be used for this project type
+    project_type: Option<String>,
+}
+
+// TODO: searching? Could be used to implement a live searching category list
 #[get("category")]
-pub async fn category_list(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
-    let mut results = Category::list(&**pool)
-        .await?
+pub async fn category_list(
+    pool: web::Data<PgPool>,
+    query: web::Query<ProjectTypeQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let results = list_categories(
+        Category::list(&**pool).await?,
+        query.project_type.as_deref(),
+    );
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// Lists the categories which can be used for `project_type`, or every category if it isn't
+/// given, sorted by name
+fn list_categories(categories: Vec<Category>, project_type: Option<&str>) -> Vec<CategoryData> {
+    let mut results = categories
         .into_iter()
+        .filter(|x| {
+            project_type
+                .map(|project_type| x.project_type == project_type)
+                .unwrap_or(true)
+        })
         .map(|x| CategoryData {
             icon: x.icon,
             name: x.category,
@@ -53,7 +76,7 @@ pub async fn category_list(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiE
 
     results.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
 
-    Ok(HttpResponse::Ok().json(results))
+    results
 }
 
 #[put("category")]
@@ -114,10 +137,25 @@ pub struct LoaderData {
 }
 
 #[get("loader")]
-pub async fn loader_list(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
-    let mut results = Loader::list(&**pool)
-        .await?
+pub async fn loader_list(
+    pool: web::Data<PgPool>,
+    query: web::Query<ProjectTypeQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let results = list_loaders(Loader::list(&**pool).await?, query.project_type.as_deref());
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// Lists the loaders which support `project_type`, or every loader if it isn't given,
+/// sorted by name
+fn list_loaders(loaders: Vec<Loader>, project_type: Option<&str>) -> Vec<LoaderData> {
+    let mut results = loaders
         .into_iter()
+        .filter(|x| {
+            project_type
+                .map(|project_type| x.supported_project_types.iter().any(|x| x == project_type))
+                .unwrap_or(true)
+        })
         .map(|x| LoaderData {
             icon: x.icon,
             name: x.loader,
@@ -127,7 +165,7 @@ pub async fn loader_list(pool: web::Data<PgPool>) -> Result<HttpResponse, ApiErr
 
     results.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
 
-    Ok(HttpResponse::Ok().json(results))
+    results
 }
 
 #[put("loader")]
@@ -184,6 +222,7 @@ pub async fn loader_delete(
 pub struct GameVersionQueryData {
     pub version: String,
     pub version_type: String,
+    #[serde(with = "crate::util::time::rfc3339")]
     pub date: chrono::DateTime<chrono::Utc>,
     pub major: bool,
 }
@@ -459,3 +498,75 @@ pub async fn report_type_delete(
         Ok(HttpResponse::NotFound().body(""))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories() -> Vec<Category> {
+        vec![
+            ("technology", "mod"),
+            ("Adventure", "modpack"),
+            ("adventure", "mod"),
+            ("magic", "mod"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(id, (category, project_type))| Category {
+            id: models::ids::CategoryId(id as i32),
+            category: category.to_string(),
+            project_type: project_type.to_string(),
+            icon: "<svg></svg>".to_string(),
+        })
+        .collect()
+    }
+
+    fn loaders() -> Vec<Loader> {
+        vec![
+            ("forge", vec!["mod", "modpack"]),
+            ("fabric", vec!["mod", "modpack"]),
+            ("bukkit", vec!["plugin"]),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(id, (loader, project_types))| Loader {
+            id: models::ids::LoaderId(id as i32),
+            loader: loader.to_string(),
+            icon: "<svg></svg>".to_string(),
+            supported_project_types: project_types.into_iter().map(String::from).collect(),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn categories_are_listed_by_name() {
+        let results = list_categories(categories(), None);
+
+        let names = results.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Adventure", "adventure", "magic", "technology"]);
+        assert_eq!(results[0].project_type, "modpack");
+    }
+
+    #[test]
+    fn categories_are_filtered_by_project_type() {
+        let results = list_categories(categories(), Some("mod"));
+
+        let names = results.iter().map(|x| x.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["adventure", "magic", "technology"]);
+        assert!(list_categories(categories(), Some("plugin")).is_empty());
+    }
+
+    #[test]
+    fn loaders_are_filtered_by_project_type() {
+        let names = |project_type| {
+            list_loaders(loaders(), project_type)
+                .into_iter()
+                .map(|x| x.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(None), vec!["bukkit", "fabric", "forge"]);
+        assert_eq!(names(Some("modpack")), vec!["fabric", "forge"]);
+        assert_eq!(names(Some("plugin")), vec!["bukkit"]);
+    }
+}
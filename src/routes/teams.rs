@@ -6,46 +6,27 @@ use crate::models::teams::{Permissions, TeamId};
 use crate::models::users::UserId;
 use crate::routes::ApiError;
 use crate::util::auth::get_user_from_headers;
+use crate::util::pagination::PaginationConfig;
 use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::convert::TryFrom;
 
 #[get("{id}/members")]
 pub async fn team_members_get_project(
     req: HttpRequest,
     info: web::Path<(String,)>,
+    web::Query(query): web::Query<TeamMembersPagination>,
     pool: web::Data<PgPool>,
+    pagination: web::Data<PaginationConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let string = info.into_inner().0;
     let project_data =
         crate::database::models::Project::get_from_slug_or_project_id(string, &**pool).await?;
 
     if let Some(project) = project_data {
-        let members_data = TeamMember::get_from_team_full(project.team_id, &**pool).await?;
-
-        let current_user = get_user_from_headers(req.headers(), &**pool).await.ok();
-
-        if let Some(user) = current_user {
-            let team_member =
-                TeamMember::get_from_user_id(project.team_id, user.id.into(), &**pool)
-                    .await
-                    .map_err(ApiError::DatabaseError)?;
-
-            if team_member.is_some() {
-                let team_members: Vec<crate::models::teams::TeamMember> = members_data
-                    .into_iter()
-                    .map(|data| convert_team_member(data, false))
-                    .collect();
-
-                return Ok(HttpResponse::Ok().json(team_members));
-            }
-        }
-
-        let team_members: Vec<crate::models::teams::TeamMember> = members_data
-            .into_iter()
-            .filter(|x| x.accepted)
-            .map(|data| convert_team_member(data, true))
-            .collect();
+        let team_members =
+            get_team_members_page(&req, project.team_id, &query, &pagination, &**pool).await?;
 
         Ok(HttpResponse::Ok().json(team_members))
     } else {
@@ -70,39 +51,78 @@ pub fn convert_team_member(
     }
 }
 
+#[derive(Deserialize)]
+pub struct TeamMembersPagination {
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
 #[get("{id}/members")]
 pub async fn team_members_get(
     req: HttpRequest,
     info: web::Path<(TeamId,)>,
+    web::Query(query): web::Query<TeamMembersPagination>,
     pool: web::Data<PgPool>,
+    pagination: web::Data<PaginationConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let id = info.into_inner().0;
-    let members_data = TeamMember::get_from_team_full(id.into(), &**pool).await?;
 
-    let current_user = get_user_from_headers(req.headers(), &**pool).await.ok();
+    let team_members = get_team_members_page(&req, id.into(), &query, &pagination, &**pool).await?;
 
-    if let Some(user) = current_user {
-        let team_member = TeamMember::get_from_user_id(id.into(), user.id.into(), &**pool)
-            .await
-            .map_err(ApiError::DatabaseError)?;
+    Ok(HttpResponse::Ok().json(team_members))
+}
 
-        if team_member.is_some() {
-            let team_members: Vec<crate::models::teams::TeamMember> = members_data
-                .into_iter()
-                .map(|data| convert_team_member(data, false))
-                .collect();
+/// Gets a page of a team's members as shown to the user making the request
+async fn get_team_members_page(
+    req: &HttpRequest,
+    team_id: crate::database::models::ids::TeamId,
+    query: &TeamMembersPagination,
+    pagination: &PaginationConfig,
+    pool: &PgPool,
+) -> Result<Vec<crate::models::teams::TeamMember>, ApiError> {
+    let current_user = get_user_from_headers(req.headers(), pool).await.ok();
+
+    let is_member = if let Some(user) = current_user {
+        TeamMember::get_from_user_id(team_id, user.id.into(), pool)
+            .await
+            .map_err(ApiError::DatabaseError)?
+            .is_some()
+    } else {
+        false
+    };
 
-            return Ok(HttpResponse::Ok().json(team_members));
-        }
-    }
+    visible_team_members(is_member, |include_pending| {
+        TeamMember::get_page_from_team_full(
+            team_id,
+            include_pending,
+            Some(pagination.limit_or(query.limit, 100) as i64),
+            i64::try_from(query.offset).unwrap_or(i64::MAX),
+            pool,
+        )
+    })
+    .await
+}
 
-    let team_members: Vec<crate::models::teams::TeamMember> = members_data
+/// Gets the members of a team as shown to someone who is or isn't a member of it, where
+/// `get_page` loads a page of the members, including the pending ones if asked to.
+/// Pending members and the permissions of every member are only shown to the team.
+async fn visible_team_members<F, Fut>(
+    is_member: bool,
+    get_page: F,
+) -> Result<Vec<crate::models::teams::TeamMember>, ApiError>
+where
+    F: FnOnce(bool) -> Fut,
+    Fut: std::future::Future<
+        Output = Result<Vec<QueryTeamMember>, crate::database::models::DatabaseError>,
+    >,
+{
+    let members_data = get_page(is_member).await?;
+
+    Ok(members_data
         .into_iter()
-        .filter(|x| x.accepted)
-        .map(|data| convert_team_member(data, true))
-        .collect();
-
-    Ok(HttpResponse::Ok().json(team_members))
+        .map(|data| convert_team_member(data, !is_member))
+        .collect())
 }
 
 #[post("{id}/join")]
@@ -177,14 +197,14 @@ pub async fn add_team_member(
     let member = match team_member {
         Some(m) => m,
         None => {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You don't have permission to invite users to this team".to_string(),
             ))
         }
     };
 
     if !member.permissions.contains(Permissions::MANAGE_INVITES) {
-        return Err(ApiError::CustomAuthenticationError(
+        return Err(ApiError::Forbidden(
             "You don't have permission to invite users to this team".to_string(),
         ));
     }
@@ -300,14 +320,14 @@ pub async fn edit_team_member(
     let member = match team_member {
         Some(m) => m,
         None => {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You don't have permission to edit members of this team".to_string(),
             ))
         }
     };
 
     if !member.permissions.contains(Permissions::EDIT_MEMBER) {
-        return Err(ApiError::CustomAuthenticationError(
+        return Err(ApiError::Forbidden(
             "You don't have permission to edit members of this team".to_string(),
         ));
     }
@@ -341,6 +361,116 @@ pub async fn edit_team_member(
     Ok(HttpResponse::NoContent().body(""))
 }
 
+#[post("{id}/leave")]
+pub async fn leave_team(
+    req: HttpRequest,
+    info: web::Path<(TeamId,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let id = info.into_inner().0.into();
+
+    let current_user = get_user_from_headers(req.headers(), &**pool).await?;
+    let member = TeamMember::get_from_user_id(id, current_user.id.into(), &**pool).await?;
+    let member = check_can_leave(member)?;
+
+    TeamMember::delete(id, member.user_id, &**pool).await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// Checks that the caller's membership of a team, if any, can be left. The owner has to
+/// transfer ownership before leaving, so a team is never left without one.
+fn check_can_leave(member: Option<TeamMember>) -> Result<TeamMember, ApiError> {
+    let member = member.ok_or_else(|| {
+        ApiError::InvalidInputError("You are not a member of this team".to_string())
+    })?;
+
+    if member.role == crate::models::teams::OWNER_ROLE {
+        return Err(ApiError::InvalidInputError(
+            "The owner can't leave a team, transfer its ownership first".to_string(),
+        ));
+    }
+
+    Ok(member)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TransferOwnership {
+    pub user_id: UserId,
+}
+
+/// The role given to the previous owner of a team when ownership is transferred, which can
+/// be set with `FORMER_OWNER_ROLE`
+fn former_owner_role() -> String {
+    dotenv::var("FORMER_OWNER_ROLE").unwrap_or_else(|_| default_role())
+}
+
+/// The previous owner keeps every permission except deleting the project, which is left
+/// to the new owner
+fn former_owner_permissions() -> Permissions {
+    Permissions::ALL - Permissions::DELETE_PROJECT
+}
+
+/// Checks that `current_owner` may hand the team to `new_owner`, who must have accepted
+/// their invite to the team
+fn check_ownership_transfer(
+    current_owner: Option<&TeamMember>,
+    new_owner: Option<&TeamMember>,
+) -> Result<(), ApiError> {
+    let current_owner = match current_owner {
+        Some(m) if m.accepted && m.role == crate::models::teams::OWNER_ROLE => m,
+        _ => {
+            return Err(ApiError::Forbidden(
+                "Only the owner of a team can transfer its ownership".to_string(),
+            ))
+        }
+    };
+
+    match new_owner {
+        Some(m) if m.user_id == current_owner.user_id => Err(ApiError::InvalidInputError(
+            "You are already the owner of this team".to_string(),
+        )),
+        Some(m) if m.accepted => Ok(()),
+        _ => Err(ApiError::InvalidInputError(
+            "Ownership can only be transferred to a member of the team who has accepted their invite"
+                .to_string(),
+        )),
+    }
+}
+
+#[post("{id}/owner")]
+pub async fn transfer_ownership(
+    req: HttpRequest,
+    info: web::Path<(TeamId,)>,
+    pool: web::Data<PgPool>,
+    new_owner: web::Json<TransferOwnership>,
+) -> Result<HttpResponse, ApiError> {
+    let id = info.into_inner().0.into();
+
+    let current_user = get_user_from_headers(req.headers(), &**pool).await?;
+    let member = TeamMember::get_from_user_id_pending(id, current_user.id.into(), &**pool).await?;
+    let new_owner_member =
+        TeamMember::get_from_user_id_pending(id, new_owner.user_id.into(), &**pool).await?;
+
+    check_ownership_transfer(member.as_ref(), new_owner_member.as_ref())?;
+
+    let mut transaction = pool.begin().await?;
+
+    TeamMember::transfer_ownership(
+        id,
+        current_user.id.into(),
+        new_owner.user_id.into(),
+        former_owner_role(),
+        former_owner_permissions(),
+        &mut transaction,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
 #[delete("{id}/members/{user_id}")]
 pub async fn remove_team_member(
     req: HttpRequest,
@@ -358,7 +488,7 @@ pub async fn remove_team_member(
     let member = match team_member {
         Some(m) => m,
         None => {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You don't have permission to remove members from this team".to_string(),
             ))
         }
@@ -369,7 +499,7 @@ pub async fn remove_team_member(
     if let Some(delete_member) = delete_member {
         if delete_member.role == crate::models::teams::OWNER_ROLE {
             // The owner cannot be removed from a team
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "The owner can't be removed from a team".to_string(),
             ));
         }
@@ -382,7 +512,7 @@ pub async fn remove_team_member(
             {
                 TeamMember::delete(id, user_id, &**pool).await?;
             } else {
-                return Err(ApiError::CustomAuthenticationError(
+                return Err(ApiError::Forbidden(
                     "You do not have permission to remove a member from this team".to_string(),
                 ));
             }
@@ -394,7 +524,7 @@ pub async fn remove_team_member(
             // permission can remove it.
             TeamMember::delete(id, user_id, &**pool).await?;
         } else {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You do not have permission to cancel a team invite".to_string(),
             ));
         }
@@ -403,3 +533,135 @@ pub async fn remove_team_member(
         Ok(HttpResponse::NotFound().body(""))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::ids::{TeamMemberId, UserId};
+
+    fn member(user_id: i64, role: &str, accepted: bool) -> TeamMember {
+        TeamMember {
+            id: TeamMemberId(user_id),
+            team_id: crate::database::models::ids::TeamId(1),
+            user_id: UserId(user_id),
+            role: role.to_string(),
+            permissions: Permissions::default(),
+            accepted,
+        }
+    }
+
+    #[test]
+    fn only_the_owner_can_transfer_ownership() {
+        let owner = member(1, crate::models::teams::OWNER_ROLE, true);
+        let caller = member(2, "Member", true);
+
+        assert!(check_ownership_transfer(Some(&owner), Some(&caller)).is_ok());
+        assert!(matches!(
+            check_ownership_transfer(Some(&caller), Some(&owner)),
+            Err(ApiError::Forbidden(_))
+        ));
+        assert!(matches!(
+            check_ownership_transfer(None, Some(&owner)),
+            Err(ApiError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn ownership_goes_to_accepted_members() {
+        let owner = member(1, crate::models::teams::OWNER_ROLE, true);
+        let invited = member(2, "Member", false);
+
+        assert!(matches!(
+            check_ownership_transfer(Some(&owner), None),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert!(matches!(
+            check_ownership_transfer(Some(&owner), Some(&invited)),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert!(matches!(
+            check_ownership_transfer(Some(&owner), Some(&owner)),
+            Err(ApiError::InvalidInputError(_))
+        ));
+    }
+
+    #[test]
+    fn former_owners_cannot_delete_the_project() {
+        let permissions = former_owner_permissions();
+
+        assert!(!permissions.contains(Permissions::DELETE_PROJECT));
+        assert!(permissions.contains(Permissions::EDIT_MEMBER | Permissions::UPLOAD_VERSION));
+    }
+
+    #[test]
+    fn members_can_leave_a_team() {
+        let member = check_can_leave(Some(member(2, "Member", true))).unwrap();
+        assert_eq!(member.user_id, UserId(2));
+
+        // Members can also decline an invite by leaving
+        assert!(check_can_leave(Some(self::member(3, "Member", false))).is_ok());
+    }
+
+    #[test]
+    fn the_owner_cannot_leave_a_team() {
+        assert!(matches!(
+            check_can_leave(Some(member(1, crate::models::teams::OWNER_ROLE, true))),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert!(matches!(
+            check_can_leave(None),
+            Err(ApiError::InvalidInputError(_))
+        ));
+    }
+
+    fn query_member(user_id: i64, accepted: bool) -> QueryTeamMember {
+        QueryTeamMember {
+            id: TeamMemberId(user_id),
+            team_id: crate::database::models::ids::TeamId(1),
+            user: crate::database::models::User {
+                id: UserId(user_id),
+                github_id: None,
+                username: format!("user{}", user_id),
+                name: None,
+                email: None,
+                avatar_url: None,
+                bio: None,
+                created: chrono::Utc::now(),
+                role: "developer".to_string(),
+            },
+            role: "Member".to_string(),
+            permissions: Permissions::UPLOAD_VERSION,
+            accepted,
+        }
+    }
+
+    /// Loads a team with an accepted and a pending member the way
+    /// `get_page_from_team_full` does
+    fn team_page(
+        include_pending: bool,
+    ) -> futures::future::Ready<Result<Vec<QueryTeamMember>, crate::database::models::DatabaseError>>
+    {
+        futures::future::ready(Ok(vec![query_member(1, true), query_member(2, false)]
+            .into_iter()
+            .filter(|x| include_pending || x.accepted)
+            .collect()))
+    }
+
+    #[test]
+    fn team_members_see_pending_members() {
+        let members = futures::executor::block_on(visible_team_members(true, team_page)).unwrap();
+
+        assert_eq!(members.len(), 2);
+        assert!(members.iter().any(|x| !x.accepted));
+        assert!(members.iter().all(|x| x.permissions.is_some()));
+    }
+
+    #[test]
+    fn pending_members_are_hidden_from_everyone_else() {
+        let members = futures::executor::block_on(visible_team_members(false, team_page)).unwrap();
+
+        assert_eq!(members.len(), 1);
+        assert!(members[0].accepted);
+        assert!(members[0].permissions.is_none());
+    }
+}
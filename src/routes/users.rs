@@ -1,11 +1,13 @@
 use crate::database::models::User;
-use crate::file_hosting::FileHost;
-use crate::models::notifications::Notification;
-use crate::models::projects::{Project, ProjectStatus};
+use crate::file_hosting::{get_cdn_path, FileHost};
+use crate::models::notifications::{Notification, NOTIFICATION_TYPES};
+use crate::models::projects::{Project, ProjectId, ProjectStatus, VersionId};
+use crate::models::teams::Permissions;
 use crate::models::users::{Role, UserId};
 use crate::routes::notifications::convert_notification;
 use crate::routes::ApiError;
 use crate::util::auth::get_user_from_headers;
+use crate::util::pagination::PaginationConfig;
 use crate::util::validate::validation_errors_to_string;
 use actix_web::{delete, get, patch, web, HttpRequest, HttpResponse};
 use futures::StreamExt;
@@ -13,6 +15,8 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::sync::Arc;
 use validator::Validate;
 
@@ -25,6 +29,58 @@ pub async fn user_auth_get(
         .json(get_user_from_headers(req.headers(), &mut *pool.acquire().await?).await?))
 }
 
+/// Gets the permissions the current user has in each project they are a member of
+#[get("permissions")]
+pub async fn user_permissions(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let user_id: crate::database::models::UserId = user.id.into();
+
+    // Moderators can do anything to any project
+    let is_mod = user.role.is_mod();
+
+    use futures::stream::TryStreamExt;
+
+    let memberships = sqlx::query!(
+        "
+        SELECT m.id, tm.permissions FROM team_members tm
+        INNER JOIN mods m ON m.team_id = tm.team_id
+        WHERE tm.user_id = $1 AND tm.accepted = TRUE
+        ",
+        user_id as crate::database::models::ids::UserId,
+    )
+    .fetch_many(&**pool)
+    .try_filter_map(|e| async { Ok(e.right().map(|m| (m.id, m.permissions))) })
+    .try_collect::<Vec<(i64, i64)>>()
+    .await?;
+
+    Ok(HttpResponse::Ok().json(project_permissions(memberships, is_mod)))
+}
+
+/// Combines the permissions of each of a user's team memberships by the project of the team
+fn project_permissions(
+    memberships: Vec<(i64, i64)>,
+    is_mod: bool,
+) -> HashMap<ProjectId, Permissions> {
+    let mut permissions = HashMap::new();
+
+    for (project_id, bits) in memberships {
+        let entry = permissions
+            .entry(ProjectId(project_id as u64))
+            .or_insert_with(Permissions::empty);
+
+        if is_mod {
+            *entry = Permissions::ALL;
+        } else {
+            *entry |= Permissions::from_bits(bits as u64).unwrap_or_default();
+        }
+    }
+
+    permissions
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct UserIds {
     pub ids: String,
@@ -238,32 +294,15 @@ pub async fn user_edit(
             }
 
             if let Some(role) = &new_user.role {
-                if !user.role.is_mod() {
-                    return Err(ApiError::CustomAuthenticationError(
-                        "You do not have the permissions to edit the role of this user!"
-                            .to_string(),
-                    ));
-                }
+                check_can_edit_roles(&user)?;
 
-                let role = role.to_string();
-
-                sqlx::query!(
-                    "
-                    UPDATE users
-                    SET role = $1
-                    WHERE (id = $2)
-                    ",
-                    role,
-                    id as crate::database::models::ids::UserId,
-                )
-                .execute(&mut *transaction)
-                .await?;
+                update_role(id, role, &mut transaction).await?;
             }
 
             transaction.commit().await?;
             Ok(HttpResponse::NoContent().body(""))
         } else {
-            Err(ApiError::CustomAuthenticationError(
+            Err(ApiError::Forbidden(
                 "You do not have permission to edit this user!".to_string(),
             ))
         }
@@ -272,6 +311,136 @@ pub async fn user_edit(
     }
 }
 
+#[derive(Deserialize)]
+pub struct EditRole {
+    pub role: String,
+}
+
+#[patch("{id}/role")]
+pub async fn user_role_edit(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    new_role: web::Json<EditRole>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+
+    check_can_edit_roles(&user)?;
+
+    let role = parse_role(&new_role.role)?;
+
+    let id_option =
+        crate::database::models::User::get_id_from_username_or_id(info.into_inner().0, &**pool)
+            .await?;
+
+    if let Some(id) = id_option {
+        let mut transaction = pool.begin().await?;
+
+        update_role(id, &role, &mut transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(HttpResponse::NoContent().body(""))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
+/// Checks that a user can change the roles of users, which only admins can do
+fn check_can_edit_roles(user: &crate::models::users::User) -> Result<(), ApiError> {
+    if user.role != Role::Admin {
+        return Err(ApiError::Forbidden(
+            "You do not have the permissions to edit the role of this user!".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_role(role: &str) -> Result<Role, ApiError> {
+    let parsed = Role::from_string(role);
+
+    // `from_string` falls back to developer, so unknown roles have to be caught here
+    if parsed.to_string() != role {
+        return Err(ApiError::InvalidInputError(format!(
+            "Invalid role: {}",
+            role
+        )));
+    }
+
+    Ok(parsed)
+}
+
+/// Checks that demoting a user doesn't leave no admins, given whether the user is an admin
+/// and how many admins there are
+fn check_not_last_admin(is_admin: bool, admins: i64) -> Result<(), ApiError> {
+    if is_admin && admins <= 1 {
+        return Err(ApiError::InvalidInputError(
+            "The last remaining admin cannot be demoted!".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Sets the role of a user, refusing to demote the last remaining admin so that
+/// there is always someone who can manage roles
+async fn update_role(
+    id: crate::database::models::ids::UserId,
+    role: &Role,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), ApiError> {
+    if *role != Role::Admin {
+        let admin = Role::Admin.to_string();
+
+        // The admins are locked first, so concurrent demotions can't both see another
+        // remaining admin
+        sqlx::query!(
+            "
+            SELECT id FROM users
+            WHERE role = $1
+            FOR UPDATE
+            ",
+            admin,
+        )
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        let result = sqlx::query!(
+            "
+            SELECT (role = $1) AS \"is_admin!\",
+                (SELECT COUNT(*) FROM users WHERE role = $1) AS \"admins!\"
+            FROM users
+            WHERE id = $2
+            ",
+            admin,
+            id as crate::database::models::ids::UserId,
+        )
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        if let Some(result) = result {
+            check_not_last_admin(result.is_admin, result.admins)?;
+        }
+    }
+
+    let role = role.to_string();
+
+    sqlx::query!(
+        "
+        UPDATE users
+        SET role = $1
+        WHERE (id = $2)
+        ",
+        role,
+        id as crate::database::models::ids::UserId,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Extension {
     pub ext: String,
@@ -295,7 +464,7 @@ pub async fn user_icon_edit(
 
         if let Some(id) = id_option {
             if user.id != id.into() && !user.role.is_mod() {
-                return Err(ApiError::CustomAuthenticationError(
+                return Err(ApiError::Forbidden(
                     "You don't have permission to edit this user's icon.".to_string(),
                 ));
             }
@@ -315,12 +484,8 @@ pub async fn user_icon_edit(
             }
 
             if let Some(icon) = icon_url {
-                if icon.starts_with(&cdn_url) {
-                    let name = icon.split('/').next();
-
-                    if let Some(icon_path) = name {
-                        file_host.delete_file_version("", icon_path).await?;
-                    }
+                if let Some(icon_path) = get_cdn_path(&icon, &cdn_url) {
+                    file_host.delete_file_version("", icon_path).await?;
                 }
             }
 
@@ -394,7 +559,7 @@ pub async fn user_delete(
 
     if let Some(id) = id_option {
         if !user.role.is_mod() && user.id != id.into() {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You do not have permission to delete this user!".to_string(),
             ));
         }
@@ -433,7 +598,7 @@ pub async fn user_follows(
 
     if let Some(id) = id_option {
         if !user.role.is_mod() && user.id != id.into() {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You do not have permission to see the projects this user follows!".to_string(),
             ));
         }
@@ -480,7 +645,7 @@ pub async fn user_notifications(
 
     if let Some(id) = id_option {
         if !user.role.is_mod() && user.id != id.into() {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You do not have permission to see the notifications of this user!".to_string(),
             ));
         }
@@ -499,3 +664,375 @@ pub async fn user_notifications(
         Ok(HttpResponse::NotFound().body(""))
     }
 }
+
+#[derive(Deserialize)]
+pub struct NotificationReadQuery {
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct NotificationReadResult {
+    pub count: u64,
+}
+
+/// Marks all of a user's notifications as read. This is registered for both `POST` and
+/// `PATCH` in `users_config`, since a route macro can only take one method.
+pub async fn user_notifications_read(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    web::Query(query): web::Query<NotificationReadQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let id_option =
+        crate::database::models::User::get_id_from_username_or_id(info.into_inner().0, &**pool)
+            .await?;
+
+    if let Some(id) = id_option {
+        let result = read_notifications(&user, id, query.type_.as_deref(), |type_| {
+            crate::database::models::notification_item::Notification::read_many_user(
+                id, type_, &**pool,
+            )
+        })
+        .await?;
+
+        Ok(HttpResponse::Ok().json(result))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
+/// Marks the notifications of the user with `id` as read with `read`, optionally only those
+/// of a single type. Users can only read their own notifications, unless they're moderators.
+async fn read_notifications<'a, F, Fut>(
+    user: &crate::models::users::User,
+    id: crate::database::models::ids::UserId,
+    type_: Option<&'a str>,
+    read: F,
+) -> Result<NotificationReadResult, ApiError>
+where
+    F: FnOnce(Option<&'a str>) -> Fut,
+    Fut: std::future::Future<Output = Result<u64, sqlx::Error>>,
+{
+    if user.id != id.into() && !user.role.is_mod() {
+        return Err(ApiError::Forbidden(
+            "You do not have permission to edit the notifications of this user!".to_string(),
+        ));
+    }
+
+    if let Some(type_) = type_ {
+        if !NOTIFICATION_TYPES.contains(&type_) {
+            return Err(ApiError::InvalidInputError(format!(
+                "Invalid notification type: {}",
+                type_
+            )));
+        }
+    }
+
+    let count = read(type_).await?;
+
+    Ok(NotificationReadResult { count })
+}
+
+#[derive(Deserialize)]
+pub struct ActivityPagination {
+    pub count: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityType {
+    ProjectPublished,
+    VersionReleased,
+}
+
+/// A single entry in a user's public activity feed
+#[derive(Serialize)]
+pub struct Activity {
+    #[serde(rename = "type")]
+    pub type_: ActivityType,
+    pub project_id: ProjectId,
+    pub version_id: Option<VersionId>,
+    #[serde(with = "crate::util::time::rfc3339")]
+    pub date: chrono::DateTime<chrono::Utc>,
+}
+
+/// Gets the statuses of the projects which show up in activity feeds. Only projects which are
+/// searchable are public, so drafts and projects under review never show up.
+fn activity_statuses() -> Vec<String> {
+    [
+        ProjectStatus::Approved,
+        ProjectStatus::Rejected,
+        ProjectStatus::Draft,
+        ProjectStatus::Unlisted,
+        ProjectStatus::Processing,
+        ProjectStatus::Archived,
+    ]
+    .iter()
+    .filter(|x| x.is_searchable())
+    .map(|x| x.as_str().to_string())
+    .collect()
+}
+
+#[get("{id}/activity")]
+pub async fn user_activity(
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    page: web::Query<ActivityPagination>,
+    pagination: web::Data<PaginationConfig>,
+) -> Result<HttpResponse, ApiError> {
+    let id_option =
+        crate::database::models::User::get_id_from_username_or_id(info.into_inner().0, &**pool)
+            .await?;
+
+    if let Some(id) = id_option {
+        use futures::TryStreamExt;
+
+        let statuses = activity_statuses();
+        let activity = sqlx::query!(
+            "
+            SELECT activity.project_id, activity.version_id, activity.date FROM (
+                SELECT m.id project_id, NULL::bigint version_id, m.published date FROM mods m
+                INNER JOIN team_members tm ON tm.team_id = m.team_id AND tm.user_id = $1 AND tm.accepted = TRUE
+                INNER JOIN statuses s ON s.id = m.status AND s.status = ANY($2::varchar[])
+                UNION ALL
+                SELECT v.mod_id project_id, v.id version_id, v.date_published date FROM versions v
+                INNER JOIN mods m ON m.id = v.mod_id
+                INNER JOIN team_members tm ON tm.team_id = m.team_id AND tm.user_id = $1 AND tm.accepted = TRUE
+                INNER JOIN statuses s ON s.id = m.status AND s.status = ANY($2::varchar[])
+            ) AS activity
+            ORDER BY activity.date DESC
+            LIMIT $3 OFFSET $4
+            ",
+            id as crate::database::models::ids::UserId,
+            &statuses,
+            pagination.limit(page.count) as i64,
+            i64::try_from(page.offset).unwrap_or(i64::MAX),
+        )
+        .fetch_many(&**pool)
+        .try_filter_map(|e| async {
+            Ok(e.right().and_then(|m| {
+                Some(Activity {
+                    type_: if m.version_id.is_some() {
+                        ActivityType::VersionReleased
+                    } else {
+                        ActivityType::ProjectPublished
+                    },
+                    project_id: crate::database::models::ids::ProjectId(m.project_id?).into(),
+                    version_id: m
+                        .version_id
+                        .map(|x| crate::database::models::ids::VersionId(x).into()),
+                    date: m.date?,
+                })
+            }))
+        })
+        .try_collect::<Vec<Activity>>()
+        .await?;
+
+        Ok(HttpResponse::Ok().json(activity))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_differ_between_teams() {
+        let upload = Permissions::UPLOAD_VERSION | Permissions::DELETE_VERSION;
+        let memberships = vec![
+            (1, upload.bits() as i64),
+            (2, Permissions::ALL.bits() as i64),
+            (3, 0),
+        ];
+
+        let permissions = project_permissions(memberships, false);
+
+        assert_eq!(permissions.len(), 3);
+        assert_eq!(permissions[&ProjectId(1)], upload);
+        assert_eq!(permissions[&ProjectId(2)], Permissions::ALL);
+        assert_eq!(permissions[&ProjectId(3)], Permissions::empty());
+    }
+
+    #[test]
+    fn moderators_have_all_permissions() {
+        let memberships = vec![(1, Permissions::UPLOAD_VERSION.bits() as i64), (2, 0)];
+
+        let permissions = project_permissions(memberships, true);
+
+        assert_eq!(permissions[&ProjectId(1)], Permissions::ALL);
+        assert_eq!(permissions[&ProjectId(2)], Permissions::ALL);
+    }
+
+    fn user(role: Role) -> crate::models::users::User {
+        crate::models::users::User {
+            id: UserId(1),
+            github_id: None,
+            username: "user".to_string(),
+            name: None,
+            email: None,
+            avatar_url: None,
+            bio: None,
+            created: chrono::Utc::now(),
+            role,
+        }
+    }
+
+    #[test]
+    fn only_admins_can_edit_roles() {
+        assert!(check_can_edit_roles(&user(Role::Admin)).is_ok());
+        assert!(matches!(
+            check_can_edit_roles(&user(Role::Developer)),
+            Err(ApiError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn moderators_cannot_edit_roles() {
+        // Both the role endpoint and `user_edit` use this check, so moderators can't
+        // promote themselves through either of them
+        assert!(matches!(
+            check_can_edit_roles(&user(Role::Moderator)),
+            Err(ApiError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_roles_are_rejected() {
+        assert!(matches!(parse_role("admin"), Ok(Role::Admin)));
+        assert!(matches!(parse_role("moderator"), Ok(Role::Moderator)));
+        assert!(matches!(parse_role("developer"), Ok(Role::Developer)));
+        assert!(matches!(
+            parse_role("owner"),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert!(matches!(
+            parse_role("Admin"),
+            Err(ApiError::InvalidInputError(_))
+        ));
+    }
+
+    #[test]
+    fn the_last_admin_cannot_be_demoted() {
+        assert!(matches!(
+            check_not_last_admin(true, 1),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert!(check_not_last_admin(true, 2).is_ok());
+        assert!(check_not_last_admin(false, 1).is_ok());
+    }
+
+    #[test]
+    fn drafts_never_show_up_in_activity() {
+        let statuses = activity_statuses();
+
+        assert_eq!(statuses, vec!["approved".to_string()]);
+        assert!(!statuses.contains(&ProjectStatus::Draft.as_str().to_string()));
+        assert!(!statuses.contains(&ProjectStatus::Processing.as_str().to_string()));
+    }
+
+    /// Reads the unread notifications of `unread` matching the type like
+    /// `Notification::read_many_user`, returning how many were read
+    fn read_unread<'a>(
+        unread: &'a std::cell::RefCell<Vec<&'static str>>,
+    ) -> impl FnOnce(Option<&str>) -> futures::future::Ready<Result<u64, sqlx::Error>> + 'a {
+        move |type_: Option<&str>| {
+            let mut unread = unread.borrow_mut();
+            let before = unread.len();
+            unread.retain(|x| type_.map(|type_| type_ != *x).unwrap_or(false));
+
+            futures::future::ready(Ok((before - unread.len()) as u64))
+        }
+    }
+
+    fn unread() -> std::cell::RefCell<Vec<&'static str>> {
+        std::cell::RefCell::new(vec![
+            "project_update",
+            "team_invite",
+            "project_update",
+            "status_change",
+        ])
+    }
+
+    #[test]
+    fn every_unread_notification_is_read_and_counted() {
+        let unread = unread();
+
+        let result = futures::executor::block_on(read_notifications(
+            &user(Role::Developer),
+            crate::database::models::ids::UserId(1),
+            None,
+            read_unread(&unread),
+        ))
+        .unwrap();
+
+        assert_eq!(result.count, 4);
+        assert!(unread.borrow().is_empty());
+    }
+
+    #[test]
+    fn notifications_of_one_type_are_counted() {
+        let unread = unread();
+
+        let result = futures::executor::block_on(read_notifications(
+            &user(Role::Developer),
+            crate::database::models::ids::UserId(1),
+            Some("project_update"),
+            read_unread(&unread),
+        ))
+        .unwrap();
+
+        assert_eq!(result.count, 2);
+        assert_eq!(*unread.borrow(), vec!["team_invite", "status_change"]);
+    }
+
+    #[test]
+    fn moderators_can_read_other_users_notifications() {
+        let unread = unread();
+
+        let result = futures::executor::block_on(read_notifications(
+            &user(Role::Moderator),
+            crate::database::models::ids::UserId(2),
+            None,
+            read_unread(&unread),
+        ))
+        .unwrap();
+
+        assert_eq!(result.count, 4);
+    }
+
+    #[test]
+    fn users_cannot_read_other_users_notifications() {
+        let unread = unread();
+
+        let result = futures::executor::block_on(read_notifications(
+            &user(Role::Developer),
+            crate::database::models::ids::UserId(2),
+            None,
+            read_unread(&unread),
+        ));
+
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+        assert_eq!(unread.borrow().len(), 4);
+    }
+
+    #[test]
+    fn unknown_notification_types_are_rejected() {
+        let unread = unread();
+
+        let result = futures::executor::block_on(read_notifications(
+            &user(Role::Developer),
+            crate::database::models::ids::UserId(1),
+            Some("unknown"),
+            read_unread(&unread),
+        ));
+
+        assert!(matches!(result, Err(ApiError::InvalidInputError(_))));
+        assert_eq!(unread.borrow().len(), 4);
+    }
+}
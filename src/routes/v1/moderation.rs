@@ -3,6 +3,7 @@ use crate::models::projects::{Project, ProjectStatus};
 use crate::routes::moderation::ResultCount;
 use crate::routes::ApiError;
 use crate::util::auth::check_is_moderator_from_headers;
+use crate::util::pagination::PaginationConfig;
 use actix_web::web;
 use actix_web::{get, HttpRequest, HttpResponse};
 use sqlx::PgPool;
@@ -12,6 +13,7 @@ pub async fn get_mods(
     req: HttpRequest,
     pool: web::Data<PgPool>,
     count: web::Query<ResultCount>,
+    pagination: web::Data<PaginationConfig>,
 ) -> Result<HttpResponse, ApiError> {
     check_is_moderator_from_headers(req.headers(), &**pool).await?;
 
@@ -27,7 +29,7 @@ pub async fn get_mods(
         LIMIT $2;
         ",
         ProjectStatus::Processing.as_str(),
-        count.count as i64
+        pagination.limit_or(count.count, 100) as i64
     )
     .fetch_many(&**pool)
     .try_filter_map(|e| async { Ok(e.right().map(|m| database::models::ProjectId(m.id))) })
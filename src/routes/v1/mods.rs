@@ -1,10 +1,13 @@
 use crate::file_hosting::FileHost;
 use crate::models::projects::SearchRequest;
 use crate::routes::project_creation::{project_create_inner, undo_uploads, CreateError};
-use crate::routes::projects::{convert_project, ProjectIds};
+use crate::routes::projects::{can_see_project, convert_project, ProjectIds};
 use crate::routes::ApiError;
 use crate::search::{search_for_project, SearchConfig, SearchError};
 use crate::util::auth::get_user_from_headers;
+use crate::util::pagination::PaginationConfig;
+use crate::util::validate::ProjectCheckConfig;
+use crate::util::webhook::{send_moderation_webhook, WebhookConfig};
 use crate::{database, models};
 use actix_multipart::Multipart;
 use actix_web::web;
@@ -49,8 +52,9 @@ pub struct SearchResults {
 pub async fn mod_search(
     web::Query(info): web::Query<SearchRequest>,
     config: web::Data<SearchConfig>,
+    pagination: web::Data<PaginationConfig>,
 ) -> Result<HttpResponse, SearchError> {
-    let results = search_for_project(&info, &**config).await?;
+    let results = search_for_project(&info, &**config, &**pagination).await?;
     Ok(HttpResponse::Ok().json(SearchResults {
         hits: results
             .hits
@@ -98,46 +102,69 @@ pub async fn mods_get(
 
     let user_option = get_user_from_headers(req.headers(), &**pool).await.ok();
 
-    let mut projects = Vec::new();
-
-    for project_data in projects_data {
-        let mut authorized = !project_data.status.is_hidden();
-
-        if let Some(user) = &user_option {
-            if !authorized {
-                if user.role.is_mod() {
-                    authorized = true;
-                } else {
-                    let user_id: database::models::ids::UserId = user.id.into();
-
-                    let project_exists = sqlx::query!(
-                            "SELECT EXISTS(SELECT 1 FROM team_members WHERE team_id = $1 AND user_id = $2)",
-                            project_data.inner.team_id as database::models::ids::TeamId,
-                            user_id as database::models::ids::UserId,
-                        )
-                        .fetch_one(&**pool)
-                        .await?
-                        .exists;
-
-                    authorized = project_exists.unwrap_or(false);
-                }
+    // The teams of the hidden projects the user is a member of are found with a single
+    // query, rather than checking each project separately
+    let member_teams: Vec<i64> = match &user_option {
+        Some(user) if !user.role.is_mod() => {
+            let user_id: database::models::ids::UserId = user.id.into();
+            let hidden_teams: Vec<i64> = projects_data
+                .iter()
+                .filter(|x| x.status.is_hidden())
+                .map(|x| x.inner.team_id.0)
+                .collect();
+
+            if hidden_teams.is_empty() {
+                Vec::new()
+            } else {
+                sqlx::query!(
+                    "
+                    SELECT team_id FROM team_members
+                    WHERE user_id = $1 AND team_id = ANY($2)
+                    ",
+                    user_id as database::models::ids::UserId,
+                    &hidden_teams,
+                )
+                .fetch_all(&**pool)
+                .await?
+                .into_iter()
+                .map(|x| x.team_id)
+                .collect()
             }
         }
+        _ => Vec::new(),
+    };
 
-        if authorized {
-            projects.push(convert_project(project_data));
-        }
-    }
+    let is_mod = user_option
+        .as_ref()
+        .map(|x| x.role.is_mod())
+        .unwrap_or(false);
+
+    let projects = visible_projects(projects_data, is_mod, &member_teams);
 
     Ok(HttpResponse::Ok().json(projects))
 }
 
+/// Converts the projects a user can see, the same way `project_get` decides for one project
+fn visible_projects(
+    projects_data: Vec<database::models::project_item::QueryProject>,
+    is_mod: bool,
+    member_teams: &[i64],
+) -> Vec<models::projects::Project> {
+    projects_data
+        .into_iter()
+        .filter(|x| can_see_project(&x.status, is_mod, member_teams.contains(&x.inner.team_id.0)))
+        .map(convert_project)
+        .collect()
+}
+
 #[post("mod")]
 pub async fn mod_create(
     req: HttpRequest,
     payload: Multipart,
     client: Data<PgPool>,
     file_host: Data<Arc<dyn FileHost + Send + Sync>>,
+    check_config: Data<ProjectCheckConfig>,
+    webhook_config: Data<WebhookConfig>,
 ) -> Result<HttpResponse, CreateError> {
     let mut transaction = client.begin().await?;
     let mut uploaded_files = Vec::new();
@@ -148,22 +175,87 @@ pub async fn mod_create(
         &mut transaction,
         &***file_host,
         &mut uploaded_files,
+        &check_config,
     )
     .await;
 
-    if result.is_err() {
-        let undo_result = undo_uploads(&***file_host, &uploaded_files).await;
-        let rollback_result = transaction.rollback().await;
+    match result {
+        Ok(project) => {
+            transaction.commit().await?;
+
+            // Only sent once the project has been created, so moderators aren't notified
+            // about projects which were rolled back
+            send_moderation_webhook(&webhook_config, project.clone());
+
+            Ok(HttpResponse::Ok().json(project))
+        }
+        Err(err) => {
+            let undo_result = undo_uploads(&***file_host, &uploaded_files).await;
+            let rollback_result = transaction.rollback().await;
+
+            if let Err(e) = undo_result {
+                return Err(e);
+            }
+            if let Err(e) = rollback_result {
+                return Err(e.into());
+            }
 
-        if let Err(e) = undo_result {
-            return Err(e);
+            Err(err)
         }
-        if let Err(e) = rollback_result {
-            return Err(e.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::project_item::QueryProject;
+    use crate::models::projects::ProjectStatus;
+
+    fn project(status: ProjectStatus) -> QueryProject {
+        let mut project = QueryProject::example(1000);
+        project.status = status;
+        project
+    }
+
+    #[test]
+    fn getting_one_mod_matches_getting_it_in_a_batch() {
+        let statuses = vec![
+            ProjectStatus::Approved,
+            ProjectStatus::Unlisted,
+            ProjectStatus::Draft,
+            ProjectStatus::Processing,
+            ProjectStatus::Rejected,
+        ];
+        // Anonymous users, team members and moderators
+        let viewers = vec![(false, false), (false, true), (true, false)];
+
+        for status in &statuses {
+            for (is_mod, is_member) in &viewers {
+                let member_teams = if *is_member { vec![2000] } else { vec![] };
+
+                let batch = visible_projects(vec![project(*status)], *is_mod, &member_teams);
+                let single = Some(project(*status))
+                    .filter(|x| can_see_project(&x.status, *is_mod, *is_member))
+                    .map(convert_project);
+
+                assert_eq!(
+                    serde_json::to_value(&batch).unwrap(),
+                    serde_json::to_value(single.into_iter().collect::<Vec<_>>()).unwrap()
+                );
+            }
         }
-    } else {
-        transaction.commit().await?;
     }
 
-    result
+    #[test]
+    fn hidden_mods_are_left_out_of_batches() {
+        assert!(visible_projects(vec![project(ProjectStatus::Draft)], false, &[]).is_empty());
+        assert_eq!(
+            visible_projects(vec![project(ProjectStatus::Draft)], false, &[2000]).len(),
+            1
+        );
+        assert_eq!(
+            visible_projects(vec![project(ProjectStatus::Draft)], true, &[]).len(),
+            1
+        );
+    }
 }
@@ -3,6 +3,7 @@ use crate::models::projects::{ProjectId, VersionId};
 use crate::models::users::UserId;
 use crate::routes::ApiError;
 use crate::util::auth::{check_is_moderator_from_headers, get_user_from_headers};
+use crate::util::pagination::PaginationConfig;
 use actix_web::web;
 use actix_web::{get, post, HttpRequest, HttpResponse};
 use chrono::{DateTime, Utc};
@@ -18,6 +19,7 @@ pub struct Report {
     pub item_type: ItemType,
     pub reporter: UserId,
     pub body: String,
+    #[serde(with = "crate::util::time::rfc3339")]
     pub created: DateTime<Utc>,
 }
 
@@ -126,12 +128,7 @@ pub async fn report_create(
 
 #[derive(Deserialize)]
 pub struct ResultCount {
-    #[serde(default = "default_count")]
-    count: i16,
-}
-
-fn default_count() -> i16 {
-    100
+    count: Option<usize>,
 }
 
 #[get("report")]
@@ -139,6 +136,7 @@ pub async fn reports(
     req: HttpRequest,
     pool: web::Data<PgPool>,
     count: web::Query<ResultCount>,
+    pagination: web::Data<PaginationConfig>,
 ) -> Result<HttpResponse, ApiError> {
     check_is_moderator_from_headers(req.headers(), &**pool).await?;
 
@@ -150,7 +148,7 @@ pub async fn reports(
         ORDER BY created ASC
         LIMIT $1;
         ",
-        count.count as i64
+        pagination.limit_or(count.count, 100) as i64
     )
     .fetch_many(&**pool)
     .try_filter_map(|e| async {
@@ -2,9 +2,11 @@ use crate::file_hosting::FileHost;
 use crate::models::ids::{ProjectId, UserId, VersionId};
 use crate::models::projects::{Dependency, GameVersion, Loader, Version, VersionFile, VersionType};
 use crate::models::teams::Permissions;
+use crate::routes::version_file::{check_download_limit, parse_hash, DownloadTracking};
 use crate::routes::versions::{convert_version, VersionIds, VersionListFilters};
 use crate::routes::ApiError;
 use crate::util::auth::get_user_from_headers;
+use crate::util::ratelimit::DownloadLimiter;
 use crate::{database, models, Pepper};
 use actix_web::{delete, get, web, HttpRequest, HttpResponse};
 use chrono::{DateTime, Utc};
@@ -24,6 +26,7 @@ pub struct LegacyVersion {
     pub version_number: String,
     pub changelog: String,
     pub changelog_url: Option<String>,
+    #[serde(with = "crate::util::time::rfc3339")]
     pub date_published: DateTime<Utc>,
     pub downloads: u32,
     pub version_type: VersionType,
@@ -76,6 +79,9 @@ pub async fn version_list(
                 .loaders
                 .as_ref()
                 .map(|x| serde_json::from_str(x).unwrap_or_default()),
+            None,
+            None,
+            None,
             &**pool,
         )
         .await?;
@@ -192,7 +198,7 @@ pub async fn get_version_from_hash(
     pool: web::Data<PgPool>,
     algorithm: web::Query<Algorithm>,
 ) -> Result<HttpResponse, ApiError> {
-    let hash = info.into_inner().0.to_lowercase();
+    let hash = parse_hash(&info.into_inner().0)?;
 
     let result = sqlx::query!(
         "
@@ -200,7 +206,7 @@ pub async fn get_version_from_hash(
         INNER JOIN files f ON h.file_id = f.id
         WHERE h.algorithm = $2 AND h.hash = $1
         ",
-        hash.as_bytes(),
+        hash.as_slice(),
         algorithm.algorithm
     )
     .fetch_optional(&**pool)
@@ -237,8 +243,11 @@ pub async fn download_version(
     pool: web::Data<PgPool>,
     algorithm: web::Query<Algorithm>,
     pepper: web::Data<Pepper>,
+    limiter: web::Data<Arc<DownloadLimiter>>,
 ) -> Result<HttpResponse, ApiError> {
-    let hash = info.into_inner().0;
+    check_download_limit(&req, &limiter, &pepper, 1)?;
+
+    let hash = parse_hash(&info.into_inner().0)?;
 
     let result = sqlx::query!(
         "
@@ -247,7 +256,7 @@ pub async fn download_version(
         INNER JOIN versions v ON v.id = f.version_id
         WHERE h.algorithm = $2 AND h.hash = $1
         ",
-        hash.as_bytes(),
+        hash.as_slice(),
         algorithm.algorithm
     )
     .fetch_optional(&**pool)
@@ -255,14 +264,18 @@ pub async fn download_version(
     .map_err(|e| ApiError::DatabaseError(e.into()))?;
 
     if let Some(id) = result {
+        let tracking = DownloadTracking::from_env();
         let real_ip = req.connection_info();
-        let ip_option = real_ip.borrow().remote_addr();
+        let ip_option = real_ip
+            .borrow()
+            .remote_addr()
+            .filter(|_| tracking.records_downloads());
 
         if let Some(ip) = ip_option {
             let hash = sha1::Sha1::from(format!("{}{}", ip, pepper.pepper)).hexdigest();
 
-            let download_exists = sqlx::query!(
-                "SELECT EXISTS(SELECT 1 FROM downloads WHERE version_id = $1 AND date > (CURRENT_DATE - INTERVAL '30 minutes ago') AND identifier = $2)",
+            let download_exists = tracking.skips_recent_downloads() && sqlx::query!(
+                "SELECT EXISTS(SELECT 1 FROM downloads WHERE version_id = $1 AND date > (NOW() - INTERVAL '30 minutes') AND identifier = $2)",
                 id.version_id,
                 hash,
             )
@@ -332,7 +345,7 @@ pub async fn delete_file(
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(req.headers(), &**pool).await?;
 
-    let hash = info.into_inner().0.to_lowercase();
+    let hash = parse_hash(&info.into_inner().0)?;
 
     let result = sqlx::query!(
         "
@@ -341,7 +354,7 @@ pub async fn delete_file(
         INNER JOIN versions v ON v.id = f.version_id
         WHERE h.algorithm = $2 AND h.hash = $1
         ",
-        hash.as_bytes(),
+        hash.as_slice(),
         algorithm.algorithm
     )
         .fetch_optional(&**pool)
@@ -1,14 +1,14 @@
 use crate::database::models;
 use crate::database::models::notification_item::NotificationBuilder;
 use crate::database::models::version_item::{VersionBuilder, VersionFileBuilder};
-use crate::file_hosting::FileHost;
+use crate::file_hosting::{FileHost, FileHostingError};
 use crate::models::projects::{
-    Dependency, GameVersion, Loader, ProjectId, Version, VersionFile, VersionId, VersionType,
+    Dependency, FileType, GameVersion, Loader, ProjectId, Version, VersionFile, VersionId,
+    VersionType,
 };
 use crate::models::teams::Permissions;
 use crate::routes::project_creation::{CreateError, UploadedFile};
 use crate::util::auth::get_user_from_headers;
-use crate::util::validate::validation_errors_to_string;
 use crate::validate::{validate_file, ValidationResult};
 use actix_multipart::{Field, Multipart};
 use actix_web::web::Data;
@@ -16,6 +16,8 @@ use actix_web::{post, HttpRequest, HttpResponse};
 use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgPool;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use validator::Validate;
 
 #[derive(Serialize, Deserialize, Validate, Clone)]
@@ -39,11 +41,30 @@ pub struct InitialVersionData {
     pub release_channel: VersionType,
     pub loaders: Vec<Loader>,
     pub featured: bool,
+    /// Labels for the uploaded files, keyed by the name of their multipart field
+    #[serde(default)]
+    pub file_types: HashMap<String, FileType>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 struct InitialFileData {
     // TODO: hashes?
+    /// Labels for the uploaded files, keyed by the name of their multipart field
+    #[serde(default)]
+    file_types: HashMap<String, FileType>,
+}
+
+/// A newly created version, along with any non-fatal problems found with its files
+#[derive(Serialize)]
+struct VersionCreateResponse {
+    #[serde(flatten)]
+    version: Version,
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct FileUploadResponse {
+    warnings: Vec<String>,
 }
 
 // under `/api/v1/version`
@@ -92,9 +113,11 @@ async fn version_create_inner(
     uploaded_files: &mut Vec<UploadedFile>,
 ) -> Result<HttpResponse, CreateError> {
     let cdn_url = dotenv::var("CDN_URL")?;
+    let duplicate_files_conflict = duplicate_files_conflict();
 
     let mut initial_version_data = None;
     let mut version_builder = None;
+    let mut warnings = Vec::new();
 
     let all_game_versions = models::categories::GameVersion::list(&mut *transaction).await?;
     let all_loaders = models::categories::Loader::list(&mut *transaction).await?;
@@ -125,9 +148,9 @@ async fn version_create_inner(
                 ));
             }
 
-            version_create_data.validate().map_err(|err| {
-                CreateError::ValidationError(validation_errors_to_string(err, None))
-            })?;
+            version_create_data
+                .validate()
+                .map_err(CreateError::ValidationError)?;
 
             let project_id: models::ProjectId = version_create_data.project_id.unwrap().into();
 
@@ -170,7 +193,7 @@ async fn version_create_inner(
             )
             .await?
             .ok_or_else(|| {
-                CreateError::CustomAuthenticationError(
+                CreateError::Forbidden(
                     "You don't have permission to upload this version!".to_string(),
                 )
             })?;
@@ -179,7 +202,7 @@ async fn version_create_inner(
                 .permissions
                 .contains(Permissions::UPLOAD_VERSION)
             {
-                return Err(CreateError::CustomAuthenticationError(
+                return Err(CreateError::Forbidden(
                     "You don't have permission to upload this version!".to_string(),
                 ));
             }
@@ -241,6 +264,18 @@ async fn version_create_inner(
                 })
                 .collect::<Vec<_>>();
 
+            if let Some(missing) = models::version_item::DependencyBuilder::find_missing_version(
+                &dependencies,
+                &mut *transaction,
+            )
+            .await?
+            {
+                return Err(CreateError::InvalidInput(format!(
+                    "The dependency version {} does not exist",
+                    VersionId::from(missing)
+                )));
+            }
+
             version_builder = Some(VersionBuilder {
                 version_id: version_id.into(),
                 project_id,
@@ -296,8 +331,22 @@ async fn version_create_inner(
             version_data.game_versions,
             &all_game_versions,
             false,
+            version_data.file_types.get(name).copied(),
+            max_file_size(),
         )
         .await?;
+
+        if let Some(file) = version.files.last() {
+            let versions_with_file =
+                versions_with_file(file, version.project_id, &mut *transaction).await?;
+            check_duplicate_file(
+                file,
+                version.version_id,
+                &versions_with_file,
+                duplicate_files_conflict,
+                &mut warnings,
+            )?;
+        }
     }
 
     let version_data = initial_version_data
@@ -361,29 +410,7 @@ async fn version_create_inner(
         date_published: chrono::Utc::now(),
         downloads: 0,
         version_type: version_data.release_channel,
-        files: builder
-            .files
-            .iter()
-            .map(|file| VersionFile {
-                hashes: file
-                    .hashes
-                    .iter()
-                    .map(|hash| {
-                        (
-                            hash.algorithm.clone(),
-                            // This is a hack since the hashes are currently stored as ASCII
-                            // in the database, but represented here as a Vec<u8>.  At some
-                            // point we need to change the hash to be the real bytes  in the
-                            // database and add more processing here.
-                            String::from_utf8(hash.hash.clone()).unwrap(),
-                        )
-                    })
-                    .collect(),
-                url: file.url.clone(),
-                filename: file.filename.clone(),
-                primary: file.primary,
-            })
-            .collect::<Vec<_>>(),
+        files: builder.files.iter().map(convert_file).collect::<Vec<_>>(),
         dependencies: version_data.dependencies,
         game_versions: version_data.game_versions,
         loaders: version_data.loaders,
@@ -391,7 +418,10 @@ async fn version_create_inner(
 
     builder.insert(transaction).await?;
 
-    Ok(HttpResponse::Ok().json(response))
+    Ok(HttpResponse::Ok().json(VersionCreateResponse {
+        version: response,
+        warnings,
+    }))
 }
 
 // TODO: file deletion, listing, etc
@@ -438,6 +468,32 @@ pub async fn upload_file_to_version(
     result
 }
 
+/// Checks that a team member with the given permissions, if any, can add files to versions
+fn check_can_upload_files(permissions: Option<Permissions>) -> Result<(), CreateError> {
+    match permissions {
+        Some(permissions) if permissions.contains(Permissions::UPLOAD_VERSION) => Ok(()),
+        _ => Err(CreateError::Forbidden(
+            "You don't have permission to upload files to this version!".to_string(),
+        )),
+    }
+}
+
+/// Checks that none of the files of a version, including those uploaded by the same request,
+/// are named `file_name`
+fn check_unique_filename<'a>(
+    mut existing: impl Iterator<Item = &'a str>,
+    file_name: &str,
+) -> Result<(), CreateError> {
+    if existing.any(|x| x == file_name) {
+        return Err(CreateError::InvalidInput(format!(
+            "This version already has a file named {}",
+            file_name
+        )));
+    }
+
+    Ok(())
+}
+
 async fn upload_file_to_version_inner(
     req: HttpRequest,
     mut payload: Multipart,
@@ -447,9 +503,11 @@ async fn upload_file_to_version_inner(
     version_id: models::VersionId,
 ) -> Result<HttpResponse, CreateError> {
     let cdn_url = dotenv::var("CDN_URL")?;
+    let duplicate_files_conflict = duplicate_files_conflict();
 
     let mut initial_file_data: Option<InitialFileData> = None;
     let mut file_builders: Vec<VersionFileBuilder> = Vec::new();
+    let mut warnings = Vec::new();
 
     let user = get_user_from_headers(req.headers(), &mut *transaction).await?;
 
@@ -466,21 +524,9 @@ async fn upload_file_to_version_inner(
 
     let team_member =
         models::TeamMember::get_from_user_id_version(version_id, user.id.into(), &mut *transaction)
-            .await?
-            .ok_or_else(|| {
-                CreateError::CustomAuthenticationError(
-                    "You don't have permission to upload files to this version!".to_string(),
-                )
-            })?;
+            .await?;
 
-    if !team_member
-        .permissions
-        .contains(Permissions::UPLOAD_VERSION)
-    {
-        return Err(CreateError::CustomAuthenticationError(
-            "You don't have permission to upload files to this version!".to_string(),
-        ));
-    }
+    check_can_upload_files(team_member.map(|x| x.permissions))?;
 
     let project_id = ProjectId(version.project_id.0 as u64);
     let version_number = version.version_number;
@@ -520,10 +566,22 @@ async fn upload_file_to_version_inner(
             continue;
         }
 
-        let _file_data = initial_file_data.as_ref().ok_or_else(|| {
+        let file_data = initial_file_data.as_ref().ok_or_else(|| {
             CreateError::InvalidInput(String::from("`data` field must come before file fields"))
         })?;
 
+        // Files are stored by name under the version, so a file with the same name as an
+        // existing one would overwrite it on the file host
+        let (file_name, _) = get_name_ext(&content_disposition)?;
+        check_unique_filename(
+            version
+                .files
+                .iter()
+                .map(|x| x.filename.as_str())
+                .chain(file_builders.iter().map(|x| x.filename.as_str())),
+            file_name,
+        )?;
+
         upload_file(
             &mut field,
             file_host,
@@ -543,8 +601,22 @@ async fn upload_file_to_version_inner(
                 .collect(),
             &all_game_versions,
             true,
+            file_data.file_types.get(name).copied(),
+            max_file_size(),
         )
         .await?;
+
+        if let Some(file) = file_builders.last() {
+            let versions_with_file =
+                versions_with_file(file, version.project_id, &mut *transaction).await?;
+            check_duplicate_file(
+                file,
+                version_id,
+                &versions_with_file,
+                duplicate_files_conflict,
+                &mut warnings,
+            )?;
+        }
     }
 
     if file_builders.is_empty() {
@@ -557,14 +629,143 @@ async fn upload_file_to_version_inner(
         }
     }
 
-    Ok(HttpResponse::Ok().into())
+    Ok(HttpResponse::Ok().json(FileUploadResponse { warnings }))
+}
+
+// Whether uploading a file identical to one in another version of the project is
+// rejected (`DUPLICATE_FILES_CONFLICT`) instead of only producing a warning.
+fn duplicate_files_conflict() -> bool {
+    dotenv::var("DUPLICATE_FILES_CONFLICT")
+        .ok()
+        .and_then(|x| x.parse::<bool>().ok())
+        .unwrap_or(false)
+}
+
+// Gets the versions of the project which already have a file with the same hash.
+async fn versions_with_file(
+    file: &VersionFileBuilder,
+    project_id: models::ProjectId,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Vec<models::VersionId>, CreateError> {
+    let hash = match file.hashes.iter().find(|x| x.algorithm == "sha1") {
+        Some(hash) => hash,
+        None => return Ok(Vec::new()),
+    };
+
+    let versions = sqlx::query!(
+        "
+        SELECT v.id FROM hashes h
+        INNER JOIN files f ON h.file_id = f.id
+        INNER JOIN versions v ON v.id = f.version_id
+        WHERE h.algorithm = $1 AND h.hash = $2 AND v.mod_id = $3
+        ",
+        hash.algorithm,
+        hash.hash,
+        project_id as models::ProjectId,
+    )
+    .fetch_all(&mut *transaction)
+    .await?
+    .into_iter()
+    .map(|x| models::VersionId(x.id))
+    .collect();
+
+    Ok(versions)
+}
+
+// Checks whether a file is identical to one in a version of the project other than
+// `version_id`. By default this only adds a warning, but it is a hard error when
+// `conflict` is set.
+fn check_duplicate_file(
+    file: &VersionFileBuilder,
+    version_id: models::VersionId,
+    versions_with_file: &[models::VersionId],
+    conflict: bool,
+    warnings: &mut Vec<String>,
+) -> Result<(), CreateError> {
+    if versions_with_file.iter().all(|x| *x == version_id) {
+        return Ok(());
+    }
+
+    let message = format!(
+        "The file {} is identical to a file in another version of this project",
+        file.filename
+    );
+
+    if conflict {
+        return Err(CreateError::DuplicateFile(message));
+    }
+
+    warnings.push(message);
+
+    Ok(())
+}
+
+/// Gets the maximum size of a version file in bytes, set with `MAX_FILE_SIZE` and
+/// defaulting to 100MiB
+// TODO: override file size cap for authorized users or projects
+pub fn max_file_size() -> usize {
+    dotenv::var("MAX_FILE_SIZE")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(100 * (1 << 20))
+}
+
+/// Converts an uploaded file to how it's returned once its version is created
+fn convert_file(file: &VersionFileBuilder) -> VersionFile {
+    VersionFile {
+        hashes: file
+            .hashes
+            .iter()
+            .map(|hash| (hash.algorithm.clone(), hex::encode(&hash.hash)))
+            .collect(),
+        url: file.url.clone(),
+        filename: file.filename.clone(),
+        primary: file.primary,
+        size: file.size.and_then(|x| u64::try_from(x).ok()),
+        file_type: file.file_type.as_deref().and_then(FileType::from_str),
+    }
+}
+
+/// Reads a file from a multipart field. The size is checked while the file is read, so that
+/// files larger than `file_size_cap` bytes are rejected before they are fully buffered in memory
+async fn read_capped<S>(field: &mut S, file_size_cap: usize) -> Result<Vec<u8>, CreateError>
+where
+    S: futures::Stream<Item = Result<actix_web::web::Bytes, actix_multipart::MultipartError>>
+        + Unpin,
+{
+    let mut data = Vec::new();
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk.map_err(CreateError::MultipartError)?;
+
+        if data.len() + chunk.len() > file_size_cap {
+            return Err(CreateError::InvalidInput(format!(
+                "Project file exceeds the maximum of {}. Contact a moderator or admin to request permission to upload larger files.",
+                format_size(file_size_cap)
+            )));
+        }
+
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok(data)
+}
+
+/// Formats a size in bytes for error messages, in the largest unit it has at least one of
+fn format_size(bytes: usize) -> String {
+    if bytes >= 1 << 20 {
+        format!("{}MiB", bytes >> 20)
+    } else if bytes >= 1 << 10 {
+        format!("{}KiB", bytes >> 10)
+    } else {
+        format!("{} bytes", bytes)
+    }
 }
 
 // This function is used for adding a file to a version, uploading the initial
 // files for a version, and for uploading the initial version files for a project
 #[allow(clippy::too_many_arguments)]
-pub async fn upload_file(
-    field: &mut Field,
+pub async fn upload_file<S>(
+    field: &mut S,
     file_host: &dyn FileHost,
     uploaded_files: &mut Vec<UploadedFile>,
     version_files: &mut Vec<models::version_item::VersionFileBuilder>,
@@ -577,26 +778,21 @@ pub async fn upload_file(
     game_versions: Vec<GameVersion>,
     all_game_versions: &[models::categories::GameVersion],
     ignore_primary: bool,
-) -> Result<(), CreateError> {
+    file_type: Option<FileType>,
+    file_size_cap: usize,
+) -> Result<(), CreateError>
+where
+    S: futures::Stream<Item = Result<actix_web::web::Bytes, actix_multipart::MultipartError>>
+        + Unpin,
+{
     let (file_name, file_extension) = get_name_ext(content_disposition)?;
 
     let content_type = crate::util::ext::project_file_type(file_extension)
         .ok_or_else(|| CreateError::InvalidFileType(file_extension.to_string()))?;
 
-    let mut data = Vec::new();
-    while let Some(chunk) = field.next().await {
-        data.extend_from_slice(&chunk.map_err(CreateError::MultipartError)?);
-    }
-
-    // Project file size limit of 100MiB
-    const FILE_SIZE_CAP: usize = 100 * (1 << 20);
+    let data = read_capped(field, file_size_cap).await?;
 
-    // TODO: override file size cap for authorized users or projects
-    if data.len() >= FILE_SIZE_CAP {
-        return Err(CreateError::InvalidInput(
-            String::from("Project file exceeds the maximum of 100MiB. Contact a moderator or admin to request permission to upload larger files.")
-        ));
-    }
+    let file_size = data.len();
 
     let validation_result = validate_file(
         data.as_slice(),
@@ -630,20 +826,21 @@ pub async fn upload_file(
         hashes: vec![
             models::version_item::HashBuilder {
                 algorithm: "sha1".to_string(),
-                // This is an invalid cast - the database expects the hash's
-                // bytes, but this is the string version.
-                hash: upload_data.content_sha1.into_bytes(),
+                hash: hex::decode(&upload_data.content_sha1).map_err(FileHostingError::from)?,
             },
             models::version_item::HashBuilder {
                 algorithm: "sha512".to_string(),
-                // This is an invalid cast - the database expects the hash's
-                // bytes, but this is the string version.
-                hash: upload_data.content_sha512.into_bytes(),
+                hash: hex::decode(&upload_data.content_sha512).map_err(FileHostingError::from)?,
             },
         ],
         primary: validation_result == ValidationResult::Pass
             && version_files.iter().all(|x| !x.primary)
             && !ignore_primary,
+        size: Some(
+            i64::try_from(file_size)
+                .map_err(|_| CreateError::InvalidInput("Project file is too large".to_string()))?,
+        ),
+        file_type: file_type.map(|x| x.to_string()),
     });
 
     Ok(())
@@ -664,3 +861,263 @@ pub fn get_name_ext(
     };
     Ok((file_name, file_extension))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uploading_files_requires_upload_version() {
+        assert!(check_can_upload_files(Some(Permissions::UPLOAD_VERSION)).is_ok());
+        assert!(matches!(
+            check_can_upload_files(Some(Permissions::EDIT_DETAILS)),
+            Err(CreateError::Forbidden(_))
+        ));
+        assert!(matches!(
+            check_can_upload_files(None),
+            Err(CreateError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn files_named_like_existing_files_are_rejected() {
+        let existing = vec!["mod-1.0.0.jar", "mod-1.0.0-sources.jar"];
+
+        assert!(check_unique_filename(existing.iter().copied(), "mod-1.0.0-dev.jar").is_ok());
+        assert!(matches!(
+            check_unique_filename(existing.iter().copied(), "mod-1.0.0.jar"),
+            Err(CreateError::InvalidInput(_))
+        ));
+    }
+
+    fn chunks(
+        sizes: &[usize],
+    ) -> impl futures::Stream<Item = Result<actix_web::web::Bytes, actix_multipart::MultipartError>>
+    {
+        futures::stream::iter(
+            sizes
+                .iter()
+                .map(|size| Ok(actix_web::web::Bytes::from(vec![0; *size])))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// A jar with a single file of `size` bytes
+    fn jar(size: usize) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut jar = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        jar.start_file("data.bin", zip::write::FileOptions::default())
+            .unwrap();
+        jar.write_all(&vec![0; size]).unwrap();
+        jar.finish().unwrap().into_inner()
+    }
+
+    fn file_disposition(file_name: &str) -> actix_web::http::header::ContentDisposition {
+        use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+
+        ContentDisposition {
+            disposition: DispositionType::FormData,
+            parameters: vec![DispositionParam::Filename(file_name.to_string())],
+        }
+    }
+
+    /// Uploads a file sent in the given chunks through `upload_file`
+    fn upload(
+        file_host: &dyn FileHost,
+        file_name: &str,
+        chunks: Vec<Vec<u8>>,
+        file_size_cap: usize,
+        uploaded_files: &mut Vec<UploadedFile>,
+        version_files: &mut Vec<VersionFileBuilder>,
+    ) -> Result<(), CreateError> {
+        let mut field = futures::stream::iter(
+            chunks
+                .into_iter()
+                .map(|x| Ok(actix_web::web::Bytes::from(x)))
+                .collect::<Vec<_>>(),
+        );
+
+        futures::executor::block_on(upload_file(
+            &mut field,
+            file_host,
+            uploaded_files,
+            version_files,
+            "https://cdn.example.com",
+            &file_disposition(file_name),
+            crate::models::ids::ProjectId(1000),
+            "1.0.0",
+            "mod",
+            vec![],
+            vec![],
+            &[],
+            false,
+            None,
+            file_size_cap,
+        ))
+    }
+
+    #[test]
+    fn uploaded_files_are_returned_with_their_size() {
+        let file_host = crate::file_hosting::RecordingHost::new(false);
+        let data = jar(1000);
+        let mut uploaded_files = Vec::new();
+        let mut version_files = Vec::new();
+
+        upload(
+            &file_host,
+            "mod-1.0.0.jar",
+            vec![data.clone()],
+            max_file_size(),
+            &mut uploaded_files,
+            &mut version_files,
+        )
+        .unwrap();
+
+        let file = convert_file(&version_files[0]);
+        assert_eq!(file.size, Some(data.len() as u64));
+        assert_eq!(file.filename, "mod-1.0.0.jar");
+        assert!(file.primary);
+    }
+
+    #[test]
+    fn files_up_to_the_size_cap_are_read() {
+        let data = futures::executor::block_on(read_capped(&mut chunks(&[600, 424]), 1024));
+
+        assert_eq!(data.unwrap().len(), 1024);
+    }
+
+    #[test]
+    fn files_over_the_size_cap_are_rejected_and_uploads_are_undone() {
+        use actix_web::ResponseError;
+
+        let file_host = crate::file_hosting::RecordingHost::new(false);
+        let mut uploaded_files = Vec::new();
+        let mut version_files = Vec::new();
+
+        let first = jar(100);
+        let file_size_cap = first.len() + 100;
+        let first_path = format!(
+            "data/{}/versions/1.0.0/first.jar",
+            crate::models::ids::ProjectId(1000)
+        );
+
+        upload(
+            &file_host,
+            "first.jar",
+            vec![first],
+            file_size_cap,
+            &mut uploaded_files,
+            &mut version_files,
+        )
+        .unwrap();
+
+        let err = upload(
+            &file_host,
+            "second.jar",
+            vec![vec![0; file_size_cap - 10], vec![0; 20]],
+            file_size_cap,
+            &mut uploaded_files,
+            &mut version_files,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, CreateError::InvalidInput(_)));
+        assert_eq!(err.status_code(), actix_web::http::StatusCode::BAD_REQUEST);
+        assert!(err
+            .to_string()
+            .contains(&format!("{} bytes", file_size_cap)));
+
+        // The file over the cap is never uploaded, and the route undoes the earlier
+        // upload since creating the version failed
+        assert_eq!(
+            *file_host.uploaded.lock().unwrap(),
+            vec![first_path.clone()]
+        );
+        futures::executor::block_on(crate::routes::project_creation::undo_uploads(
+            &file_host,
+            &uploaded_files,
+        ))
+        .unwrap();
+
+        assert_eq!(file_host.deleted.into_inner().unwrap(), vec![first_path]);
+    }
+
+    #[test]
+    fn size_caps_are_shown_in_their_largest_unit() {
+        assert_eq!(format_size(100 * (1 << 20)), "100MiB");
+        assert_eq!(format_size(512 * (1 << 10)), "512KiB");
+        assert_eq!(format_size(1000), "1000 bytes");
+    }
+
+    fn hashed_file(filename: &str) -> VersionFileBuilder {
+        VersionFileBuilder {
+            url: format!("https://cdn.example.com/{}", filename),
+            filename: filename.to_string(),
+            hashes: vec![models::version_item::HashBuilder {
+                algorithm: "sha1".to_string(),
+                hash: b"da39a3ee5e6b4b0d3255bfef95601890afd80709".to_vec(),
+            }],
+            primary: true,
+            size: Some(1000),
+            file_type: None,
+        }
+    }
+
+    #[test]
+    fn files_in_other_versions_are_duplicates() {
+        let file = hashed_file("mod-1.1.0.jar");
+        let mut warnings = Vec::new();
+
+        check_duplicate_file(
+            &file,
+            models::VersionId(2),
+            &[models::VersionId(1), models::VersionId(2)],
+            false,
+            &mut warnings,
+        )
+        .unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("mod-1.1.0.jar"));
+    }
+
+    #[test]
+    fn files_only_in_their_own_version_are_not_duplicates() {
+        let file = hashed_file("mod-1.1.0.jar");
+        let mut warnings = Vec::new();
+
+        check_duplicate_file(&file, models::VersionId(2), &[], true, &mut warnings).unwrap();
+        check_duplicate_file(
+            &file,
+            models::VersionId(2),
+            &[models::VersionId(2)],
+            true,
+            &mut warnings,
+        )
+        .unwrap();
+
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn duplicate_files_conflict_when_configured() {
+        use actix_web::ResponseError;
+
+        let file = hashed_file("mod-1.1.0.jar");
+        let mut warnings = Vec::new();
+
+        let err = check_duplicate_file(
+            &file,
+            models::VersionId(2),
+            &[models::VersionId(1)],
+            true,
+            &mut warnings,
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, CreateError::DuplicateFile(_)));
+        assert_eq!(err.status_code(), actix_web::http::StatusCode::CONFLICT);
+        assert!(warnings.is_empty());
+    }
+}
@@ -4,12 +4,13 @@ use crate::models;
 use crate::models::projects::{GameVersion, Loader};
 use crate::models::teams::Permissions;
 use crate::util::auth::get_user_from_headers;
+use crate::util::ratelimit::DownloadLimiter;
 use crate::{database, Pepper};
 use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
-use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::sync::Arc;
 
 #[derive(Deserialize)]
@@ -22,14 +23,77 @@ fn default_algorithm() -> String {
     "sha1".into()
 }
 
-// under /api/v1/version_file/{hash}
-#[get("{version_id}")]
+/// The hashing algorithms files can be looked up by
+pub const HASH_ALGORITHMS: &[&str] = &["sha1", "sha512"];
+
+impl Algorithm {
+    /// Gets the requested algorithm, failing if files aren't hashed with it
+    pub fn get(&self) -> Result<&str, ApiError> {
+        check_algorithm(&self.algorithm)
+    }
+}
+
+/// Checks that files are hashed with the given algorithm
+pub fn check_algorithm(algorithm: &str) -> Result<&str, ApiError> {
+    if HASH_ALGORITHMS.contains(&algorithm) {
+        Ok(algorithm)
+    } else {
+        Err(ApiError::InvalidInputError(format!(
+            "Unknown hash algorithm: {}",
+            algorithm
+        )))
+    }
+}
+
+/// Decodes a hex encoded hash given by the user into the raw digest stored in the database
+pub fn parse_hash(hash: &str) -> Result<Vec<u8>, ApiError> {
+    hex::decode(hash).map_err(|_| ApiError::InvalidInputError(format!("Invalid hash: {}", hash)))
+}
+
+/// How downloads are counted, configured with the `DOWNLOAD_TRACKING` variable
+#[derive(Debug, PartialEq, Eq)]
+pub enum DownloadTracking {
+    /// Only count one download per IP hash every 30 minutes
+    Dedupe,
+    /// Count every download request
+    All,
+    /// Don't record downloads at all
+    None,
+}
+
+impl DownloadTracking {
+    pub fn from_env() -> Self {
+        Self::parse(dotenv::var("DOWNLOAD_TRACKING").ok().as_deref())
+    }
+
+    /// Parses a tracking mode, falling back to deduplicating downloads
+    pub fn parse(mode: Option<&str>) -> Self {
+        match mode {
+            Some("all") => DownloadTracking::All,
+            Some("none") => DownloadTracking::None,
+            _ => DownloadTracking::Dedupe,
+        }
+    }
+
+    /// Whether downloads are recorded at all
+    pub fn records_downloads(&self) -> bool {
+        *self != DownloadTracking::None
+    }
+
+    /// Whether a recent download from the same client is skipped
+    pub fn skips_recent_downloads(&self) -> bool {
+        *self == DownloadTracking::Dedupe
+    }
+}
+
+// under /api/v2/version_file/{hash}
+#[get("{hash}")]
 pub async fn get_version_from_hash(
     info: web::Path<(String,)>,
     pool: web::Data<PgPool>,
     algorithm: web::Query<Algorithm>,
 ) -> Result<HttpResponse, ApiError> {
-    let hash = info.into_inner().0.to_lowercase();
+    let hash = parse_hash(&info.into_inner().0)?;
 
     let result = sqlx::query!(
         "
@@ -37,8 +101,8 @@ pub async fn get_version_from_hash(
         INNER JOIN files f ON h.file_id = f.id
         WHERE h.algorithm = $2 AND h.hash = $1
         ",
-        hash.as_bytes(),
-        algorithm.algorithm
+        hash.as_slice(),
+        algorithm.get()?
     )
     .fetch_optional(&**pool)
     .await?;
@@ -60,21 +124,107 @@ pub async fn get_version_from_hash(
     }
 }
 
+// under /api/v2/version_file/{hash}/file
+#[get("{hash}/file")]
+pub async fn get_file_from_hash(
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    algorithm: web::Query<Algorithm>,
+) -> Result<HttpResponse, ApiError> {
+    let hash = parse_hash(&info.into_inner().0)?;
+
+    let result = sqlx::query!(
+        "
+        SELECT f.id file_id, f.version_id version_id FROM hashes h
+        INNER JOIN files f ON h.file_id = f.id
+        WHERE h.algorithm = $2 AND h.hash = $1
+        ",
+        hash.as_slice(),
+        algorithm.get()?
+    )
+    .fetch_optional(&**pool)
+    .await?;
+
+    if let Some(id) = result {
+        let file = database::models::Version::get_full(
+            database::models::VersionId(id.version_id),
+            &**pool,
+        )
+        .await?
+        .and_then(|version| version.files.into_iter().find(|x| x.id.0 == id.file_id));
+
+        if let Some(file) = file {
+            Ok(HttpResponse::Ok().json(super::versions::convert_file(file)))
+        } else {
+            Ok(HttpResponse::NotFound().body(""))
+        }
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct DownloadRedirect {
     pub url: String,
 }
 
-// under /api/v1/version_file/{hash}/download
-#[get("{version_id}/download")]
+/// Gets the IP address of the client making a request, preferring the one given by
+/// Cloudflare when `CLOUDFLARE_INTEGRATION` is enabled
+pub fn get_client_ip(req: &HttpRequest) -> Option<String> {
+    let connection_info = req.connection_info();
+
+    if dotenv::var("CLOUDFLARE_INTEGRATION")
+        .ok()
+        .map(|i| i.parse().unwrap())
+        .unwrap_or(false)
+    {
+        if let Some(header) = req.headers().get("CF-Connecting-IP") {
+            return header.to_str().ok().map(String::from);
+        }
+    }
+
+    connection_info.remote_addr().map(String::from)
+}
+
+/// Counts downloads against the client's download rate limit, failing once it is exceeded.
+/// Clients are identified by the same peppered hash of their IP used for download tracking.
+pub fn check_download_limit(
+    req: &HttpRequest,
+    limiter: &DownloadLimiter,
+    pepper: &Pepper,
+    downloads: u32,
+) -> Result<(), ApiError> {
+    if let Some(ip) = get_client_ip(req) {
+        if limiter.is_ignored(&ip) {
+            return Ok(());
+        }
+
+        let client = sha1::Sha1::from(format!("{}{}", ip, pepper.pepper)).hexdigest();
+
+        if !limiter.check(&client, downloads) {
+            return Err(ApiError::RateLimitError(format!(
+                "Only {} downloads are allowed per minute",
+                limiter.max_per_minute
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// under /api/v2/version_file/{hash}/download
+#[get("{hash}/download")]
 pub async fn download_version(
     req: HttpRequest,
     info: web::Path<(String,)>,
     pool: web::Data<PgPool>,
     algorithm: web::Query<Algorithm>,
     pepper: web::Data<Pepper>,
+    limiter: web::Data<Arc<DownloadLimiter>>,
 ) -> Result<HttpResponse, ApiError> {
-    let hash = info.into_inner().0.to_lowercase();
+    check_download_limit(&req, &limiter, &pepper, 1)?;
+
+    let hash = parse_hash(&info.into_inner().0)?;
     let mut transaction = pool.begin().await?;
 
     let result = sqlx::query!(
@@ -84,8 +234,8 @@ pub async fn download_version(
         INNER JOIN versions v ON v.id = f.version_id
         WHERE h.algorithm = $2 AND h.hash = $1
         ",
-        hash.as_bytes(),
-        algorithm.algorithm
+        hash.as_slice(),
+        algorithm.get()?
     )
     .fetch_optional(&mut *transaction)
     .await?;
@@ -117,26 +267,18 @@ async fn download_version_inner(
     transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     pepper: &web::Data<Pepper>,
 ) -> Result<(), ApiError> {
-    let real_ip = req.connection_info();
-    let ip_option = if dotenv::var("CLOUDFLARE_INTEGRATION")
-        .ok()
-        .map(|i| i.parse().unwrap())
-        .unwrap_or(false)
-    {
-        if let Some(header) = req.headers().get("CF-Connecting-IP") {
-            header.to_str().ok()
-        } else {
-            real_ip.borrow().remote_addr()
-        }
-    } else {
-        real_ip.borrow().remote_addr()
-    };
+    let tracking = DownloadTracking::from_env();
+
+    if !tracking.records_downloads() {
+        return Ok(());
+    }
 
-    if let Some(ip) = ip_option {
+    if let Some(ip) = get_client_ip(req) {
         let hash = sha1::Sha1::from(format!("{}{}", ip, pepper.pepper)).hexdigest();
 
-        let download_exists = sqlx::query!(
-                "SELECT EXISTS(SELECT 1 FROM downloads WHERE version_id = $1 AND date > (CURRENT_DATE - INTERVAL '30 minutes ago') AND identifier = $2)",
+        let download_exists = tracking.skips_recent_downloads()
+            && sqlx::query!(
+                "SELECT EXISTS(SELECT 1 FROM downloads WHERE version_id = $1 AND date > (NOW() - INTERVAL '30 minutes') AND identifier = $2)",
                 version_id as database::models::VersionId,
                 hash,
             )
@@ -188,8 +330,8 @@ async fn download_version_inner(
     Ok(())
 }
 
-// under /api/v1/version_file/{hash}
-#[delete("{version_id}")]
+// under /api/v2/version_file/{hash}
+#[delete("{hash}")]
 pub async fn delete_file(
     req: HttpRequest,
     info: web::Path<(String,)>,
@@ -199,17 +341,17 @@ pub async fn delete_file(
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(req.headers(), &**pool).await?;
 
-    let hash = info.into_inner().0.to_lowercase();
+    let hash = parse_hash(&info.into_inner().0)?;
 
     let result = sqlx::query!(
         "
-        SELECT f.id id, f.version_id version_id, f.filename filename, v.version_number version_number, v.mod_id project_id FROM hashes h
+        SELECT f.id id, f.version_id version_id, f.filename filename, f.is_primary is_primary, v.version_number version_number, v.mod_id project_id FROM hashes h
         INNER JOIN files f ON h.file_id = f.id
         INNER JOIN versions v ON v.id = f.version_id
         WHERE h.algorithm = $2 AND h.hash = $1
         ",
-        hash.as_bytes(),
-        algorithm.algorithm
+        hash.as_slice(),
+        algorithm.get()?
     )
         .fetch_optional(&**pool)
         .await
@@ -225,16 +367,14 @@ pub async fn delete_file(
             .await
             .map_err(ApiError::DatabaseError)?
             .ok_or_else(|| {
-                ApiError::CustomAuthenticationError(
-                    "You don't have permission to delete this file!".to_string(),
-                )
+                ApiError::Forbidden("You don't have permission to delete this file!".to_string())
             })?;
 
             if !team_member
                 .permissions
                 .contains(Permissions::DELETE_VERSION)
             {
-                return Err(ApiError::CustomAuthenticationError(
+                return Err(ApiError::Forbidden(
                     "You don't have permission to delete this file!".to_string(),
                 ));
             }
@@ -262,6 +402,35 @@ pub async fn delete_file(
         .execute(&mut *transaction)
         .await?;
 
+        // A version shouldn't be left without a primary file, so the newest remaining file
+        // takes its place
+        if row.is_primary {
+            let remaining = sqlx::query!(
+                "
+                SELECT id, created FROM files
+                WHERE version_id = $1
+                ",
+                row.version_id,
+            )
+            .fetch_all(&mut *transaction)
+            .await?;
+
+            if let Some(promoted) =
+                super::versions::newest_by_date(remaining.into_iter().map(|x| (x.id, x.created)))
+            {
+                sqlx::query!(
+                    "
+                    UPDATE files
+                    SET is_primary = TRUE
+                    WHERE (id = $1)
+                    ",
+                    promoted,
+                )
+                .execute(&mut *transaction)
+                .await?;
+            }
+        }
+
         let project_id: models::projects::ProjectId =
             database::models::ids::ProjectId(row.project_id).into();
         file_host
@@ -289,14 +458,15 @@ pub struct UpdateData {
     pub game_versions: Vec<GameVersion>,
 }
 
-#[post("{version_id}/update")]
+// under /api/v2/version_file/{hash}/update
+#[post("{hash}/update")]
 pub async fn get_update_from_hash(
     info: web::Path<(String,)>,
     pool: web::Data<PgPool>,
     algorithm: web::Query<Algorithm>,
     update_data: web::Json<UpdateData>,
 ) -> Result<HttpResponse, ApiError> {
-    let hash = info.into_inner().0.to_lowercase();
+    let hash = parse_hash(&info.into_inner().0)?;
 
     // get version_id from hash
     // get mod_id from hash
@@ -309,8 +479,8 @@ pub async fn get_update_from_hash(
         INNER JOIN versions v ON v.id = f.version_id
         WHERE h.algorithm = $2 AND h.hash = $1
         ",
-        hash.as_bytes(),
-        algorithm.algorithm
+        hash.as_slice(),
+        algorithm.get()?
     )
     .fetch_optional(&**pool)
     .await?;
@@ -334,11 +504,14 @@ pub async fn get_update_from_hash(
                     .map(|x| x.0)
                     .collect(),
             ),
+            None,
+            Some(1),
+            None,
             &**pool,
         )
         .await?;
 
-        if let Some(version_id) = version_ids.last() {
+        if let Some(version_id) = version_ids.first() {
             let version_data = database::models::Version::get_full(*version_id, &**pool).await?;
 
             if let Some(data) = version_data {
@@ -367,11 +540,12 @@ pub async fn get_versions_from_hashes(
     pool: web::Data<PgPool>,
     file_data: web::Json<FileHashes>,
 ) -> Result<HttpResponse, ApiError> {
+    let algorithm = check_algorithm(&file_data.algorithm)?;
     let hashes_parsed: Vec<Vec<u8>> = file_data
         .hashes
         .iter()
-        .map(|x| x.as_bytes().to_vec())
-        .collect();
+        .map(|x| parse_hash(x))
+        .collect::<Result<_, _>>()?;
 
     let result = sqlx::query!(
         "
@@ -380,7 +554,7 @@ pub async fn get_versions_from_hashes(
         WHERE h.algorithm = $2 AND h.hash IN (SELECT * FROM UNNEST($1::bytea[]))
         ",
         hashes_parsed.as_slice(),
-        file_data.algorithm
+        algorithm
     )
     .fetch_all(&**pool)
     .await?;
@@ -398,25 +572,81 @@ pub async fn get_versions_from_hashes(
 
     for row in result {
         if let Some(version) = versions_data.iter().find(|x| x.id.0 == row.version_id) {
-            response.insert(row.hash, super::versions::convert_version(version.clone()));
+            response.insert(
+                hex::encode(&row.hash),
+                super::versions::convert_version(version.clone()),
+            );
         }
     }
 
     Ok(HttpResponse::Ok().json(response))
 }
 
+// under /api/v2/version_files/exist
+#[post("exist")]
+pub async fn hashes_exist(
+    pool: web::Data<PgPool>,
+    file_data: web::Json<FileHashes>,
+) -> Result<HttpResponse, ApiError> {
+    let algorithm = check_algorithm(&file_data.algorithm)?;
+    let hashes_parsed: Vec<Vec<u8>> = file_data
+        .hashes
+        .iter()
+        .map(|x| parse_hash(x))
+        .collect::<Result<_, _>>()?;
+
+    let known_hashes = sqlx::query!(
+        "
+        SELECT DISTINCT h.hash hash FROM hashes h
+        WHERE h.algorithm = $2 AND h.hash = ANY($1)
+        ",
+        hashes_parsed.as_slice(),
+        algorithm
+    )
+    .fetch_all(&**pool)
+    .await?
+    .into_iter()
+    .map(|x| x.hash)
+    .collect::<HashSet<_>>();
+
+    Ok(HttpResponse::Ok().json(hash_membership(
+        &file_data.hashes,
+        &hashes_parsed,
+        &known_hashes,
+    )))
+}
+
+/// Maps each hash as given by the user to whether its digest is known
+fn hash_membership(
+    hashes: &[String],
+    hashes_parsed: &[Vec<u8>],
+    known_hashes: &HashSet<Vec<u8>>,
+) -> HashMap<String, bool> {
+    hashes
+        .iter()
+        .zip(hashes_parsed.iter())
+        .map(|(hash, parsed)| (hash.clone(), known_hashes.contains(parsed)))
+        .collect()
+}
+
 #[post("download")]
 pub async fn download_files(
     req: HttpRequest,
     pool: web::Data<PgPool>,
     file_data: web::Json<FileHashes>,
     pepper: web::Data<Pepper>,
+    limiter: web::Data<Arc<DownloadLimiter>>,
 ) -> Result<HttpResponse, ApiError> {
+    let algorithm = check_algorithm(&file_data.algorithm)?;
     let hashes_parsed: Vec<Vec<u8>> = file_data
         .hashes
         .iter()
-        .map(|x| x.as_bytes().to_vec())
-        .collect();
+        .map(|x| parse_hash(x))
+        .collect::<Result<_, _>>()?;
+
+    // Every requested file counts as a download, so the batch can't get around the limit
+    let downloads = u32::try_from(hashes_parsed.len()).unwrap_or(u32::MAX);
+    check_download_limit(&req, &limiter, &pepper, downloads)?;
 
     let mut transaction = pool.begin().await?;
 
@@ -428,7 +658,7 @@ pub async fn download_files(
         WHERE h.algorithm = $2 AND h.hash IN (SELECT * FROM UNNEST($1::bytea[]))
         ",
         hashes_parsed.as_slice(),
-        file_data.algorithm
+        algorithm
     )
     .fetch_all(&mut *transaction)
     .await?;
@@ -444,9 +674,11 @@ pub async fn download_files(
             &pepper,
         )
         .await?;
-        response.insert(row.hash, row.url);
+        response.insert(hex::encode(&row.hash), row.url);
     }
 
+    transaction.commit().await?;
+
     Ok(HttpResponse::Ok().json(response))
 }
 
@@ -463,11 +695,12 @@ pub async fn update_files(
     pool: web::Data<PgPool>,
     update_data: web::Json<ManyUpdateData>,
 ) -> Result<HttpResponse, ApiError> {
+    let algorithm = check_algorithm(&update_data.algorithm)?;
     let hashes_parsed: Vec<Vec<u8>> = update_data
         .hashes
         .iter()
-        .map(|x| x.as_bytes().to_vec())
-        .collect();
+        .map(|x| parse_hash(x))
+        .collect::<Result<_, _>>()?;
 
     let mut transaction = pool.begin().await?;
 
@@ -479,7 +712,7 @@ pub async fn update_files(
         WHERE h.algorithm = $2 AND h.hash IN (SELECT * FROM UNNEST($1::bytea[]))
         ",
         hashes_parsed.as_slice(),
-        update_data.algorithm
+        algorithm
     )
         .fetch_all(&mut *transaction)
         .await?;
@@ -505,11 +738,14 @@ pub async fn update_files(
                     .map(|x| x.0.clone())
                     .collect(),
             ),
+            None,
+            Some(1),
+            None,
             &**pool,
         )
         .await?;
 
-        if let Some(latest_version) = updated_versions.last() {
+        if let Some(latest_version) = updated_versions.first() {
             version_ids.push(*latest_version);
         }
     }
@@ -521,7 +757,7 @@ pub async fn update_files(
     for row in &result {
         if let Some(version) = versions.iter().find(|x| x.id.0 == row.version_id) {
             response.insert(
-                row.hash.clone(),
+                hex::encode(&row.hash),
                 super::versions::convert_version(version.clone()),
             );
         }
@@ -529,3 +765,107 @@ pub async fn update_files(
 
     Ok(HttpResponse::Ok().json(response))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::versions::newest_by_date;
+    use sha2::Digest;
+
+    #[test]
+    fn hashes_round_trip_through_hex() {
+        let sha1 = sha1::Sha1::from(b"fabric.jar").digest().bytes().to_vec();
+        let sha512 = sha2::Sha512::digest(b"fabric.jar").to_vec();
+
+        for digest in &[sha1, sha512] {
+            let encoded = hex::encode(digest);
+
+            assert_eq!(&parse_hash(&encoded).unwrap(), digest);
+            assert_eq!(&parse_hash(&encoded.to_uppercase()).unwrap(), digest);
+        }
+    }
+
+    #[test]
+    fn download_tracking_modes() {
+        let dedupe = DownloadTracking::parse(None);
+        assert_eq!(dedupe, DownloadTracking::Dedupe);
+        assert_eq!(
+            DownloadTracking::parse(Some("dedupe")),
+            DownloadTracking::Dedupe
+        );
+        assert!(dedupe.records_downloads());
+        assert!(dedupe.skips_recent_downloads());
+
+        let all = DownloadTracking::parse(Some("all"));
+        assert_eq!(all, DownloadTracking::All);
+        assert!(all.records_downloads());
+        assert!(!all.skips_recent_downloads());
+
+        let none = DownloadTracking::parse(Some("none"));
+        assert_eq!(none, DownloadTracking::None);
+        assert!(!none.records_downloads());
+    }
+
+    #[test]
+    fn invalid_hashes_are_rejected() {
+        assert!(matches!(
+            parse_hash("not a hash"),
+            Err(ApiError::InvalidInputError(_))
+        ));
+        assert!(matches!(
+            parse_hash("abc"),
+            Err(ApiError::InvalidInputError(_))
+        ));
+    }
+
+    #[test]
+    fn hash_algorithms_are_validated() {
+        let sha1 = web::Query::<Algorithm>::from_query("").unwrap();
+        assert_eq!(sha1.get().unwrap(), "sha1");
+
+        let sha512 = web::Query::<Algorithm>::from_query("algorithm=sha512").unwrap();
+        assert_eq!(sha512.get().unwrap(), "sha512");
+
+        let md5 = web::Query::<Algorithm>::from_query("algorithm=md5").unwrap();
+        assert!(matches!(md5.get(), Err(ApiError::InvalidInputError(_))));
+    }
+
+    #[test]
+    fn hash_membership_mixes_known_and_unknown_hashes() {
+        let known = sha1::Sha1::from(b"fabric.jar").digest().bytes().to_vec();
+        let unknown = sha1::Sha1::from(b"forge.jar").digest().bytes().to_vec();
+
+        // Hashes are reported the way they were given, even if not in lowercase
+        let hashes = vec![hex::encode(&known).to_uppercase(), hex::encode(&unknown)];
+        let hashes_parsed = hashes
+            .iter()
+            .map(|x| parse_hash(x))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let known_hashes = vec![known].into_iter().collect::<HashSet<_>>();
+
+        let membership = hash_membership(&hashes, &hashes_parsed, &known_hashes);
+
+        assert_eq!(membership.len(), 2);
+        assert!(membership[&hashes[0]]);
+        assert!(!membership[&hashes[1]]);
+    }
+
+    #[test]
+    fn deleting_a_primary_file_promotes_the_newest_sibling() {
+        let older = crate::util::time::parse_timestamp("2021-01-01T00:00:00Z").unwrap();
+        let newer = crate::util::time::parse_timestamp("2021-02-01T00:00:00Z").unwrap();
+
+        let remaining = vec![(1, older), (2, newer), (3, older)];
+        assert_eq!(newest_by_date(remaining), Some(2));
+
+        // Files uploaded together are told apart by their ids
+        let remaining = vec![(1, older), (3, older), (2, older)];
+        assert_eq!(newest_by_date(remaining), Some(3));
+    }
+
+    #[test]
+    fn deleting_the_last_file_promotes_nothing() {
+        assert_eq!(newest_by_date(Vec::new()), None);
+    }
+}
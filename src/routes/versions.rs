@@ -1,20 +1,30 @@
 use super::ApiError;
 use crate::database;
 use crate::models;
-use crate::models::projects::{Dependency, DependencyType};
+use crate::models::projects::{Dependency, DependencyType, FileType};
 use crate::models::teams::Permissions;
 use crate::util::auth::get_user_from_headers;
+use crate::util::pagination::PaginationConfig;
 use crate::util::validate::validation_errors_to_string;
-use actix_web::{delete, get, patch, web, HttpRequest, HttpResponse};
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use validator::Validate;
 
+/// The page size of `version_list` when no limit is given. This is above the global default
+/// since launchers have always fetched a project's versions in a single request.
+const DEFAULT_VERSION_PAGE_SIZE: usize = 50;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct VersionListFilters {
     pub game_versions: Option<String>,
     pub loaders: Option<String>,
     pub featured: Option<bool>,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
 }
 
 #[get("version")]
@@ -22,6 +32,7 @@ pub async fn version_list(
     info: web::Path<(String,)>,
     web::Query(filters): web::Query<VersionListFilters>,
     pool: web::Data<PgPool>,
+    pagination: web::Data<PaginationConfig>,
 ) -> Result<HttpResponse, ApiError> {
     let string = info.into_inner().0;
 
@@ -30,75 +41,164 @@ pub async fn version_list(
     if let Some(project) = result {
         let id = project.id;
 
+        let game_versions: Option<Vec<String>> = filters
+            .game_versions
+            .as_ref()
+            .map(|x| serde_json::from_str(x).unwrap_or_default());
+        let loaders: Option<Vec<String>> = filters
+            .loaders
+            .as_ref()
+            .map(|x| serde_json::from_str(x).unwrap_or_default());
+        let (limit, offset) = version_page(&filters, &pagination);
+
         let version_ids = database::models::Version::get_project_versions(
             id,
-            filters
-                .game_versions
-                .as_ref()
-                .map(|x| serde_json::from_str(x).unwrap_or_default()),
-            filters
-                .loaders
-                .as_ref()
-                .map(|x| serde_json::from_str(x).unwrap_or_default()),
+            game_versions.clone(),
+            loaders.clone(),
+            filters.featured,
+            Some(limit),
+            Some(offset),
             &**pool,
         )
         .await?;
 
-        let mut versions = database::models::Version::get_many_full(version_ids, &**pool).await?;
+        let mut versions =
+            database::models::Version::get_many_full(version_ids.clone(), &**pool).await?;
+
+        // get_many_full doesn't preserve the ordering from the query
+        versions.sort_by_key(|version| version_ids.iter().position(|id| *id == version.id));
 
         let mut response = versions
-            .iter()
-            .cloned()
-            .filter(|version| {
-                filters
-                    .featured
-                    .map(|featured| featured == version.featured)
-                    .unwrap_or(true)
-            })
+            .into_iter()
             .map(convert_version)
             .collect::<Vec<_>>();
 
-        versions.sort_by(|a, b| b.date_published.cmp(&a.date_published));
-
         // Attempt to populate versions with "auto featured" versions
-        if response.is_empty() && !versions.is_empty() && filters.featured.unwrap_or(false) {
-            let loaders = database::models::categories::Loader::list(&**pool).await?;
+        if response.is_empty() && filters.featured.unwrap_or(false) {
+            let version_ids = database::models::Version::get_project_versions(
+                id,
+                game_versions,
+                loaders,
+                None,
+                Some(limit),
+                Some(offset),
+                &**pool,
+            )
+            .await?;
+
+            let versions = database::models::Version::get_many_full(version_ids, &**pool)
+                .await?
+                .into_iter()
+                .map(convert_version)
+                .collect();
+
+            let loaders = database::models::categories::Loader::list(&**pool)
+                .await?
+                .into_iter()
+                .map(|x| x.loader)
+                .collect::<Vec<_>>();
             let game_versions =
                 database::models::categories::GameVersion::list_filter(None, Some(true), &**pool)
-                    .await?;
+                    .await?
+                    .into_iter()
+                    .map(|x| x.version)
+                    .collect::<Vec<_>>();
 
-            let mut joined_filters = Vec::new();
-            for game_version in &game_versions {
-                for loader in &loaders {
-                    joined_filters.push((game_version, loader))
-                }
-            }
+            response = auto_featured_versions(versions, &game_versions, &loaders);
+        }
 
-            joined_filters.into_iter().for_each(|filter| {
-                versions
-                    .iter()
-                    .find(|version| {
-                        version.game_versions.contains(&filter.0.version)
-                            && version.loaders.contains(&filter.1.loader)
-                    })
-                    .map(|version| response.push(convert_version(version.clone())))
-                    .unwrap_or(());
-            });
+        Ok(HttpResponse::Ok().json(response))
+    } else {
+        Ok(HttpResponse::NotFound().body(""))
+    }
+}
 
-            if response.is_empty() {
-                versions
-                    .into_iter()
-                    .for_each(|version| response.push(convert_version(version)));
+/// The limit and offset of the page of versions requested by `filters`
+fn version_page(filters: &VersionListFilters, pagination: &PaginationConfig) -> (i64, i64) {
+    (
+        pagination.limit_or(filters.limit, DEFAULT_VERSION_PAGE_SIZE) as i64,
+        i64::try_from(filters.offset).unwrap_or(i64::MAX),
+    )
+}
+
+/// Picks the newest version for every pair of a featured game version and a loader, for
+/// projects which haven't featured any versions themselves. If none of the versions match
+/// a pair, all of them are returned.
+fn auto_featured_versions(
+    mut versions: Vec<models::projects::Version>,
+    game_versions: &[String],
+    loaders: &[String],
+) -> Vec<models::projects::Version> {
+    sort_newest_first(&mut versions);
+
+    let mut featured = HashSet::new();
+    for game_version in game_versions {
+        for loader in loaders {
+            if let Some(version) = versions.iter().find(|version| {
+                version.game_versions.iter().any(|x| &x.0 == game_version)
+                    && version.loaders.iter().any(|x| &x.0 == loader)
+            }) {
+                featured.insert(version.id);
             }
         }
+    }
 
-        response.sort_by(|a, b| b.date_published.cmp(&a.date_published));
-        response.dedup_by(|a, b| a.id == b.id);
+    if !featured.is_empty() {
+        versions.retain(|version| featured.contains(&version.id));
+    }
 
-        Ok(HttpResponse::Ok().json(response))
-    } else {
-        Ok(HttpResponse::NotFound().body(""))
+    versions
+}
+
+/// Orders versions newest first, breaking ties by id the same way as
+/// `Version::get_project_versions` so that pages stay stable
+fn sort_newest_first(versions: &mut [models::projects::Version]) {
+    versions.sort_by(|a, b| {
+        b.date_published
+            .cmp(&a.date_published)
+            .then_with(|| b.id.0.cmp(&a.id.0))
+    });
+}
+
+#[get("version/{number}")]
+pub async fn version_project_get(
+    info: web::Path<(String, String)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let (string, version_number) = info.into_inner();
+
+    let result = database::models::Project::get_from_slug_or_project_id(string, &**pool).await?;
+
+    if let Some(project) = result {
+        // Version numbers aren't unique within a project, so the most recent version with
+        // the number is used
+        let versions = sqlx::query!(
+            "
+            SELECT id, date_published FROM versions
+            WHERE mod_id = $1 AND version_number = $2
+            ",
+            project.id as database::models::ids::ProjectId,
+            version_number,
+        )
+        .fetch_all(&**pool)
+        .await?;
+
+        if let Some(version_id) =
+            newest_by_date(versions.into_iter().map(|x| (x.id, x.date_published)))
+        {
+            let version_data = database::models::Version::get_full(
+                database::models::ids::VersionId(version_id),
+                &**pool,
+            )
+            .await?;
+
+            if let Some(data) = version_data {
+                return Ok(HttpResponse::Ok().json(convert_version(data)));
+            }
+        }
     }
+
+    Ok(HttpResponse::NotFound().body(""))
 }
 
 #[derive(Serialize, Deserialize)]
@@ -126,21 +226,224 @@ pub async fn versions_get(
     Ok(HttpResponse::Ok().json(versions))
 }
 
+#[derive(Deserialize)]
+pub struct VersionInclude {
+    /// A comma separated list of extra data to include. Currently only supports `dependencies`
+    pub include: Option<String>,
+}
+
+/// A version with its dependencies' version objects embedded
+#[derive(Serialize)]
+pub struct VersionWithDependencies {
+    #[serde(flatten)]
+    pub version: models::projects::Version,
+    pub resolved_dependencies: Vec<ResolvedDependency>,
+}
+
+#[derive(Serialize)]
+pub struct ResolvedDependency {
+    pub version_id: Option<models::ids::VersionId>,
+    pub project_id: Option<models::ids::ProjectId>,
+    pub dependency_type: DependencyType,
+    /// The full dependency version, if the dependency is on a specific version
+    pub version: Option<models::projects::Version>,
+    /// Whether the dependency's target version or project still exists
+    pub resolved: bool,
+}
+
 #[get("{version_id}")]
 pub async fn version_get(
     info: web::Path<(models::ids::VersionId,)>,
+    web::Query(include): web::Query<VersionInclude>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, ApiError> {
     let id = info.into_inner().0;
     let version_data = database::models::Version::get_full(id.into(), &**pool).await?;
 
     if let Some(data) = version_data {
-        Ok(HttpResponse::Ok().json(convert_version(data)))
+        let include_dependencies = include
+            .include
+            .as_deref()
+            .map(|x| x.split(',').any(|x| x.trim() == "dependencies"))
+            .unwrap_or(false);
+
+        if !include_dependencies {
+            return Ok(HttpResponse::Ok().json(convert_version(data)));
+        }
+
+        let dependency_versions = database::models::Version::get_many_full(
+            data.dependencies
+                .iter()
+                .filter_map(|x| x.version_id)
+                .collect(),
+            &**pool,
+        )
+        .await?;
+        let dependency_projects = database::models::Project::get_many(
+            data.dependencies
+                .iter()
+                .filter(|x| x.version_id.is_none())
+                .filter_map(|x| x.project_id)
+                .collect(),
+            &**pool,
+        )
+        .await?;
+
+        let resolved_dependencies = resolve_version_dependencies(
+            &data.dependencies,
+            dependency_versions,
+            &dependency_projects.iter().map(|x| x.id).collect::<Vec<_>>(),
+        );
+
+        Ok(HttpResponse::Ok().json(VersionWithDependencies {
+            version: convert_version(data),
+            resolved_dependencies,
+        }))
     } else {
         Ok(HttpResponse::NotFound().body(""))
     }
 }
 
+/// Pairs each dependency with the version it depends on, out of `dependency_versions`.
+/// Dependencies on versions which don't exist anymore, or on projects which aren't in
+/// `existing_projects`, are marked unresolved.
+fn resolve_version_dependencies(
+    dependencies: &[database::models::version_item::QueryDependency],
+    dependency_versions: Vec<database::models::version_item::QueryVersion>,
+    existing_projects: &[database::models::ids::ProjectId],
+) -> Vec<ResolvedDependency> {
+    dependencies
+        .iter()
+        .map(|dependency| {
+            let dependency_type = DependencyType::from_str(&*dependency.dependency_type);
+
+            if let Some(version_id) = dependency.version_id {
+                let version = dependency_versions
+                    .iter()
+                    .find(|x| x.id.0 == version_id.0)
+                    .cloned();
+
+                ResolvedDependency {
+                    version_id: Some(version_id.into()),
+                    project_id: version
+                        .as_ref()
+                        .map(|x| x.project_id)
+                        .or(dependency.project_id)
+                        .map(|x| x.into()),
+                    dependency_type,
+                    resolved: version.is_some(),
+                    version: version.map(convert_version),
+                }
+            } else {
+                ResolvedDependency {
+                    version_id: None,
+                    project_id: dependency.project_id.map(|x| x.into()),
+                    dependency_type,
+                    version: None,
+                    resolved: existing_projects
+                        .iter()
+                        .any(|x| Some(*x) == dependency.project_id),
+                }
+            }
+        })
+        .collect()
+}
+
+/// The maximum number of levels of dependencies that are resolved for a version
+const MAX_DEPENDENCY_DEPTH: usize = 10;
+
+/// Every version a version transitively depends on, along with the projects depended on
+#[derive(Serialize)]
+pub struct DependencyGraph {
+    pub versions: Vec<models::projects::Version>,
+    /// The projects of the resolved versions, and those depended on without a specific version
+    pub projects: Vec<models::ids::ProjectId>,
+}
+
+#[get("{version_id}/dependencies")]
+pub async fn version_dependencies_get(
+    info: web::Path<(models::ids::VersionId,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let id: database::models::VersionId = info.into_inner().0.into();
+    let version_data = database::models::Version::get_full(id, &**pool).await?;
+
+    let version = match version_data {
+        Some(version) => version,
+        None => return Ok(HttpResponse::NotFound().body("")),
+    };
+
+    let pool = &**pool;
+    let graph = resolve_dependencies(version, |ids| {
+        database::models::Version::get_many_full(ids, pool)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(graph))
+}
+
+/// Walks the dependencies of `version` breadth first, up to `MAX_DEPENDENCY_DEPTH` levels
+/// deep, getting each level of versions with `get_versions`
+async fn resolve_dependencies<F, Fut>(
+    version: database::models::version_item::QueryVersion,
+    mut get_versions: F,
+) -> Result<DependencyGraph, ApiError>
+where
+    F: FnMut(Vec<database::models::VersionId>) -> Fut,
+    Fut: std::future::Future<
+        Output = Result<Vec<database::models::version_item::QueryVersion>, sqlx::Error>,
+    >,
+{
+    let mut versions = Vec::new();
+    let mut projects: Vec<models::ids::ProjectId> = Vec::new();
+
+    // Versions are only visited once, so dependency cycles can't be followed forever
+    let mut visited = HashSet::new();
+    visited.insert(version.id.0);
+
+    let mut dependencies = version.dependencies;
+
+    for _ in 0..MAX_DEPENDENCY_DEPTH {
+        let mut next_ids = Vec::new();
+
+        for dependency in dependencies {
+            match dependency.version_id {
+                Some(version_id) => {
+                    if visited.insert(version_id.0) {
+                        next_ids.push(version_id);
+                    }
+                }
+                None => {
+                    if let Some(project_id) = dependency.project_id {
+                        projects.push(project_id.into());
+                    }
+                }
+            }
+        }
+
+        if next_ids.is_empty() {
+            break;
+        }
+
+        let next_versions = get_versions(next_ids).await?;
+
+        dependencies = next_versions
+            .iter()
+            .flat_map(|x| x.dependencies.clone())
+            .collect();
+
+        for next_version in next_versions {
+            projects.push(next_version.project_id.into());
+            versions.push(convert_version(next_version));
+        }
+    }
+
+    let mut seen_projects = HashSet::new();
+    projects.retain(|x| seen_projects.insert(*x));
+
+    Ok(DependencyGraph { versions, projects })
+}
+
 pub fn convert_version(
     data: database::models::version_item::QueryVersion,
 ) -> models::projects::Version {
@@ -165,25 +468,7 @@ pub fn convert_version(
             _ => VersionType::Release,
         },
 
-        files: data
-            .files
-            .into_iter()
-            .map(|f| {
-                models::projects::VersionFile {
-                    url: f.url,
-                    filename: f.filename,
-                    // FIXME: Hashes are currently stored as an ascii byte slice instead
-                    // of as an actual byte array in the database
-                    hashes: f
-                        .hashes
-                        .into_iter()
-                        .map(|(k, v)| Some((k, String::from_utf8(v).ok()?)))
-                        .collect::<Option<_>>()
-                        .unwrap_or_else(Default::default),
-                    primary: f.primary,
-                }
-            })
-            .collect(),
+        files: data.files.into_iter().map(convert_file).collect(),
         dependencies: data
             .dependencies
             .into_iter()
@@ -206,6 +491,23 @@ pub fn convert_version(
     }
 }
 
+pub fn convert_file(
+    file: database::models::version_item::QueryFile,
+) -> models::projects::VersionFile {
+    models::projects::VersionFile {
+        url: file.url,
+        filename: file.filename,
+        hashes: file
+            .hashes
+            .into_iter()
+            .map(|(k, v)| (k, hex::encode(v)))
+            .collect(),
+        primary: file.primary,
+        size: file.size.and_then(|x| u64::try_from(x).ok()),
+        file_type: file.file_type.as_deref().and_then(FileType::from_str),
+    }
+}
+
 #[derive(Serialize, Deserialize, Validate)]
 pub struct EditVersion {
     #[validate(length(min = 3, max = 256))]
@@ -263,7 +565,7 @@ pub async fn version_edit(
 
         if let Some(perms) = permissions {
             if !perms.contains(Permissions::UPLOAD_VERSION) {
-                return Err(ApiError::CustomAuthenticationError(
+                return Err(ApiError::Forbidden(
                     "You do not have the permissions to edit this version!".to_string(),
                 ));
             }
@@ -324,15 +626,6 @@ pub async fn version_edit(
             }
 
             if let Some(dependencies) = &new_version.dependencies {
-                sqlx::query!(
-                    "
-                    DELETE FROM dependencies WHERE dependent_id = $1
-                    ",
-                    id as database::models::ids::VersionId,
-                )
-                .execute(&mut *transaction)
-                .await?;
-
                 let builders = dependencies
                     .iter()
                     .map(|x| database::models::version_item::DependencyBuilder {
@@ -342,6 +635,28 @@ pub async fn version_edit(
                     })
                     .collect::<Vec<database::models::version_item::DependencyBuilder>>();
 
+                if let Some(missing) =
+                    database::models::version_item::DependencyBuilder::find_missing_version(
+                        &builders,
+                        &mut *transaction,
+                    )
+                    .await?
+                {
+                    return Err(ApiError::InvalidInputError(format!(
+                        "The dependency version {} does not exist",
+                        models::ids::VersionId::from(missing)
+                    )));
+                }
+
+                sqlx::query!(
+                    "
+                    DELETE FROM dependencies WHERE dependent_id = $1
+                    ",
+                    id as database::models::ids::VersionId,
+                )
+                .execute(&mut *transaction)
+                .await?;
+
                 for dependency in builders {
                     dependency.insert(version_item.id, &mut transaction).await?;
                 }
@@ -430,13 +745,15 @@ pub async fn version_edit(
             }
 
             if let Some(primary_file) = &new_version.primary_file {
+                let hash = super::version_file::parse_hash(&primary_file.1)?;
+
                 let result = sqlx::query!(
                     "
                     SELECT f.id id FROM hashes h
                     INNER JOIN files f ON h.file_id = f.id
                     WHERE h.algorithm = $2 AND h.hash = $1
                     ",
-                    primary_file.1.as_bytes(),
+                    hash.as_slice(),
                     primary_file.0
                 )
                 .fetch_optional(&**pool)
@@ -488,7 +805,7 @@ pub async fn version_edit(
             transaction.commit().await?;
             Ok(HttpResponse::NoContent().body(""))
         } else {
-            Err(ApiError::CustomAuthenticationError(
+            Err(ApiError::Forbidden(
                 "You do not have permission to edit this version!".to_string(),
             ))
         }
@@ -524,7 +841,7 @@ pub async fn version_delete(
             .permissions
             .contains(Permissions::DELETE_VERSION)
         {
-            return Err(ApiError::CustomAuthenticationError(
+            return Err(ApiError::Forbidden(
                 "You do not have permission to delete versions in this team".to_string(),
             ));
         }
@@ -542,3 +859,597 @@ pub async fn version_delete(
         Ok(HttpResponse::NotFound().body(""))
     }
 }
+
+#[derive(Deserialize)]
+pub struct FileMoveQuery {
+    /// The version to move the file to
+    pub to: models::ids::VersionId,
+}
+
+/// Moves a file, identified by its hash, to another version of the same project
+#[post("{version_id}/files/{hash}/move")]
+pub async fn version_file_move(
+    req: HttpRequest,
+    info: web::Path<(models::ids::VersionId, String)>,
+    pool: web::Data<PgPool>,
+    query: web::Query<FileMoveQuery>,
+    algorithm: web::Query<super::version_file::Algorithm>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(req.headers(), &**pool).await?;
+    let (id, hash) = info.into_inner();
+    let hash = super::version_file::parse_hash(&hash)?;
+    let algorithm = algorithm.get()?;
+
+    let source = database::models::Version::get(id.into(), &**pool).await?;
+    let target = database::models::Version::get(query.to.into(), &**pool).await?;
+
+    let (source, target) = match (source, target) {
+        (Some(source), Some(target)) => (source, target),
+        _ => return Ok(HttpResponse::NotFound().body("")),
+    };
+
+    check_file_move(&source, &target)?;
+
+    if !user.role.is_mod() {
+        let team_member = database::models::TeamMember::get_from_user_id_version(
+            source.id,
+            user.id.into(),
+            &**pool,
+        )
+        .await
+        .map_err(ApiError::DatabaseError)?
+        .ok_or_else(|| {
+            ApiError::Forbidden("You do not have permission to move files in this team".to_string())
+        })?;
+
+        if !team_member
+            .permissions
+            .contains(Permissions::UPLOAD_VERSION)
+        {
+            return Err(ApiError::Forbidden(
+                "You do not have permission to move files in this team".to_string(),
+            ));
+        }
+    }
+
+    let mut transaction = pool.begin().await?;
+
+    let file = sqlx::query!(
+        "
+        SELECT f.id FROM hashes h
+        INNER JOIN files f ON h.file_id = f.id
+        WHERE f.version_id = $1 AND h.algorithm = $2 AND h.hash = $3
+        ",
+        source.id as database::models::ids::VersionId,
+        algorithm,
+        hash.as_slice(),
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let file = if let Some(file) = file {
+        file
+    } else {
+        return Ok(HttpResponse::NotFound().body(""));
+    };
+
+    let files = sqlx::query!(
+        "
+        SELECT id, version_id, is_primary, created FROM files
+        WHERE version_id = $1 OR version_id = $2
+        ",
+        source.id as database::models::ids::VersionId,
+        target.id as database::models::ids::VersionId,
+    )
+    .fetch_all(&mut *transaction)
+    .await?
+    .into_iter()
+    .map(|x| MovedFile {
+        id: x.id,
+        version_id: x.version_id,
+        is_primary: x.is_primary,
+        created: x.created,
+    })
+    .collect::<Vec<_>>();
+
+    let plan = plan_file_move(file.id, target.id.0, &files);
+
+    sqlx::query!(
+        "
+        UPDATE files
+        SET version_id = $2, is_primary = $3
+        WHERE id = $1
+        ",
+        file.id,
+        target.id as database::models::ids::VersionId,
+        plan.primary,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    if let Some(promoted) = plan.promoted {
+        sqlx::query!(
+            "
+            UPDATE files
+            SET is_primary = TRUE
+            WHERE id = $1
+            ",
+            promoted,
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
+
+/// Checks that a file can be moved from the `source` version to the `target` version
+fn check_file_move(
+    source: &database::models::Version,
+    target: &database::models::Version,
+) -> Result<(), ApiError> {
+    if source.project_id != target.project_id {
+        return Err(ApiError::InvalidInputError(
+            "Files can only be moved between versions of the same project".to_string(),
+        ));
+    }
+
+    if source.id == target.id {
+        return Err(ApiError::InvalidInputError(
+            "The file is already in this version".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Finds the id of the newest of the given rows by their creation date, such as the file that
+/// takes the place of a version's primary file when it is moved or deleted. Rows created at
+/// the same time are told apart by their ids.
+pub fn newest_by_date(
+    rows: impl IntoIterator<Item = (i64, chrono::DateTime<chrono::Utc>)>,
+) -> Option<i64> {
+    rows.into_iter()
+        .max_by_key(|(id, created)| (*created, *id))
+        .map(|(id, _)| id)
+}
+
+/// A file of one of the versions a file is moved between
+struct MovedFile {
+    id: i64,
+    version_id: i64,
+    is_primary: bool,
+    created: chrono::DateTime<chrono::Utc>,
+}
+
+/// How the primary files of both versions change when a file is moved
+#[derive(Debug, PartialEq)]
+struct FileMovePlan {
+    /// Whether the moved file becomes the primary file of the target version, which is only
+    /// the case if the target version doesn't have one
+    primary: bool,
+    /// If the primary file was moved, the newest remaining file of the source version takes
+    /// its place, as when a primary file is deleted
+    promoted: Option<i64>,
+}
+
+fn plan_file_move(file_id: i64, target_id: i64, files: &[MovedFile]) -> FileMovePlan {
+    let moved_primary = files.iter().any(|x| x.id == file_id && x.is_primary);
+
+    let primary = !files
+        .iter()
+        .any(|x| x.version_id == target_id && x.is_primary);
+
+    let promoted = if moved_primary {
+        newest_by_date(
+            files
+                .iter()
+                .filter(|x| x.id != file_id && x.version_id != target_id)
+                .map(|x| (x.id, x.created)),
+        )
+    } else {
+        None
+    };
+
+    FileMovePlan { primary, promoted }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ids::{ProjectId, UserId, VersionId};
+    use crate::models::projects::{Version, VersionType};
+
+    fn version(id: u64, date_published: &str) -> Version {
+        Version {
+            id: VersionId(id),
+            project_id: ProjectId(1),
+            author_id: UserId(1),
+            featured: false,
+            name: format!("Version {}", id),
+            version_number: id.to_string(),
+            changelog: String::new(),
+            changelog_url: None,
+            date_published: crate::util::time::parse_timestamp(date_published).unwrap(),
+            downloads: 0,
+            version_type: VersionType::Release,
+            files: vec![],
+            dependencies: vec![],
+            game_versions: vec![],
+            loaders: vec![],
+        }
+    }
+
+    fn filters(limit: Option<usize>, offset: usize) -> VersionListFilters {
+        VersionListFilters {
+            game_versions: None,
+            loaders: None,
+            featured: None,
+            limit,
+            offset,
+        }
+    }
+
+    #[test]
+    fn version_pages_default_to_fifty_and_are_capped() {
+        let pagination = PaginationConfig {
+            default_page_size: 10,
+            max_page_size: 100,
+        };
+
+        assert_eq!(version_page(&filters(None, 0), &pagination), (50, 0));
+        assert_eq!(version_page(&filters(Some(20), 40), &pagination), (20, 40));
+        assert_eq!(version_page(&filters(Some(500), 0), &pagination), (100, 0));
+        assert_eq!(
+            version_page(&filters(None, usize::MAX), &pagination),
+            (50, i64::MAX)
+        );
+    }
+
+    #[test]
+    fn version_page_parameters_are_optional() {
+        let filters: VersionListFilters =
+            serde_json::from_str(r#"{"game_versions": null, "loaders": null, "featured": null}"#)
+                .unwrap();
+
+        assert_eq!(filters.limit, None);
+        assert_eq!(filters.offset, 0);
+    }
+
+    #[test]
+    fn versions_are_sorted_newest_first() {
+        let mut versions = vec![
+            version(1, "2021-01-01T00:00:00Z"),
+            version(3, "2021-03-01T00:00:00Z"),
+            version(2, "2021-03-01T00:00:00Z"),
+            version(4, "2021-02-01T00:00:00Z"),
+        ];
+
+        sort_newest_first(&mut versions);
+
+        let ids = versions.iter().map(|x| x.id.0).collect::<Vec<_>>();
+        assert_eq!(ids, vec![3, 2, 4, 1]);
+    }
+
+    fn supporting(mut version: Version, game_version: &str, loader: &str) -> Version {
+        version
+            .game_versions
+            .push(models::projects::GameVersion(game_version.to_string()));
+        version
+            .loaders
+            .push(models::projects::Loader(loader.to_string()));
+        version
+    }
+
+    #[test]
+    fn auto_featured_versions_are_the_newest_for_each_game_version_and_loader() {
+        let versions = vec![
+            supporting(version(1, "2021-01-01T00:00:00Z"), "1.16.5", "fabric"),
+            supporting(version(2, "2021-02-01T00:00:00Z"), "1.16.5", "fabric"),
+            supporting(version(3, "2021-01-15T00:00:00Z"), "1.16.5", "forge"),
+            supporting(version(4, "2021-03-01T00:00:00Z"), "1.17", "fabric"),
+        ];
+        let game_versions = vec!["1.16.5".to_string(), "1.17".to_string()];
+        let loaders = vec!["fabric".to_string(), "forge".to_string()];
+
+        let featured = auto_featured_versions(versions, &game_versions, &loaders);
+
+        let ids = featured.iter().map(|x| x.id.0).collect::<Vec<_>>();
+        assert_eq!(ids, vec![4, 2, 3]);
+    }
+
+    #[test]
+    fn auto_featured_versions_fall_back_to_every_version() {
+        let versions = vec![
+            supporting(version(1, "2021-01-01T00:00:00Z"), "1.12.2", "forge"),
+            supporting(version(2, "2021-02-01T00:00:00Z"), "1.12.2", "forge"),
+        ];
+        let game_versions = vec!["1.17".to_string()];
+        let loaders = vec!["fabric".to_string()];
+
+        let featured = auto_featured_versions(versions, &game_versions, &loaders);
+
+        let ids = featured.iter().map(|x| x.id.0).collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 1]);
+    }
+
+    fn stored_version(id: i64, project_id: i64) -> database::models::Version {
+        database::models::Version {
+            id: database::models::ids::VersionId(id),
+            project_id: database::models::ids::ProjectId(project_id),
+            author_id: database::models::ids::UserId(1),
+            name: format!("Version {}", id),
+            version_number: id.to_string(),
+            changelog: String::new(),
+            changelog_url: None,
+            date_published: chrono::Utc::now(),
+            downloads: 0,
+            release_channel: database::models::ids::ChannelId(1),
+            featured: false,
+        }
+    }
+
+    fn file(id: i64, version_id: i64, is_primary: bool, created: &str) -> MovedFile {
+        MovedFile {
+            id,
+            version_id,
+            is_primary,
+            created: crate::util::time::parse_timestamp(created).unwrap(),
+        }
+    }
+
+    #[test]
+    fn files_move_within_a_project() {
+        assert!(check_file_move(&stored_version(1, 1), &stored_version(2, 1)).is_ok());
+        assert!(matches!(
+            check_file_move(&stored_version(1, 1), &stored_version(1, 1)),
+            Err(ApiError::InvalidInputError(_))
+        ));
+    }
+
+    #[test]
+    fn files_cannot_move_to_another_project() {
+        assert!(matches!(
+            check_file_move(&stored_version(1, 1), &stored_version(2, 2)),
+            Err(ApiError::InvalidInputError(_))
+        ));
+    }
+
+    #[test]
+    fn moved_files_are_primary_only_without_a_target_primary() {
+        let files = vec![
+            file(1, 10, false, "2021-01-01T00:00:00Z"),
+            file(2, 10, true, "2021-01-01T00:00:00Z"),
+            file(3, 20, true, "2021-01-01T00:00:00Z"),
+        ];
+        assert_eq!(
+            plan_file_move(1, 20, &files),
+            FileMovePlan {
+                primary: false,
+                promoted: None,
+            }
+        );
+
+        let files = vec![
+            file(1, 10, false, "2021-01-01T00:00:00Z"),
+            file(2, 10, true, "2021-01-01T00:00:00Z"),
+            file(3, 20, false, "2021-01-01T00:00:00Z"),
+        ];
+        assert_eq!(
+            plan_file_move(1, 20, &files),
+            FileMovePlan {
+                primary: true,
+                promoted: None,
+            }
+        );
+    }
+
+    #[test]
+    fn moving_a_primary_file_promotes_the_newest_remaining_file() {
+        let files = vec![
+            file(1, 10, true, "2021-03-01T00:00:00Z"),
+            file(2, 10, false, "2021-02-01T00:00:00Z"),
+            file(3, 10, false, "2021-01-01T00:00:00Z"),
+            file(4, 10, false, "2021-02-01T00:00:00Z"),
+            file(5, 20, false, "2021-04-01T00:00:00Z"),
+        ];
+
+        assert_eq!(
+            plan_file_move(1, 20, &files),
+            FileMovePlan {
+                primary: true,
+                promoted: Some(4),
+            }
+        );
+    }
+
+    #[test]
+    fn moving_the_only_file_promotes_nothing() {
+        let files = vec![file(1, 10, true, "2021-01-01T00:00:00Z")];
+
+        assert_eq!(
+            plan_file_move(1, 20, &files),
+            FileMovePlan {
+                primary: true,
+                promoted: None,
+            }
+        );
+    }
+
+    /// A stored version of project `id / 10` depending on the versions in `dependencies`
+    fn dependent_version(
+        id: i64,
+        dependencies: &[i64],
+    ) -> database::models::version_item::QueryVersion {
+        database::models::version_item::QueryVersion {
+            id: database::models::ids::VersionId(id),
+            project_id: database::models::ids::ProjectId(id / 10),
+            author_id: database::models::ids::UserId(1),
+            name: format!("Version {}", id),
+            version_number: id.to_string(),
+            changelog: String::new(),
+            changelog_url: None,
+            date_published: chrono::Utc::now(),
+            downloads: 0,
+            release_channel: "release".to_string(),
+            files: vec![],
+            game_versions: vec![],
+            loaders: vec![],
+            featured: false,
+            dependencies: dependencies
+                .iter()
+                .map(|x| database::models::version_item::QueryDependency {
+                    project_id: None,
+                    version_id: Some(database::models::ids::VersionId(*x)),
+                    dependency_type: "required".to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Resolves the dependencies of the first of `versions`, returning the ids of the
+    /// versions and projects found
+    fn resolve(
+        versions: Vec<database::models::version_item::QueryVersion>,
+    ) -> (Vec<u64>, Vec<u64>) {
+        let root = versions[0].clone();
+        let stored = versions
+            .into_iter()
+            .map(|x| (x.id.0, x))
+            .collect::<std::collections::HashMap<_, _>>();
+
+        let graph = futures::executor::block_on(resolve_dependencies(root, |ids| {
+            futures::future::ready(Ok::<_, sqlx::Error>(
+                ids.into_iter()
+                    .filter_map(|id| stored.get(&id.0).cloned())
+                    .collect::<Vec<_>>(),
+            ))
+        }))
+        .unwrap();
+
+        let mut versions = graph.versions.iter().map(|x| x.id.0).collect::<Vec<_>>();
+        versions.sort_unstable();
+        let mut projects = graph.projects.iter().map(|x| x.0).collect::<Vec<_>>();
+        projects.sort_unstable();
+
+        (versions, projects)
+    }
+
+    #[test]
+    fn dependencies_are_resolved_along_a_chain() {
+        let (versions, projects) = resolve(vec![
+            dependent_version(10, &[20]),
+            dependent_version(20, &[30]),
+            dependent_version(30, &[]),
+        ]);
+
+        assert_eq!(versions, vec![20, 30]);
+        assert_eq!(projects, vec![2, 3]);
+    }
+
+    #[test]
+    fn shared_dependencies_are_resolved_once() {
+        let (versions, projects) = resolve(vec![
+            dependent_version(10, &[20, 30]),
+            dependent_version(20, &[40]),
+            dependent_version(30, &[40]),
+            dependent_version(40, &[]),
+        ]);
+
+        assert_eq!(versions, vec![20, 30, 40]);
+        assert_eq!(projects, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn dependency_cycles_are_not_followed() {
+        let (versions, _) = resolve(vec![
+            dependent_version(10, &[20, 10]),
+            dependent_version(20, &[30]),
+            dependent_version(30, &[10, 20]),
+        ]);
+
+        assert_eq!(versions, vec![20, 30]);
+    }
+
+    #[test]
+    fn dependencies_are_only_resolved_to_the_maximum_depth() {
+        let chain = (0..MAX_DEPENDENCY_DEPTH as i64 + 5)
+            .map(|x| dependent_version(x * 10 + 10, &[x * 10 + 20]))
+            .collect();
+
+        let (versions, _) = resolve(chain);
+
+        assert_eq!(versions.len(), MAX_DEPENDENCY_DEPTH);
+    }
+
+    #[test]
+    fn version_numbers_resolve_to_their_version() {
+        let published = crate::util::time::parse_timestamp("2021-01-01T00:00:00Z").unwrap();
+
+        assert_eq!(newest_by_date(vec![(4, published)]), Some(4));
+    }
+
+    #[test]
+    fn missing_version_numbers_resolve_to_nothing() {
+        assert_eq!(newest_by_date(Vec::new()), None);
+    }
+
+    #[test]
+    fn duplicate_version_numbers_resolve_to_the_most_recent_version() {
+        let older = crate::util::time::parse_timestamp("2021-01-01T00:00:00Z").unwrap();
+        let newer = crate::util::time::parse_timestamp("2021-03-01T00:00:00Z").unwrap();
+
+        assert_eq!(newest_by_date(vec![(7, newer), (9, older)]), Some(7));
+        assert_eq!(newest_by_date(vec![(9, older), (7, newer)]), Some(7));
+    }
+
+    #[test]
+    fn missing_dependencies_are_unresolved() {
+        use database::models::ids::ProjectId;
+        use database::models::version_item::QueryDependency;
+
+        let dependencies = vec![
+            QueryDependency {
+                project_id: None,
+                version_id: Some(database::models::ids::VersionId(40)),
+                dependency_type: "required".to_string(),
+            },
+            QueryDependency {
+                project_id: Some(ProjectId(7)),
+                version_id: Some(database::models::ids::VersionId(50)),
+                dependency_type: "optional".to_string(),
+            },
+            QueryDependency {
+                project_id: Some(ProjectId(8)),
+                version_id: None,
+                dependency_type: "required".to_string(),
+            },
+            QueryDependency {
+                project_id: Some(ProjectId(9)),
+                version_id: None,
+                dependency_type: "incompatible".to_string(),
+            },
+        ];
+
+        let resolved = resolve_version_dependencies(
+            &dependencies,
+            vec![dependent_version(40, &[])],
+            &[ProjectId(8)],
+        );
+
+        assert!(resolved[0].resolved);
+        assert_eq!(resolved[0].version.as_ref().map(|x| x.id.0), Some(40));
+        assert_eq!(resolved[0].project_id.map(|x| x.0), Some(4));
+
+        // The deleted version keeps the ids it was stored with
+        assert!(!resolved[1].resolved);
+        assert!(resolved[1].version.is_none());
+        assert_eq!(resolved[1].version_id.map(|x| x.0), Some(50));
+        assert_eq!(resolved[1].project_id.map(|x| x.0), Some(7));
+
+        assert!(resolved[2].resolved);
+        assert!(!resolved[3].resolved);
+    }
+}
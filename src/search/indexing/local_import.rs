@@ -16,7 +16,7 @@ pub async fn index_local(pool: PgPool) -> Result<Vec<UploadSearchProject>, Index
             SELECT m.id id, m.project_type project_type, m.title title, m.description description, m.downloads downloads, m.follows follows,
             m.icon_url icon_url, m.published published,
             m.updated updated,
-            m.team_id team_id, m.license license, m.slug slug,
+            m.team_id team_id, m.license license, m.slug slug, m.sensitive sensitive,
             s.status status_name, cs.name client_side_type, ss.name server_side_type, l.short short, pt.name project_type_name, u.username username,
             STRING_AGG(DISTINCT c.category, ',') categories, STRING_AGG(DISTINCT lo.loader, ',') loaders, STRING_AGG(DISTINCT gv.version, ',') versions
             FROM mods m
@@ -70,6 +70,7 @@ pub async fn index_local(pool: PgPool) -> Result<Vec<UploadSearchProject>, Index
                         server_side: m.server_side_type,
                         slug: m.slug,
                         project_type: m.project_type_name,
+                        sensitive: m.sensitive,
                     }
                 }))
             })
@@ -87,7 +88,7 @@ pub async fn query_one(
             SELECT m.id id, m.project_type project_type, m.title title, m.description description, m.downloads downloads, m.follows follows,
             m.icon_url icon_url, m.published published,
             m.updated updated,
-            m.team_id team_id, m.license license, m.slug slug,
+            m.team_id team_id, m.license license, m.slug slug, m.sensitive sensitive,
             s.status status_name, cs.name client_side_type, ss.name server_side_type, l.short short, pt.name project_type_name, u.username username,
             STRING_AGG(DISTINCT c.category, ',') categories, STRING_AGG(DISTINCT lo.loader, ',') loaders, STRING_AGG(DISTINCT gv.version, ',') versions
             FROM mods m
@@ -161,5 +162,6 @@ pub async fn query_one(
         server_side: m.server_side_type,
         slug: m.slug,
         project_type: m.project_type_name,
+        sensitive: m.sensitive,
     })
 }
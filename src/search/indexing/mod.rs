@@ -9,6 +9,8 @@ use meilisearch_sdk::indexes::Index;
 use meilisearch_sdk::settings::Settings;
 use sqlx::postgres::PgPool;
 use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -32,6 +34,33 @@ pub enum IndexingError {
 // assumes a max average size of 1KiB per project to avoid this cap.
 const MEILISEARCH_CHUNK_SIZE: usize = 10000;
 
+/// The names of every index projects are added to, one for each order results can be sorted in
+const PROJECT_INDICES: [&str; 5] = [
+    "relevance_projects",
+    "downloads_projects",
+    "follows_projects",
+    "updated_projects",
+    "newest_projects",
+];
+
+/// Keeps the local database from being indexed more than once at a time, since
+/// concurrent runs would fight over the same indices
+#[derive(Default)]
+pub struct IndexingGuard {
+    running: AtomicBool,
+}
+
+impl IndexingGuard {
+    /// Marks indexing as started, returning `false` if it is already running
+    pub fn try_start(&self) -> bool {
+        !self.running.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn finish(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
 #[derive(Debug)]
 pub struct IndexingSettings {
     pub index_local: bool,
@@ -51,14 +80,35 @@ pub async fn index_projects(
     settings: IndexingSettings,
     config: &SearchConfig,
 ) -> Result<(), IndexingError> {
+    index_projects_with(
+        settings,
+        || index_local(pool.clone()),
+        |projects| add_projects(projects, config),
+    )
+    .await
+}
+
+/// Imports the searchable projects from every source enabled in `settings` with its import
+/// function, and adds all of them to the indices with `add`
+async fn index_projects_with<I, IFut, A, AFut>(
+    settings: IndexingSettings,
+    import_local: I,
+    add: A,
+) -> Result<(), IndexingError>
+where
+    I: FnOnce() -> IFut,
+    IFut: Future<Output = Result<Vec<UploadSearchProject>, IndexingError>>,
+    A: FnOnce(Vec<UploadSearchProject>) -> AFut,
+    AFut: Future<Output = Result<(), IndexingError>>,
+{
     let mut docs_to_add: Vec<UploadSearchProject> = vec![];
 
     if settings.index_local {
-        docs_to_add.append(&mut index_local(pool.clone()).await?);
+        docs_to_add.append(&mut import_local().await?);
     }
     // Write Indices
 
-    add_projects(docs_to_add, config).await?;
+    add(docs_to_add).await?;
 
     Ok(())
 }
@@ -66,11 +116,51 @@ pub async fn index_projects(
 pub async fn reset_indices(config: &SearchConfig) -> Result<(), IndexingError> {
     let client = Client::new(&*config.address, &*config.key);
 
-    client.delete_index("relevance_projects").await?;
-    client.delete_index("downloads_projects").await?;
-    client.delete_index("follows_projects").await?;
-    client.delete_index("updated_projects").await?;
-    client.delete_index("newest_projects").await?;
+    for name in PROJECT_INDICES.iter() {
+        client.delete_index(name).await?;
+    }
+    Ok(())
+}
+
+/// Removes a project from each of the project indices
+pub async fn delete_from_index(
+    id: crate::models::projects::ProjectId,
+    config: &SearchConfig,
+) -> Result<(), meilisearch_sdk::errors::Error> {
+    let client = Client::new(&*config.address, &*config.key);
+    let client = &client;
+
+    delete_from_indices(id, |name, document| async move {
+        let index = match client.get_index(name).await {
+            Ok(index) => index,
+            // If the index hasn't been created yet there is nothing to remove
+            Err(meilisearch_sdk::errors::Error::MeiliSearchError {
+                error_code: meilisearch_sdk::errors::ErrorCode::IndexNotFound,
+                ..
+            }) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        index.delete_document(document).await?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes a project's document from each of the project indices with `delete_document`,
+/// which is given the name of the index and the id of the document
+async fn delete_from_indices<F, Fut, E>(
+    id: crate::models::projects::ProjectId,
+    mut delete_document: F,
+) -> Result<(), E>
+where
+    F: FnMut(&'static str, String) -> Fut,
+    Fut: std::future::Future<Output = Result<(), E>>,
+{
+    for name in PROJECT_INDICES.iter() {
+        delete_document(*name, id.to_string()).await?;
+    }
+
     Ok(())
 }
 
@@ -262,6 +352,7 @@ fn default_settings() -> Settings {
         "license".to_string(),
         "client_side".to_string(),
         "server_side".to_string(),
+        "sensitive".to_string(),
     ];
 
     let searchable_attributes = vec![
@@ -288,6 +379,7 @@ fn default_settings() -> Settings {
             String::from("client_side"),
             String::from("server_side"),
             String::from("project_type"),
+            String::from("sensitive"),
         ])
 }
 
@@ -349,3 +441,106 @@ pub fn sort_projects(a: &str, b: &str) -> std::cmp::Ordering {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::projects::ProjectId;
+    use std::cell::RefCell;
+
+    fn project(id: &str) -> UploadSearchProject {
+        let created = crate::util::time::parse_timestamp("2021-01-01T00:00:00Z").unwrap();
+
+        UploadSearchProject {
+            project_id: id.to_string(),
+            project_type: "mod".to_string(),
+            slug: None,
+            author: "author".to_string(),
+            title: format!("Project {}", id),
+            description: String::new(),
+            categories: vec![],
+            versions: vec![],
+            follows: 0,
+            downloads: 0,
+            icon_url: String::new(),
+            latest_version: "None".to_string(),
+            license: "mit".to_string(),
+            client_side: "required".to_string(),
+            server_side: "optional".to_string(),
+            sensitive: false,
+            date_created: created,
+            created_timestamp: created.timestamp(),
+            date_modified: created,
+            modified_timestamp: created.timestamp(),
+        }
+    }
+
+    #[test]
+    fn reindexing_adds_every_imported_project() {
+        let added = RefCell::new(Vec::new());
+
+        futures::executor::block_on(index_projects_with(
+            IndexingSettings { index_local: true },
+            || futures::future::ready(Ok(vec![project("AAAA"), project("BBBB"), project("CCCC")])),
+            |projects| {
+                added
+                    .borrow_mut()
+                    .extend(projects.into_iter().map(|x| x.project_id));
+                futures::future::ready(Ok(()))
+            },
+        ))
+        .unwrap();
+
+        assert_eq!(added.into_inner(), vec!["AAAA", "BBBB", "CCCC"]);
+    }
+
+    #[test]
+    fn reindexing_skips_disabled_sources() {
+        let added = RefCell::new(None);
+
+        futures::executor::block_on(index_projects_with(
+            IndexingSettings { index_local: false },
+            || -> futures::future::Ready<_> { panic!("local projects shouldn't be imported") },
+            |projects| {
+                *added.borrow_mut() = Some(projects.len());
+                futures::future::ready(Ok(()))
+            },
+        ))
+        .unwrap();
+
+        assert_eq!(added.into_inner(), Some(0));
+    }
+
+    #[test]
+    fn deleting_a_project_only_deletes_it_from_the_project_indices() {
+        let calls = RefCell::new(Vec::new());
+
+        futures::executor::block_on(delete_from_indices(ProjectId(1000), |name, document| {
+            calls.borrow_mut().push((name, document));
+            futures::future::ready(Ok::<(), ()>(()))
+        }))
+        .unwrap();
+
+        let id = ProjectId(1000).to_string();
+        assert_eq!(
+            calls.into_inner(),
+            PROJECT_INDICES
+                .iter()
+                .map(|name| (*name, id.clone()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn deleting_a_project_stops_at_the_first_error() {
+        let calls = RefCell::new(0);
+
+        let result = futures::executor::block_on(delete_from_indices(ProjectId(1000), |_, _| {
+            *calls.borrow_mut() += 1;
+            futures::future::ready(Err("unavailable"))
+        }));
+
+        assert_eq!(result, Err("unavailable"));
+        assert_eq!(calls.into_inner(), 1);
+    }
+}
@@ -1,6 +1,8 @@
 use super::{add_projects, IndexingError, UploadSearchProject};
 use crate::search::SearchConfig;
+use std::future::Future;
 use std::sync::Mutex;
+use std::time::Duration;
 
 pub struct CreationQueue {
     // There's probably a better structure for this, but a mutex works
@@ -26,10 +28,130 @@ impl CreationQueue {
     }
 }
 
+/// Indexes every project in the queue, retrying with an exponential backoff when
+/// meilisearch fails. Projects which still can't be indexed after `max_retries`
+/// are logged and dropped, and get picked up by the next full local index.
 pub async fn index_queue(
     queue: &CreationQueue,
     config: &SearchConfig,
 ) -> Result<(), IndexingError> {
     let queue = queue.take();
-    add_projects(queue, config).await
+
+    if queue.is_empty() {
+        return Ok(());
+    }
+
+    let result = retry_with_backoff(
+        config.max_retries,
+        config.retry_base_delay,
+        || add_projects(queue.clone(), config),
+        actix_rt::time::delay_for,
+    )
+    .await;
+
+    if let Err(err) = &result {
+        log::error!(
+            "Giving up on indexing projects [{}] after {} attempts: {}",
+            queue
+                .iter()
+                .map(|x| &*x.project_id)
+                .collect::<Vec<_>>()
+                .join(", "),
+            config.max_retries + 1,
+            err
+        );
+    }
+
+    result
+}
+
+/// Runs `attempt` until it succeeds or has been retried `max_retries` times, waiting with
+/// `delay` for `base_delay` before the first retry and twice as long before every retry
+/// after it
+async fn retry_with_backoff<F, Fut, D, DFut>(
+    max_retries: u32,
+    base_delay: Duration,
+    mut attempt: F,
+    mut delay: D,
+) -> Result<(), IndexingError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), IndexingError>>,
+    D: FnMut(Duration) -> DFut,
+    DFut: Future<Output = ()>,
+{
+    let mut retries = 0;
+
+    loop {
+        match attempt().await {
+            Ok(()) => return Ok(()),
+            Err(err) if retries >= max_retries => return Err(err),
+            Err(err) => {
+                log::warn!("Indexing created projects failed, retrying: {}", err);
+
+                delay(base_delay * (1 << retries.min(6))).await;
+                retries += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn unavailable() -> IndexingError {
+        IndexingError::SerDeError(serde_json::from_str::<u32>("unavailable").unwrap_err())
+    }
+
+    #[test]
+    fn failed_indexing_is_retried_until_it_succeeds() {
+        let attempts = RefCell::new(0);
+        let indexed = RefCell::new(Vec::new());
+        let delays = RefCell::new(Vec::new());
+
+        let result = futures::executor::block_on(retry_with_backoff(
+            5,
+            Duration::from_millis(100),
+            || {
+                *attempts.borrow_mut() += 1;
+                futures::future::ready(if *attempts.borrow() < 3 {
+                    Err(unavailable())
+                } else {
+                    indexed.borrow_mut().push("AABBCCDD");
+                    Ok(())
+                })
+            },
+            |duration| {
+                delays.borrow_mut().push(duration);
+                futures::future::ready(())
+            },
+        ));
+
+        assert!(result.is_ok());
+        assert_eq!(indexed.into_inner(), vec!["AABBCCDD"]);
+        assert_eq!(
+            delays.into_inner(),
+            vec![Duration::from_millis(100), Duration::from_millis(200)]
+        );
+    }
+
+    #[test]
+    fn failed_indexing_gives_up_after_the_max_retries() {
+        let attempts = RefCell::new(0);
+
+        let result = futures::executor::block_on(retry_with_backoff(
+            2,
+            Duration::from_millis(100),
+            || {
+                *attempts.borrow_mut() += 1;
+                futures::future::ready(Err(unavailable()))
+            },
+            |_| futures::future::ready(()),
+        ));
+
+        assert!(matches!(result, Err(IndexingError::SerDeError(_))));
+        assert_eq!(attempts.into_inner(), 3);
+    }
 }
@@ -1,13 +1,15 @@
-use crate::models::error::ApiError;
-use crate::models::projects::SearchRequest;
+use crate::models::error::{ApiError, ErrorCode};
+use crate::models::ids::base62_impl::parse_base62;
+use crate::models::projects::{ProjectId, SearchRequest, SideType};
+use crate::util::pagination::PaginationConfig;
 use actix_web::http::StatusCode;
 use actix_web::web::HttpResponse;
 use chrono::{DateTime, Utc};
 use meilisearch_sdk::client::Client;
 use meilisearch_sdk::document::Document;
+use meilisearch_sdk::search::Selectors;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::cmp::min;
 use thiserror::Error;
 
 pub mod indexing;
@@ -24,6 +26,12 @@ pub enum SearchError {
     EnvError(#[from] dotenv::Error),
     #[error("Invalid index to sort by: {0}")]
     InvalidIndex(String),
+    #[error("Invalid field to return: {0}")]
+    InvalidField(String),
+    #[error("Invalid side type: {0}")]
+    InvalidSideType(String),
+    #[error("Invalid project id to exclude: {0}")]
+    InvalidProjectId(String),
 }
 
 impl actix_web::ResponseError for SearchError {
@@ -34,27 +42,41 @@ impl actix_web::ResponseError for SearchError {
             SearchError::SerDeError(..) => StatusCode::BAD_REQUEST,
             SearchError::IntParsingError(..) => StatusCode::BAD_REQUEST,
             SearchError::InvalidIndex(..) => StatusCode::BAD_REQUEST,
+            SearchError::InvalidField(..) => StatusCode::BAD_REQUEST,
+            SearchError::InvalidSideType(..) => StatusCode::BAD_REQUEST,
+            SearchError::InvalidProjectId(..) => StatusCode::BAD_REQUEST,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
         HttpResponse::build(self.status_code()).json(ApiError {
             error: match self {
-                SearchError::EnvError(..) => "environment_error",
-                SearchError::MeiliSearchError(..) => "meilisearch_error",
-                SearchError::SerDeError(..) => "invalid_input",
-                SearchError::IntParsingError(..) => "invalid_input",
-                SearchError::InvalidIndex(..) => "invalid_input",
+                SearchError::EnvError(..) => ErrorCode::EnvironmentError,
+                SearchError::MeiliSearchError(..) => ErrorCode::MeilisearchError,
+                SearchError::SerDeError(..) => ErrorCode::InvalidInput,
+                SearchError::IntParsingError(..) => ErrorCode::InvalidInput,
+                SearchError::InvalidIndex(..) => ErrorCode::InvalidInput,
+                SearchError::InvalidField(..) => ErrorCode::InvalidInput,
+                SearchError::InvalidSideType(..) => ErrorCode::InvalidInput,
+                SearchError::InvalidProjectId(..) => ErrorCode::InvalidInput,
             },
             description: &self.to_string(),
         })
     }
 }
 
+/// How many projects with a slug or title equal to the query are looked up when
+/// `exact_first` is requested. Slugs are unique, but titles aren't.
+const EXACT_MATCH_CANDIDATES: usize = 20;
+
 #[derive(Clone)]
 pub struct SearchConfig {
     pub address: String,
     pub key: String,
+    /// How many times indexing the created project queue is retried before giving up
+    pub max_retries: u32,
+    /// The delay before the first retry, which doubles with every attempt after it
+    pub retry_base_delay: std::time::Duration,
 }
 
 /// A project document used for uploading projects to meilisearch's indices.
@@ -76,20 +98,26 @@ pub struct UploadSearchProject {
     pub license: String,
     pub client_side: String,
     pub server_side: String,
+    pub sensitive: bool,
 
     /// RFC 3339 formatted creation date of the project
+    #[serde(with = "crate::util::time::rfc3339")]
     pub date_created: DateTime<Utc>,
     /// Unix timestamp of the creation date of the project
     pub created_timestamp: i64,
     /// RFC 3339 formatted date/time of last major modification (update)
+    #[serde(with = "crate::util::time::rfc3339")]
     pub date_modified: DateTime<Utc>,
     /// Unix timestamp of the last major modification
     pub modified_timestamp: i64,
 }
 
+/// A search result as returned by meilisearch, which may only have some of its fields
+type SearchHit = serde_json::Map<String, serde_json::Value>;
+
 #[derive(Serialize, Deserialize, Debug)]
-pub struct SearchResults {
-    pub hits: Vec<ResultSearchProject>,
+pub struct SearchResults<T = ResultSearchProject> {
+    pub hits: Vec<T>,
     pub offset: usize,
     pub limit: usize,
     pub total_hits: usize,
@@ -117,6 +145,72 @@ pub struct ResultSearchProject {
     pub license: String,
     pub client_side: String,
     pub server_side: String,
+    /// Projects indexed before sensitive projects existed don't have this field until the
+    /// next full reindex, and aren't sensitive
+    #[serde(default)]
+    pub sensitive: bool,
+}
+
+/// The fields of a search result which can be requested with the `fields` parameter
+pub const SEARCH_RESULT_FIELDS: &[&str] = &[
+    "project_id",
+    "project_type",
+    "slug",
+    "author",
+    "title",
+    "description",
+    "categories",
+    "versions",
+    "downloads",
+    "follows",
+    "icon_url",
+    "date_created",
+    "date_modified",
+    "latest_version",
+    "license",
+    "client_side",
+    "server_side",
+    "sensitive",
+];
+
+/// Parses the `fields` parameter, a json array of field names. Unknown field names
+/// are rejected.
+fn parse_fields(fields: &str) -> Result<Vec<String>, SearchError> {
+    let fields = serde_json::from_str::<Vec<String>>(fields)?;
+
+    if let Some(field) = fields
+        .iter()
+        .find(|x| !SEARCH_RESULT_FIELDS.contains(&x.as_str()))
+    {
+        return Err(SearchError::InvalidField(field.clone()));
+    }
+
+    Ok(fields)
+}
+
+/// Searches for projects, only retrieving the requested fields of each hit
+pub async fn project_fields(
+    info: &SearchRequest,
+    config: &SearchConfig,
+    pagination: &PaginationConfig,
+    fields: &str,
+) -> Result<SearchResults<SearchHit>, SearchError> {
+    let fields = parse_fields(fields)?;
+
+    // Finding the exact match needs these, so they are removed again afterwards
+    let mut attributes = fields.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+    attributes.extend_from_slice(&["project_id", "title", "slug"]);
+
+    let mut results = search_hits(info, config, pagination, Some(&attributes)).await?;
+
+    for hit in &mut results.hits {
+        *hit = std::mem::take(hit)
+            .into_iter()
+            .filter(|(key, _)| fields.contains(key))
+            .collect();
+    }
+
+    Ok(results)
 }
 
 impl Document for UploadSearchProject {
@@ -135,12 +229,76 @@ impl Document for ResultSearchProject {
     }
 }
 
+/// Gets the facet filter for a `client_side` or `server_side` parameter, checking that
+/// the value is a valid side type
+fn side_facet(field: &str, value: &str) -> Result<String, SearchError> {
+    let side_type = SideType::from_str(value);
+
+    if side_type.as_str() != value {
+        return Err(SearchError::InvalidSideType(value.to_string()));
+    }
+
+    Ok(format!("{}:{}", field, side_type))
+}
+
+/// Gets the filter excluding the given projects. The ids are parsed first, so that only
+/// base62 ids end up in the filter.
+fn exclusion_filter(ids: &[String]) -> Result<String, SearchError> {
+    Ok(ids
+        .iter()
+        .map(|id| {
+            let id: ProjectId = parse_base62(id)
+                .map(ProjectId)
+                .map_err(|_| SearchError::InvalidProjectId(id.to_string()))?;
+
+            Ok(format!("NOT project_id = \"{}\"", id))
+        })
+        .collect::<Result<Vec<_>, SearchError>>()?
+        .join(" AND "))
+}
+
+/// Gets the meilisearch index which sorts projects in the given order
+fn sort_index(sort: &str) -> Option<&'static str> {
+    match sort {
+        "relevance" => Some("relevance_projects"),
+        "downloads" => Some("downloads_projects"),
+        "follows" => Some("follows_projects"),
+        "updated" => Some("updated_projects"),
+        "newest" => Some("newest_projects"),
+        _ => None,
+    }
+}
+
 pub async fn search_for_project(
     info: &SearchRequest,
     config: &SearchConfig,
+    pagination: &PaginationConfig,
 ) -> Result<SearchResults, SearchError> {
-    let client = Client::new(&*config.address, &*config.key);
+    let results = search_hits(info, config, pagination, None).await?;
+
+    Ok(SearchResults {
+        hits: results
+            .hits
+            .into_iter()
+            .map(|hit| serde_json::from_value(serde_json::Value::Object(hit)))
+            .collect::<Result<_, _>>()?,
+        offset: results.offset,
+        limit: results.limit,
+        total_hits: results.total_hits,
+    })
+}
+
+/// Checks whether a field of a hit is exactly the query, ignoring case
+fn is_exact_match(hit: &SearchHit, field: &str, query: &str) -> bool {
+    hit.get(field)
+        .and_then(|x| x.as_str())
+        .map(|x| x.eq_ignore_ascii_case(query))
+        .unwrap_or(false)
+}
 
+/// Gets the filters of a search request, which hide sensitive projects and the excluded
+/// projects unless requested otherwise
+fn search_filters(info: &SearchRequest) -> Result<Cow<str>, SearchError> {
     let filters: Cow<_> = match (info.filters.as_deref(), info.version.as_deref()) {
         (Some(f), Some(v)) => format!("({}) AND ({})", f, v).into(),
         (Some(f), None) => f.into(),
@@ -148,23 +306,59 @@ pub async fn search_for_project(
         (None, None) => "".into(),
     };
 
+    // Sensitive projects are hidden unless they are explicitly requested. This is negated
+    // so that projects indexed before the field existed aren't hidden until a reindex.
+    let filters: Cow<_> = if info.show_sensitive.unwrap_or(false) {
+        filters
+    } else if filters.is_empty() {
+        "NOT sensitive = true".into()
+    } else {
+        format!("({}) AND (NOT sensitive = true)", filters).into()
+    };
+
+    Ok(if let Some(excluded_ids) = info.excluded_ids.as_deref() {
+        let exclusions = exclusion_filter(&serde_json::from_str::<Vec<String>>(excluded_ids)?)?;
+
+        match (filters.is_empty(), exclusions.is_empty()) {
+            (_, true) => filters,
+            (true, false) => exclusions.into(),
+            (false, false) => format!("({}) AND ({})", filters, exclusions).into(),
+        }
+    } else {
+        filters
+    })
+}
+
+/// Searches for projects, retrieving every field of each hit unless attributes are given
+async fn search_hits(
+    info: &SearchRequest,
+    config: &SearchConfig,
+    pagination: &PaginationConfig,
+    attributes: Option<&[&str]>,
+) -> Result<SearchResults<SearchHit>, SearchError> {
+    let client = Client::new(&*config.address, &*config.key);
+
+    let filters = search_filters(info)?;
+
     let offset = info.offset.as_deref().unwrap_or("0").parse()?;
-    let index = info.index.as_deref().unwrap_or("relevance");
-    let limit = info.limit.as_deref().unwrap_or("10").parse()?;
-
-    let index = match index {
-        "relevance" => "relevance_projects",
-        "downloads" => "downloads_projects",
-        "follows" => "follows_projects",
-        "updated" => "updated_projects",
-        "newest" => "newest_projects",
-        i => return Err(SearchError::InvalidIndex(i.to_string())),
+    let limit = pagination.limit(info.limit.as_deref().map(|x| x.parse()).transpose()?);
+
+    // Unlike the older `index` parameter, an invalid `sort` falls back to relevance
+    let index = if let Some(sort) = info.sort.as_deref() {
+        sort_index(sort).unwrap_or("relevance_projects")
+    } else {
+        let index = info.index.as_deref().unwrap_or("relevance");
+        sort_index(index).ok_or_else(|| SearchError::InvalidIndex(index.to_string()))?
     };
 
     let meilisearch_index = client.get_index(index).await?;
     let mut query = meilisearch_index.search();
 
-    query.with_limit(min(100, limit)).with_offset(offset);
+    query.with_limit(limit).with_offset(offset);
+
+    if let Some(attributes) = attributes {
+        query.with_attributes_to_retrieve(Selectors::Some(attributes));
+    }
 
     if let Some(search) = info.query.as_deref() {
         if !search.is_empty() {
@@ -184,23 +378,232 @@ pub async fn search_for_project(
     // to construct efficiently.  Instead it should take impl Iterator<Item=&[&str]>,
     // &[impl AsRef<[&str]>], or one of many other proper solutions to that issue.
 
-    let why_meilisearch;
-    let why_must_you_do_this;
-    if let Some(facets) = &info.facets {
-        why_meilisearch = serde_json::from_str::<Vec<Vec<&str>>>(facets)?;
-        why_must_you_do_this = why_meilisearch
-            .iter()
-            .map(|v| v as &[_])
-            .collect::<Vec<&[_]>>();
-        query.with_facet_filters(&why_must_you_do_this);
+    let side_facets = vec![
+        info.client_side
+            .as_deref()
+            .map(|x| side_facet("client_side", x))
+            .transpose()?,
+        info.server_side
+            .as_deref()
+            .map(|x| side_facet("server_side", x))
+            .transpose()?,
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+
+    // Side facets are added as their own groups, so they are ANDed with the given facets
+    let mut why_meilisearch = if let Some(facets) = &info.facets {
+        serde_json::from_str::<Vec<Vec<&str>>>(facets)?
+    } else {
+        Vec::new()
+    };
+    why_meilisearch.extend(side_facets.iter().map(|x| vec![&**x]));
+
+    let why_must_you_do_this = if why_meilisearch.is_empty() {
+        None
+    } else {
+        Some(
+            why_meilisearch
+                .iter()
+                .map(|v| v as &[_])
+                .collect::<Vec<&[_]>>(),
+        )
+    };
+
+    if let Some(facets) = &why_must_you_do_this {
+        query.with_facet_filters(facets);
     }
 
-    let results = query.execute::<ResultSearchProject>().await?;
+    let results = query.execute::<SearchHit>().await?;
+    let mut hits = results
+        .hits
+        .into_iter()
+        .map(|r| r.result)
+        .collect::<Vec<_>>();
+
+    // Relevance ranking can bury a project whose slug or title is exactly the query,
+    // so when requested it is looked up separately and moved to the top of the results
+    if info.exact_first.unwrap_or(false) {
+        if let Some(search) = info.query.as_deref().filter(|x| !x.is_empty()) {
+            // The candidates are searched for with the query itself rather than a filter on
+            // the slug and title, so the query is never interpreted as filter syntax. The
+            // requested sort order is ignored, since it may not rank exact matches highly.
+            let relevance_index = client.get_index("relevance_projects").await?;
+            let mut exact_query = relevance_index.search();
+            exact_query
+                .with_query(search)
+                .with_limit(EXACT_MATCH_CANDIDATES);
+
+            if !filters.is_empty() {
+                exact_query.with_filters(&filters);
+            }
+
+            if let Some(facets) = &why_must_you_do_this {
+                exact_query.with_facet_filters(facets);
+            }
+
+            if let Some(attributes) = attributes {
+                exact_query.with_attributes_to_retrieve(Selectors::Some(attributes));
+            }
+
+            let mut candidates = exact_query
+                .execute::<SearchHit>()
+                .await?
+                .hits
+                .into_iter()
+                .map(|r| r.result)
+                .collect::<Vec<_>>();
+
+            // A slug match is preferred, since it refers to a single project
+            let exact_match = candidates
+                .iter()
+                .position(|hit| is_exact_match(hit, "slug", search))
+                .or_else(|| {
+                    candidates
+                        .iter()
+                        .position(|hit| is_exact_match(hit, "title", search))
+                })
+                .map(|index| candidates.swap_remove(index));
+
+            if let Some(exact_match) = exact_match {
+                hits.retain(|hit| hit.get("project_id") != exact_match.get("project_id"));
+
+                if offset == 0 {
+                    hits.insert(0, exact_match);
+                    hits.truncate(limit);
+                }
+            }
+        }
+    }
 
     Ok(SearchResults {
-        hits: results.hits.into_iter().map(|r| r.result).collect(),
+        hits,
         offset: results.offset,
         limit: results.limit,
         total_hits: results.nb_hits,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_matches_ignore_case() {
+        let hit = serde_json::json!({ "title": "Sodium", "slug": null })
+            .as_object()
+            .unwrap()
+            .clone();
+
+        assert!(is_exact_match(&hit, "title", "sodium"));
+        assert!(!is_exact_match(&hit, "title", "sodium extra"));
+        assert!(!is_exact_match(&hit, "slug", "sodium"));
+    }
+
+    #[test]
+    fn fields_must_be_search_result_fields() {
+        assert_eq!(
+            parse_fields(r#"["title", "downloads"]"#).unwrap(),
+            vec!["title".to_string(), "downloads".to_string()]
+        );
+        assert!(matches!(
+            parse_fields(r#"["title", "body"]"#),
+            Err(SearchError::InvalidField(x)) if x == "body"
+        ));
+        assert!(matches!(
+            parse_fields("title"),
+            Err(SearchError::SerDeError(..))
+        ));
+    }
+
+    #[test]
+    fn sort_orders_map_to_indices() {
+        assert_eq!(sort_index("relevance"), Some("relevance_projects"));
+        assert_eq!(sort_index("newest"), Some("newest_projects"));
+        assert_eq!(sort_index("newest_projects"), None);
+        assert_eq!(sort_index(""), None);
+    }
+
+    #[test]
+    fn side_facets_are_validated() {
+        assert_eq!(
+            side_facet("client_side", "required").unwrap(),
+            "client_side:required"
+        );
+        assert_eq!(
+            side_facet("server_side", "unknown").unwrap(),
+            "server_side:unknown"
+        );
+        assert!(matches!(
+            side_facet("client_side", "sometimes"),
+            Err(SearchError::InvalidSideType(x)) if x == "sometimes"
+        ));
+    }
+
+    #[test]
+    fn excluded_ids_must_be_project_ids() {
+        assert_eq!(
+            exclusion_filter(&["AABBCCDD".to_string(), "xyz".to_string()]).unwrap(),
+            "NOT project_id = \"AABBCCDD\" AND NOT project_id = \"xyz\""
+        );
+        assert_eq!(exclusion_filter(&[]).unwrap(), "");
+        assert!(matches!(
+            exclusion_filter(&["AABBCCDD\\\" OR project_id = \"x".to_string()]),
+            Err(SearchError::InvalidProjectId(_))
+        ));
+        assert!(matches!(
+            exclusion_filter(&["a b".to_string()]),
+            Err(SearchError::InvalidProjectId(_))
+        ));
+    }
+
+    fn request() -> SearchRequest {
+        SearchRequest {
+            query: None,
+            facets: None,
+            filters: None,
+            version: None,
+            offset: None,
+            index: None,
+            sort: None,
+            limit: None,
+            excluded_ids: None,
+            show_sensitive: None,
+            exact_first: None,
+            fields: None,
+            client_side: None,
+            server_side: None,
+        }
+    }
+
+    #[test]
+    fn sensitive_projects_are_hidden_by_default() {
+        assert_eq!(search_filters(&request()).unwrap(), "NOT sensitive = true");
+
+        let request = SearchRequest {
+            filters: Some("downloads > 10".to_string()),
+            show_sensitive: Some(false),
+            ..request()
+        };
+        assert_eq!(
+            search_filters(&request).unwrap(),
+            "(downloads > 10) AND (NOT sensitive = true)"
+        );
+    }
+
+    #[test]
+    fn sensitive_projects_can_be_included() {
+        let request = SearchRequest {
+            show_sensitive: Some(true),
+            ..request()
+        };
+        assert_eq!(search_filters(&request).unwrap(), "");
+
+        let request = SearchRequest {
+            filters: Some("downloads > 10".to_string()),
+            ..request
+        };
+        assert_eq!(search_filters(&request).unwrap(), "downloads > 10");
+    }
+}
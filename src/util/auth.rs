@@ -16,6 +16,8 @@ pub enum AuthenticationError {
     GithubError(#[from] reqwest::Error),
     #[error("Invalid Authentication Credentials")]
     InvalidCredentialsError,
+    #[error("You don't have the role required for this action")]
+    MissingRole,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -77,13 +79,17 @@ pub async fn get_user_from_headers<'a, 'b, E>(
 where
     E: sqlx::Executor<'a, Database = sqlx::Postgres>,
 {
-    let token = headers
+    let token = get_token_from_headers(headers)?;
+
+    Ok(get_user_from_token(token, executor).await?)
+}
+
+fn get_token_from_headers(headers: &HeaderMap) -> Result<&str, AuthenticationError> {
+    headers
         .get("Authorization")
         .ok_or(AuthenticationError::InvalidCredentialsError)?
         .to_str()
-        .map_err(|_| AuthenticationError::InvalidCredentialsError)?;
-
-    Ok(get_user_from_token(token, executor).await?)
+        .map_err(|_| AuthenticationError::InvalidCredentialsError)
 }
 
 pub async fn check_is_moderator_from_headers<'a, 'b, E>(
@@ -95,10 +101,16 @@ where
 {
     let user = get_user_from_headers(headers, executor).await?;
 
+    require_moderator(user)
+}
+
+/// Checks that a signed in user is a moderator. Their credentials are valid, so this fails
+/// with `MissingRole` (a 403) rather than `InvalidCredentialsError` (a 401).
+fn require_moderator(user: User) -> Result<User, AuthenticationError> {
     if user.role.is_mod() {
         Ok(user)
     } else {
-        Err(AuthenticationError::InvalidCredentialsError)
+        Err(AuthenticationError::MissingRole)
     }
 }
 
@@ -111,8 +123,66 @@ where
 {
     let user = get_user_from_headers(headers, executor).await?;
 
+    require_admin(user)
+}
+
+/// Checks that a signed in user is an admin, failing with `MissingRole` like
+/// `require_moderator`
+fn require_admin(user: User) -> Result<User, AuthenticationError> {
     match user.role {
         Role::Admin => Ok(user),
-        _ => Err(AuthenticationError::InvalidCredentialsError),
+        _ => Err(AuthenticationError::MissingRole),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::ApiError;
+    use actix_web::http::{HeaderValue, StatusCode};
+    use actix_web::ResponseError;
+
+    fn user(role: Role) -> User {
+        User {
+            id: UserId(1),
+            github_id: None,
+            username: "user".to_string(),
+            name: None,
+            email: None,
+            avatar_url: None,
+            bio: None,
+            created: chrono::Utc::now(),
+            role,
+        }
+    }
+
+    fn status(error: AuthenticationError) -> StatusCode {
+        ApiError::from(error).status_code()
+    }
+
+    #[test]
+    fn anonymous_requests_are_unauthorized() {
+        let error = get_token_from_headers(&HeaderMap::new()).unwrap_err();
+        assert_eq!(status(error), StatusCode::UNAUTHORIZED);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            actix_web::http::header::AUTHORIZATION,
+            HeaderValue::from_static("token"),
+        );
+        assert_eq!(get_token_from_headers(&headers).unwrap(), "token");
+    }
+
+    #[test]
+    fn users_without_the_role_are_forbidden() {
+        let error = require_moderator(user(Role::Developer)).unwrap_err();
+        assert_eq!(status(error), StatusCode::FORBIDDEN);
+
+        let error = require_admin(user(Role::Moderator)).unwrap_err();
+        assert_eq!(status(error), StatusCode::FORBIDDEN);
+
+        assert!(require_moderator(user(Role::Moderator)).is_ok());
+        assert!(require_moderator(user(Role::Admin)).is_ok());
+        assert!(require_admin(user(Role::Admin)).is_ok());
     }
 }
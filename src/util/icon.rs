@@ -0,0 +1,136 @@
+use image::io::Reader;
+use image::ImageOutputFormat;
+use std::io::Cursor;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum IconError {
+    #[error("The icon could not be read as an image")]
+    Malformed,
+    #[error("Icons must be at most {0}x{0} pixels")]
+    TooLarge(u32),
+    #[error("Error while re-encoding the icon: {0}")]
+    Encoding(#[from] image::ImageError),
+}
+
+/// An uploaded icon which passed validation, ready to be sent to the file host
+pub struct ProcessedIcon {
+    pub data: Vec<u8>,
+    pub extension: String,
+    pub content_type: &'static str,
+}
+
+/// The largest width or height an icon may have, from `ICON_MAX_DIMENSION`
+fn max_dimension() -> u32 {
+    dotenv::var("ICON_MAX_DIMENSION")
+        .ok()
+        .and_then(|x| x.parse::<u32>().ok())
+        .unwrap_or(1024)
+}
+
+/// Checks that an uploaded icon can be decoded and that neither of its dimensions exceeds
+/// `ICON_MAX_DIMENSION`, re-encoding it to a PNG when `ICON_REENCODE` is enabled. SVGs
+/// can't be decoded as raster images, so they are only subject to the upload size limit.
+pub fn process_icon(
+    data: Vec<u8>,
+    extension: &str,
+    content_type: &'static str,
+) -> Result<ProcessedIcon, IconError> {
+    if content_type == "image/svg+xml" {
+        return Ok(ProcessedIcon {
+            data,
+            extension: extension.to_string(),
+            content_type,
+        });
+    }
+
+    let max_dimension = max_dimension();
+
+    // The dimensions are read from the header first, so that enormous images are
+    // rejected before being decoded in full
+    let (width, height) = Reader::new(Cursor::new(&data))
+        .with_guessed_format()
+        .map_err(|_| IconError::Malformed)?
+        .into_dimensions()
+        .map_err(|_| IconError::Malformed)?;
+
+    if width > max_dimension || height > max_dimension {
+        return Err(IconError::TooLarge(max_dimension));
+    }
+
+    let image = image::load_from_memory(&data).map_err(|_| IconError::Malformed)?;
+
+    let reencode = dotenv::var("ICON_REENCODE")
+        .ok()
+        .and_then(|x| x.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    if reencode {
+        let mut png = Vec::new();
+        image.write_to(&mut png, ImageOutputFormat::Png)?;
+
+        return Ok(ProcessedIcon {
+            data: png,
+            extension: "png".to_string(),
+            content_type: "image/png",
+        });
+    }
+
+    Ok(ProcessedIcon {
+        data,
+        extension: extension.to_string(),
+        content_type,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(width, height))
+            .write_to(&mut data, ImageOutputFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn accepts_valid_icons() {
+        let icon = process_icon(png(64, 64), "png", "image/png").unwrap();
+
+        assert_eq!(icon.content_type, "image/png");
+        assert_eq!(icon.extension, "png");
+        assert!(image::load_from_memory(&icon.data).is_ok());
+    }
+
+    #[test]
+    fn rejects_oversized_icons() {
+        let max_dimension = max_dimension();
+
+        assert!(matches!(
+            process_icon(png(max_dimension + 1, 1), "png", "image/png"),
+            Err(IconError::TooLarge(x)) if x == max_dimension
+        ));
+        assert!(matches!(
+            process_icon(png(1, max_dimension + 1), "png", "image/png"),
+            Err(IconError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_corrupt_icons() {
+        let mut truncated = png(64, 64);
+        truncated.truncate(truncated.len() / 2);
+
+        assert!(matches!(
+            process_icon(truncated, "png", "image/png"),
+            Err(IconError::Malformed)
+        ));
+        assert!(matches!(
+            process_icon(b"not an image".to_vec(), "png", "image/png"),
+            Err(IconError::Malformed)
+        ));
+    }
+}
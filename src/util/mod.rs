@@ -1,4 +1,9 @@
 pub mod auth;
 pub mod ext;
+pub mod icon;
+pub mod pagination;
+pub mod ratelimit;
+pub mod sanitize;
+pub mod time;
 pub mod validate;
 pub mod webhook;
@@ -0,0 +1,62 @@
+/// Page size limits shared by all paginated list endpoints
+#[derive(Clone)]
+pub struct PaginationConfig {
+    pub default_page_size: usize,
+    pub max_page_size: usize,
+}
+
+impl PaginationConfig {
+    pub fn from_env() -> Self {
+        let default_page_size = dotenv::var("DEFAULT_PAGE_SIZE")
+            .ok()
+            .and_then(|x| x.parse::<usize>().ok())
+            .unwrap_or(10);
+        let max_page_size = dotenv::var("MAX_PAGE_SIZE")
+            .ok()
+            .and_then(|x| x.parse::<usize>().ok())
+            .unwrap_or(100);
+
+        Self {
+            default_page_size,
+            max_page_size,
+        }
+    }
+
+    /// Gets the page size to use for a request, clamping it to the maximum
+    pub fn limit(&self, requested: Option<usize>) -> usize {
+        self.limit_or(requested, self.default_page_size)
+    }
+
+    /// Like `limit`, but with an endpoint specific default page size for
+    /// routes that historically returned more than the global default
+    pub fn limit_or(&self, requested: Option<usize>, default: usize) -> usize {
+        std::cmp::min(requested.unwrap_or(default), self.max_page_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaginationConfig;
+
+    fn config() -> PaginationConfig {
+        PaginationConfig {
+            default_page_size: 10,
+            max_page_size: 100,
+        }
+    }
+
+    #[test]
+    fn uses_the_default_page_size() {
+        assert_eq!(config().limit(None), 10);
+        assert_eq!(config().limit_or(None, 50), 50);
+    }
+
+    #[test]
+    fn clamps_limits_over_the_maximum() {
+        assert_eq!(config().limit(Some(20)), 20);
+        assert_eq!(config().limit(Some(100)), 100);
+        assert_eq!(config().limit(Some(101)), 100);
+        assert_eq!(config().limit_or(Some(1000), 50), 100);
+        assert_eq!(config().limit_or(None, 1000), 100);
+    }
+}
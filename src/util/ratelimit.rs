@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// A per-client limit on downloads, counted over fixed one minute windows. This is kept
+/// separate from the download tracking, so deduplicated downloads still count towards it.
+pub struct DownloadLimiter {
+    pub max_per_minute: u32,
+    /// IPs which aren't limited, such as the frontend's, from `RATE_LIMIT_IGNORE_IPS`
+    pub ignore_ips: Vec<String>,
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl DownloadLimiter {
+    pub fn from_env() -> Self {
+        let max_per_minute = dotenv::var("DOWNLOAD_RATE_LIMIT")
+            .ok()
+            .and_then(|x| x.parse::<u32>().ok())
+            .unwrap_or(60);
+
+        let ignore_ips = dotenv::var("RATE_LIMIT_IGNORE_IPS")
+            .ok()
+            .and_then(|s| serde_json::from_str::<Vec<String>>(&s).ok())
+            .unwrap_or_default();
+
+        Self::new(max_per_minute, ignore_ips)
+    }
+
+    pub fn new(max_per_minute: u32, ignore_ips: Vec<String>) -> Self {
+        Self {
+            max_per_minute,
+            ignore_ips,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether downloads from the given IP are exempt from the limit
+    pub fn is_ignored(&self, ip: &str) -> bool {
+        self.ignore_ips.iter().any(|x| x == ip)
+    }
+
+    /// Counts a number of downloads from the given client, returning whether they are all
+    /// within the limit
+    pub fn check(&self, client: &str, downloads: u32) -> bool {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+
+        let window = windows.entry(client.to_string()).or_insert((now, 0));
+
+        if now.duration_since(window.0) >= WINDOW {
+            *window = (now, 0);
+        }
+
+        window.1 = window.1.saturating_add(downloads);
+        window.1 <= self.max_per_minute
+    }
+
+    /// Forgets every client whose window has ended
+    pub fn prune(&self) {
+        let now = Instant::now();
+
+        self.windows
+            .lock()
+            .unwrap()
+            .retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DownloadLimiter;
+
+    #[test]
+    fn limits_each_client_separately() {
+        let limiter = DownloadLimiter::new(2, Vec::new());
+
+        assert!(limiter.check("a", 1));
+        assert!(limiter.check("a", 1));
+        assert!(!limiter.check("a", 1));
+        assert!(limiter.check("b", 1));
+    }
+
+    #[test]
+    fn counts_every_download_of_a_batch() {
+        let limiter = DownloadLimiter::new(5, Vec::new());
+
+        assert!(!limiter.check("a", 6));
+        assert!(!limiter.check("a", u32::MAX));
+    }
+
+    #[test]
+    fn ignores_configured_ips() {
+        let limiter = DownloadLimiter::new(5, vec!["127.0.0.1".to_string()]);
+
+        assert!(limiter.is_ignored("127.0.0.1"));
+        assert!(!limiter.is_ignored("127.0.0.2"));
+    }
+}
@@ -0,0 +1,311 @@
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// How many times a document is sanitized before giving up on keeping it as markdown
+const MAX_PASSES: usize = 8;
+
+/// The HTML which is allowed in markdown documents
+fn html_sanitizer() -> ammonia::Builder<'static> {
+    let mut builder = ammonia::Builder::default();
+
+    builder.url_schemes(
+        ["http", "https", "mailto"]
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>(),
+    );
+
+    builder
+}
+
+fn markdown_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+/// Sanitizes a markdown document. Raw HTML is cleaned with an allowlist of safe tags and
+/// attributes, which removes `<script>` elements, event handlers and `javascript:` links,
+/// and markdown links or images to unsafe schemes are replaced by their text. The rest of
+/// the document, including code, is kept exactly as it was written. HTML is cleaned one
+/// block at a time, so an element left open at the end of a block is closed there.
+pub fn sanitize_markdown(markdown: &str) -> String {
+    let mut markdown = markdown.to_string();
+
+    // Removing markup can change how the text around it is parsed, so the document is
+    // sanitized again until nothing changes
+    for _ in 0..MAX_PASSES {
+        let sanitized = sanitize_pass(&markdown);
+
+        if sanitized == markdown {
+            return sanitized;
+        }
+
+        markdown = sanitized;
+    }
+
+    // Documents which keep changing are rendered, which is always safe to store since
+    // markdown allows HTML
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, Parser::new_ext(&markdown, markdown_options()));
+
+    html_sanitizer().clean(&html).to_string()
+}
+
+/// The most characters a project body can have once it has been sanitized
+pub const MAX_BODY_LENGTH: usize = 65536;
+
+/// Sanitizes a project body, returning `None` if it's longer than `MAX_BODY_LENGTH`
+/// afterwards. Cleaning HTML escapes the characters it keeps, so a body can grow past
+/// the limit it was validated against.
+pub fn sanitize_body(body: &str) -> Option<String> {
+    let body = sanitize_markdown(body);
+
+    if body.chars().count() > MAX_BODY_LENGTH {
+        None
+    } else {
+        Some(body)
+    }
+}
+
+/// A link or image which is being parsed, with the range of its text
+struct OpenLink {
+    range: Range<usize>,
+    safe: bool,
+    text: Option<Range<usize>>,
+}
+
+fn sanitize_pass(markdown: &str) -> String {
+    let parser = Parser::new_ext(markdown, markdown_options()).into_offset_iter();
+
+    // Link reference definitions aren't events, so unsafe ones are removed here
+    let mut edits = parser
+        .reference_definitions()
+        .iter()
+        .filter(|(_, definition)| !is_safe_url(&definition.dest))
+        .map(|(_, definition)| (definition.span.clone(), String::new()))
+        .collect::<Vec<_>>();
+
+    let mut html: Option<Range<usize>> = None;
+    let mut links: Vec<OpenLink> = Vec::new();
+    let mut in_code_block = false;
+
+    for (event, range) in parser {
+        // Adjacent pieces of raw HTML are cleaned together, so that elements such as
+        // `<a><img></a>` are kept whole
+        match (&event, html.as_mut()) {
+            (Event::Html(_), Some(html)) if html.end == range.start => {
+                html.end = range.end;
+                continue;
+            }
+            _ => {
+                if let Some(html) = html.take() {
+                    let clean = html_sanitizer().clean(&markdown[html.clone()]).to_string();
+                    edits.push((html, clean));
+                }
+            }
+        }
+
+        match event {
+            Event::Start(Tag::Link(_, destination, _))
+            | Event::Start(Tag::Image(_, destination, _)) => {
+                extend_link_text(&mut links, &range);
+
+                links.push(OpenLink {
+                    range,
+                    safe: is_safe_url(&destination),
+                    text: None,
+                });
+            }
+            Event::End(Tag::Link(..)) | Event::End(Tag::Image(..)) => {
+                if let Some(link) = links.pop() {
+                    if !link.safe {
+                        match link.text {
+                            Some(text) => {
+                                edits.push((link.range.start..text.start, String::new()));
+                                edits.push((text.end..link.range.end, String::new()));
+                            }
+                            None => edits.push((link.range, String::new())),
+                        }
+                    }
+                }
+            }
+            event => {
+                extend_link_text(&mut links, &range);
+
+                match event {
+                    Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+                    Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+                    Event::Html(_) => html = Some(range),
+                    // A `<` which doesn't start valid HTML is text, but renderers which
+                    // are more lenient about HTML could still read a tag into it
+                    Event::Text(text) if !in_code_block && &*text == "<" && range.len() == 1 => {
+                        edits.push((range, "&lt;".to_string()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if let Some(html) = html {
+        let clean = html_sanitizer().clean(&markdown[html.clone()]).to_string();
+        edits.push((html, clean));
+    }
+
+    edits.sort_by_key(|(range, _)| range.start);
+
+    let mut output = String::with_capacity(markdown.len());
+    let mut position = 0;
+
+    for (range, replacement) in edits {
+        output.push_str(&markdown[position..range.start]);
+        output.push_str(&replacement);
+        position = range.end;
+    }
+
+    output.push_str(&markdown[position..]);
+
+    output
+}
+
+/// Adds the range of an element inside a link to the link's text
+fn extend_link_text(links: &mut [OpenLink], range: &Range<usize>) {
+    if let Some(link) = links.last_mut() {
+        link.text = Some(match link.text.take() {
+            Some(text) => text.start.min(range.start)..text.end.max(range.end),
+            None => range.clone(),
+        });
+    }
+}
+
+/// Checks whether a link destination is allowed by the HTML sanitizer
+fn is_safe_url(url: &str) -> bool {
+    if url.is_empty() {
+        return true;
+    }
+
+    let link = format!(
+        r#"<a href="{}">"#,
+        url.replace('&', "&amp;").replace('"', "&quot;")
+    );
+
+    html_sanitizer().clean(&link).to_string().contains("href=")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_scripts() {
+        let output = sanitize_markdown("Hello <script>alert(1)</script>world");
+
+        assert!(!output.contains("<script"));
+        assert!(output.contains("Hello"));
+        assert!(output.contains("world"));
+
+        let output = sanitize_markdown("Hello\n\n<script>\nalert(1)\n</script>\n\nworld");
+
+        assert!(!output.contains("script"));
+        assert!(!output.contains("alert"));
+        assert!(output.contains("Hello"));
+        assert!(output.contains("world"));
+    }
+
+    #[test]
+    fn keeps_markdown() {
+        let markdown = "# Title\n\n> A quote with **bold** &amp; *italics*\n\n- [Link](https://modrinth.com \"title\")\n- ![Image](https://cdn.modrinth.com/icon.png)\n- [Relative](/mod/sodium) and <https://modrinth.com>\n\n| a | b |\n|---|---|\n| 1 < 2 | 3 > 2 |";
+
+        let output = sanitize_markdown(markdown);
+
+        assert_eq!(output, markdown.replace("1 < 2", "1 &lt; 2"));
+    }
+
+    #[test]
+    fn keeps_safe_html() {
+        let markdown = "<p>\n<a href=\"https://modrinth.com\"><img src=\"https://cdn.modrinth.com/icon.png\"></a>\n</p>\n\n<details>\n<summary>More</summary>\nText\n</details>";
+
+        let output = sanitize_markdown(markdown);
+
+        assert_eq!(
+            output,
+            markdown.replace(
+                "href=\"https://modrinth.com\"",
+                "href=\"https://modrinth.com\" rel=\"noopener noreferrer\""
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_code() {
+        let markdown =
+            "Use `<script src=\"x\"></script>` here\n\n```html\n<script src=\"mod.js\"></script>\n```\n\n    <img src=x onerror=alert(1)";
+
+        assert_eq!(sanitize_markdown(markdown), markdown);
+    }
+
+    #[test]
+    fn strips_event_handlers() {
+        let output = sanitize_markdown(r#"<a/onmouseover="alert(1)">x</a>"#);
+
+        assert!(!output.contains("<a"));
+    }
+
+    #[test]
+    fn strips_encoded_javascript_links() {
+        let output = sanitize_markdown(r#"<a href="jav&#x61;script:alert(1)">x</a>"#);
+
+        assert!(!output.contains("alert"));
+    }
+
+    #[test]
+    fn strips_unclosed_tags() {
+        let output = sanitize_markdown("<img src=x onerror=alert(1)\n\ntext");
+
+        assert!(!output.contains("<img"));
+    }
+
+    #[test]
+    fn strips_javascript_markdown_links() {
+        let output = sanitize_markdown(
+            "[x](javascript:alert(1)) [y](jav&#x61;script:alert(2)) [z](javascript\\:alert(3)) ![i](javascript:alert(4)) <javascript:alert(5)> [w]\n\n[w]: javascript:alert(6)",
+        );
+
+        assert!(destinations(&output).is_empty());
+        assert!(!output.contains("[w]:"));
+        assert!(output.contains("x y z i"));
+    }
+
+    #[test]
+    fn strips_links_formed_by_removing_others() {
+        let output = sanitize_markdown("[[y]](javascript:a)(javascript:alert(1))\n\n[y]: #");
+
+        assert!(destinations(&output)
+            .iter()
+            .all(|x| !x.contains("javascript")));
+    }
+
+    #[test]
+    fn bodies_are_limited_after_sanitizing() {
+        let body = "a".repeat(MAX_BODY_LENGTH);
+        assert_eq!(sanitize_body(&body), Some(body));
+
+        // Every `>` is escaped to `&gt;`, which makes the body four times longer
+        let body = format!("<div>{}</div>", ">".repeat(MAX_BODY_LENGTH / 2));
+        assert!(body.len() <= MAX_BODY_LENGTH);
+        assert_eq!(sanitize_body(&body), None);
+    }
+
+    fn destinations(markdown: &str) -> Vec<String> {
+        Parser::new_ext(markdown, markdown_options())
+            .filter_map(|event| match event {
+                Event::Start(Tag::Link(_, destination, _))
+                | Event::Start(Tag::Image(_, destination, _)) => Some(destination.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TimestampError {
+    #[error("Invalid timestamp `{0}`, expected an RFC 3339 date such as `2021-01-01T00:00:00Z`")]
+    Malformed(String),
+    #[error("The end of the time range is before its start")]
+    InvertedRange,
+}
+
+/// Parses an RFC 3339 timestamp given as user input, such as a query parameter
+pub fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, TimestampError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|x| x.with_timezone(&Utc))
+        .map_err(|_| TimestampError::Malformed(value.to_string()))
+}
+
+/// Serializes timestamps as RFC 3339 in UTC with millisecond precision, so that every
+/// endpoint returns dates in the same format. Use with `#[serde(with = "...")]`.
+pub mod rfc3339 {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&date.to_rfc3339_opts(SecondsFormat::Millis, true))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        super::parse_timestamp(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The parsed bounds of a `TimestampRange`, either of which may be open
+pub type TimestampBounds = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// An optional range of time, taken from the `start` and `end` query parameters
+#[derive(Deserialize)]
+pub struct TimestampRange {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+impl TimestampRange {
+    /// Parses both ends of the range, failing if either is malformed or if the range
+    /// ends before it starts
+    pub fn parse(&self) -> Result<TimestampBounds, TimestampError> {
+        let start = self.start.as_deref().map(parse_timestamp).transpose()?;
+        let end = self.end.as_deref().map(parse_timestamp).transpose()?;
+
+        if let (Some(start), Some(end)) = (start, end) {
+            if end < start {
+                return Err(TimestampError::InvertedRange);
+            }
+        }
+
+        Ok((start, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: Option<&str>, end: Option<&str>) -> TimestampRange {
+        TimestampRange {
+            start: start.map(|x| x.to_string()),
+            end: end.map(|x| x.to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_open_and_closed_ranges() {
+        let (start, end) = range(Some("2021-01-01T00:00:00Z"), None).parse().unwrap();
+
+        assert_eq!(start.unwrap().to_rfc3339(), "2021-01-01T00:00:00+00:00");
+        assert!(end.is_none());
+
+        let (start, end) = range(
+            Some("2021-01-01T00:00:00+02:00"),
+            Some("2021-01-02T00:00:00Z"),
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(start.unwrap().to_rfc3339(), "2020-12-31T22:00:00+00:00");
+        assert_eq!(end.unwrap().to_rfc3339(), "2021-01-02T00:00:00+00:00");
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        assert!(matches!(
+            range(Some("yesterday"), None).parse(),
+            Err(TimestampError::Malformed(x)) if x == "yesterday"
+        ));
+    }
+
+    #[test]
+    fn rejects_inverted_ranges() {
+        assert!(matches!(
+            range(Some("2021-01-02T00:00:00Z"), Some("2021-01-01T00:00:00Z")).parse(),
+            Err(TimestampError::InvertedRange)
+        ));
+    }
+}
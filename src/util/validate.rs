@@ -1,6 +1,10 @@
+use crate::models::error::FieldError;
+use crate::models::projects::MAX_CATEGORIES;
+use crate::models::users::Role;
 use lazy_static::lazy_static;
 use regex::Regex;
-use validator::{ValidationErrors, ValidationErrorsKind};
+use std::collections::HashMap;
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
 
 lazy_static! {
     pub static ref RE_URL_SAFE: Regex = Regex::new(r#"^[a-zA-Z0-9!@$()`.+,_"-]*$"#).unwrap();
@@ -35,12 +39,15 @@ pub fn validation_errors_to_string(errors: ValidationErrors, adder: Option<Strin
                         if let Some(adder) = adder {
                             output.push_str(&*format!(
                                 "Field {} {} failed validation with error {}",
-                                field, adder, error.code
+                                field,
+                                adder,
+                                describe_error(error)
                             ));
                         } else {
                             output.push_str(&*format!(
                                 "Field {} failed validation with error {}",
-                                field, error.code
+                                field,
+                                describe_error(error)
                             ));
                         }
                     }
@@ -53,3 +60,317 @@ pub fn validation_errors_to_string(errors: ValidationErrors, adder: Option<Strin
 
     "".to_string()
 }
+
+/// Checks that a project of the given type is in at most `max_categories` categories,
+/// returning the reason it was rejected otherwise
+pub fn check_category_count(
+    project_type: &str,
+    categories: usize,
+    max_categories: usize,
+) -> Option<String> {
+    if categories > max_categories {
+        Some(format!(
+            "A {} can be in at most {} categories",
+            project_type, max_categories
+        ))
+    } else {
+        None
+    }
+}
+
+/// Lists every field which failed validation, including those of nested structs and lists
+pub fn validation_errors_to_fields(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut fields = Vec::new();
+    collect_field_errors(errors, "", &mut fields);
+
+    fields
+}
+
+fn collect_field_errors(errors: &ValidationErrors, prefix: &str, fields: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = format!("{}{}", prefix, field);
+
+        match kind {
+            ValidationErrorsKind::Struct(errors) => {
+                collect_field_errors(errors, &format!("{}.", path), fields)
+            }
+            ValidationErrorsKind::List(list) => {
+                for (index, errors) in list {
+                    collect_field_errors(errors, &format!("{}[{}].", path, index), fields)
+                }
+            }
+            ValidationErrorsKind::Field(errors) => fields.extend(errors.iter().map(|error| {
+                FieldError {
+                    field: path.clone(),
+                    code: error.code.to_string(),
+                    message: error
+                        .message
+                        .as_ref()
+                        .map(|x| x.to_string())
+                        .unwrap_or_else(|| describe_error(error)),
+                }
+            })),
+        }
+    }
+}
+
+/// Describes a single validation error, including the allowed bounds for length errors so
+/// every field reports the limits it was actually validated against
+fn describe_error(error: &ValidationError) -> String {
+    if error.code == "length" {
+        let min = error.params.get("min");
+        let max = error.params.get("max");
+
+        match (min, max) {
+            (Some(min), Some(max)) => {
+                return format!("length, must be between {} and {}", min, max)
+            }
+            (Some(min), None) => return format!("length, must be at least {}", min),
+            (None, Some(max)) => return format!("length, must be at most {}", max),
+            (None, None) => {}
+        }
+    }
+
+    error.code.to_string()
+}
+
+/// Gets the maximum number of categories a project of the given type can be in. Limits
+/// can be set per project type with `MAX_CATEGORIES_BY_PROJECT_TYPE`, a json object such
+/// as `{"modpack": 5}`, and otherwise default to `MAX_CATEGORIES`.
+pub fn max_categories(project_type: &str) -> usize {
+    parse_max_categories(
+        dotenv::var("MAX_CATEGORIES_BY_PROJECT_TYPE")
+            .ok()
+            .as_deref(),
+        project_type,
+    )
+}
+
+/// Looks up the limit of a project type in the limits given as `MAX_CATEGORIES_BY_PROJECT_TYPE`
+fn parse_max_categories(limits: Option<&str>, project_type: &str) -> usize {
+    limits
+        .and_then(|x| serde_json::from_str::<HashMap<String, usize>>(x).ok())
+        .and_then(|x| x.get(project_type).copied())
+        .unwrap_or(MAX_CATEGORIES)
+}
+
+/// Checks that external links use HTTPS when `https_only` is set, returning the reason the
+/// first offending link was rejected
+pub fn check_https_urls<'a>(
+    https_only: bool,
+    urls: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    if !https_only {
+        return None;
+    }
+
+    urls.into_iter()
+        .find(|url| !url.to_lowercase().starts_with("https://"))
+        .map(|url| format!("The link {} must use HTTPS.", url))
+}
+
+/// Optional checks on the projects users create and edit, which are disabled by default
+#[derive(Clone)]
+pub struct ProjectCheckConfig {
+    /// Whether descriptions which are empty, only a link or too short are rejected
+    pub description_quality_checks: bool,
+    /// The fewest words a description can have when the quality checks are enabled
+    pub description_min_words: usize,
+    /// Whether external links other than HTTPS ones are rejected
+    pub https_only_urls: bool,
+}
+
+impl ProjectCheckConfig {
+    pub fn from_env() -> Self {
+        let description_quality_checks = dotenv::var("DESCRIPTION_QUALITY_CHECKS")
+            .ok()
+            .and_then(|x| x.parse::<bool>().ok())
+            .unwrap_or(false);
+        let description_min_words = dotenv::var("DESCRIPTION_MIN_WORDS")
+            .ok()
+            .and_then(|x| x.parse::<usize>().ok())
+            .unwrap_or(0);
+        let https_only_urls = dotenv::var("HTTPS_ONLY_URLS")
+            .ok()
+            .and_then(|x| x.parse::<bool>().ok())
+            .unwrap_or(false);
+
+        Self {
+            description_quality_checks,
+            description_min_words,
+            https_only_urls,
+        }
+    }
+}
+
+/// Checks a project description against the quality checks in `config`, returning the
+/// reason it was rejected. Descriptions written by moderators are never checked.
+pub fn check_description_quality(
+    config: &ProjectCheckConfig,
+    role: &Role,
+    description: &str,
+) -> Option<String> {
+    if !config.description_quality_checks || role.is_mod() {
+        return None;
+    }
+
+    let description = description.trim();
+
+    if description.is_empty() {
+        return Some("The description must not be empty.".to_string());
+    }
+
+    let words = description.split_whitespace().collect::<Vec<_>>();
+
+    if words.len() == 1 && validator::validate_url(words[0]) {
+        return Some("The description must not only be a link.".to_string());
+    }
+
+    if words.len() < config.description_min_words {
+        return Some(format!(
+            "The description must contain at least {} words.",
+            config.description_min_words
+        ));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[derive(Validate)]
+    struct Item {
+        #[validate(length(min = 3, max = 64))]
+        title: String,
+    }
+
+    #[derive(Validate)]
+    struct Form {
+        #[validate(length(max = 4))]
+        name: String,
+        #[validate]
+        items: Vec<Item>,
+    }
+
+    #[test]
+    fn field_errors_include_nested_paths_and_bounds() {
+        let form = Form {
+            name: "too long".to_string(),
+            items: vec![
+                Item {
+                    title: "valid".to_string(),
+                },
+                Item {
+                    title: "x".to_string(),
+                },
+            ],
+        };
+
+        let mut fields = validation_errors_to_fields(&form.validate().unwrap_err());
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+
+        assert_eq!(fields.len(), 2);
+
+        assert_eq!(fields[0].field, "items[1].title");
+        assert_eq!(fields[0].code, "length");
+        assert_eq!(fields[0].message, "length, must be between 3 and 64");
+
+        assert_eq!(fields[1].field, "name");
+        assert_eq!(fields[1].message, "length, must be at most 4");
+    }
+
+    #[test]
+    fn allows_exactly_the_maximum_number_of_categories() {
+        assert_eq!(check_category_count("mod", 0, 3), None);
+        assert_eq!(check_category_count("mod", 3, 3), None);
+        assert_eq!(
+            check_category_count("mod", 4, 3),
+            Some("A mod can be in at most 3 categories".to_string())
+        );
+        assert_eq!(
+            check_category_count("modpack", 6, 5),
+            Some("A modpack can be in at most 5 categories".to_string())
+        );
+    }
+
+    #[test]
+    fn category_limits_depend_on_the_project_type() {
+        let limits = Some(r#"{"modpack": 5}"#);
+
+        let modpack = parse_max_categories(limits, "modpack");
+        let project_mod = parse_max_categories(limits, "mod");
+        assert_eq!(modpack, 5);
+        assert_eq!(project_mod, MAX_CATEGORIES);
+
+        assert_eq!(check_category_count("modpack", 5, modpack), None);
+        assert!(check_category_count("mod", 5, project_mod).is_some());
+    }
+
+    #[test]
+    fn category_limits_default_to_the_global_maximum() {
+        assert_eq!(parse_max_categories(None, "mod"), MAX_CATEGORIES);
+        assert_eq!(parse_max_categories(Some("{}"), "mod"), MAX_CATEGORIES);
+        assert_eq!(
+            parse_max_categories(Some("not json"), "modpack"),
+            MAX_CATEGORIES
+        );
+    }
+
+    fn checks(min_words: usize) -> ProjectCheckConfig {
+        ProjectCheckConfig {
+            description_quality_checks: true,
+            description_min_words: min_words,
+            https_only_urls: false,
+        }
+    }
+
+    #[test]
+    fn whitespace_descriptions_are_rejected() {
+        assert!(check_description_quality(&checks(0), &Role::Developer, " \n\t ").is_some());
+        assert!(check_description_quality(&checks(0), &Role::Developer, "").is_some());
+    }
+
+    #[test]
+    fn link_only_descriptions_are_rejected() {
+        assert!(check_description_quality(
+            &checks(0),
+            &Role::Developer,
+            " https://example.com/download "
+        )
+        .is_some());
+        assert!(check_description_quality(
+            &checks(0),
+            &Role::Developer,
+            "Download at https://example.com/download"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn short_descriptions_are_rejected() {
+        assert!(check_description_quality(&checks(3), &Role::Developer, "A mod").is_some());
+        assert!(check_description_quality(&checks(3), &Role::Developer, "A small mod").is_none());
+    }
+
+    #[test]
+    fn moderators_are_exempt_from_description_checks() {
+        assert!(check_description_quality(&checks(3), &Role::Moderator, "").is_none());
+        assert!(
+            check_description_quality(&checks(3), &Role::Admin, "https://example.com").is_none()
+        );
+    }
+
+    #[test]
+    fn description_checks_are_disabled_by_default() {
+        let config = ProjectCheckConfig {
+            description_quality_checks: false,
+            description_min_words: 3,
+            https_only_urls: false,
+        };
+
+        assert!(check_description_quality(&config, &Role::Developer, "").is_none());
+    }
+}
@@ -1,6 +1,7 @@
-use crate::models::projects::Project;
+use crate::models::projects::{Project, ProjectStatus};
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use std::time::Duration;
 
 #[derive(Serialize)]
 struct DiscordEmbed {
@@ -33,6 +34,7 @@ struct DiscordWebhook {
 pub async fn send_discord_webhook(
     project: Project,
     webhook_url: String,
+    client: &reqwest::Client,
 ) -> Result<(), reqwest::Error> {
     let mut fields = vec![
         DiscordEmbedField {
@@ -89,15 +91,191 @@ pub async fn send_discord_webhook(
         },
     };
 
-    let client = reqwest::Client::new();
-
     client
         .post(&webhook_url)
         .json(&DiscordWebhook {
             embeds: vec![embed],
         })
         .send()
-        .await?;
+        .await?
+        .error_for_status()?;
 
     Ok(())
 }
+
+/// Where and how the moderation webhook is sent
+#[derive(Clone)]
+pub struct WebhookConfig {
+    /// The webhook notified about projects entering the review queue, set with
+    /// `MODERATION_DISCORD_WEBHOOK`
+    pub moderation_url: Option<String>,
+    pub client: reqwest::Client,
+    /// How many times sending the webhook is retried before giving up
+    pub retries: u32,
+}
+
+impl WebhookConfig {
+    pub fn from_env() -> Self {
+        let moderation_url = dotenv::var("MODERATION_DISCORD_WEBHOOK")
+            .ok()
+            .filter(|x| !x.is_empty());
+
+        Self {
+            moderation_url,
+            client: reqwest::Client::new(),
+            retries: 3,
+        }
+    }
+}
+
+/// Notifies the moderation webhook that a project has entered the review queue, if one
+/// is configured. This is sent in the background, retrying with an increasing delay, so
+/// that a slow or failing webhook doesn't hold up or fail the request.
+pub fn send_moderation_webhook(config: &WebhookConfig, project: Project) {
+    if let Some(delivery) = moderation_webhook_delivery(config, project) {
+        actix_rt::spawn(delivery);
+    }
+}
+
+/// Builds the task sending the moderation webhook for a project, unless no webhook is
+/// configured or the project isn't waiting for review
+fn moderation_webhook_delivery(
+    config: &WebhookConfig,
+    project: Project,
+) -> Option<impl std::future::Future<Output = ()>> {
+    if project.status != ProjectStatus::Processing {
+        return None;
+    }
+
+    let webhook_url = config.moderation_url.clone()?;
+    let client = config.client.clone();
+    let retries = config.retries;
+
+    Some(async move {
+        let mut attempt = 0;
+
+        loop {
+            match send_discord_webhook(project.clone(), webhook_url.clone(), &client).await {
+                Ok(()) => return,
+                Err(err) if attempt >= retries => {
+                    log::warn!("Failed to send moderation webhook: {}", err);
+                    return;
+                }
+                Err(_) => {
+                    attempt += 1;
+                    actix_rt::time::delay_for(Duration::from_secs(1 << attempt)).await;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::project_item::QueryProject;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    fn project(status: ProjectStatus) -> Project {
+        let mut project = crate::routes::projects::convert_project(QueryProject::example(1000));
+        project.status = status;
+        project
+    }
+
+    fn config(moderation_url: Option<String>) -> WebhookConfig {
+        WebhookConfig {
+            moderation_url,
+            client: reqwest::Client::new(),
+            retries: 0,
+        }
+    }
+
+    /// Starts a server answering a single request with `status`, returning its url and
+    /// the request it received
+    fn webhook_server(status: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+
+            // Reads the headers and then the rest of the body
+            while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let text = String::from_utf8_lossy(&request).to_string();
+            let (headers, body) = text.split_at(text.find("\r\n\r\n").unwrap() + 4);
+            let length = headers
+                .lines()
+                .find_map(|x| {
+                    x.to_lowercase()
+                        .strip_prefix("content-length: ")
+                        .map(|x| x.parse::<usize>().unwrap())
+                })
+                .unwrap_or(0);
+            let mut received = body.len();
+            while received < length {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+                received += read;
+            }
+
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        (url, server)
+    }
+
+    #[test]
+    fn the_webhook_is_sent_for_processing_projects() {
+        let (url, server) = webhook_server("204 No Content");
+        let delivery =
+            moderation_webhook_delivery(&config(Some(url)), project(ProjectStatus::Processing));
+
+        actix_rt::System::new("test").block_on(delivery.unwrap());
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /webhook "));
+        assert!(request.contains("\"embeds\""));
+    }
+
+    #[test]
+    fn the_webhook_is_not_sent_for_drafts() {
+        let config = config(Some("http://127.0.0.1:9/webhook".to_string()));
+
+        assert!(moderation_webhook_delivery(&config, project(ProjectStatus::Draft)).is_none());
+        assert!(moderation_webhook_delivery(&config, project(ProjectStatus::Approved)).is_none());
+    }
+
+    #[test]
+    fn the_webhook_is_not_sent_without_a_url() {
+        assert!(
+            moderation_webhook_delivery(&config(None), project(ProjectStatus::Processing))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn failing_webhooks_are_only_logged() {
+        let (url, server) = webhook_server("500 Internal Server Error");
+        let delivery =
+            moderation_webhook_delivery(&config(Some(url)), project(ProjectStatus::Processing));
+
+        // The delivery has no way to report a failure back to the request creating the
+        // project, so it finishes normally once it gives up
+        actix_rt::System::new("test").block_on(delivery.unwrap());
+
+        assert!(server.join().is_ok());
+    }
+}